@@ -0,0 +1,216 @@
+//! A safe API to compile constraint sets, and to compute and check traces
+//! against them, from Rust.
+//!
+//! ```no_run
+//! use corset::{Corset, TraceSource};
+//!
+//! let mut corset = Corset::compile(&[("adder.lisp", "(defcolumns A B C) (defconstraint sum () (vanishes! (- C (+ A B))))")])?;
+//! let report = corset.check(TraceSource::File("adder.json".as_ref()))?;
+//! for failure in report.failures.iter() {
+//!     println!("{} failed at rows {:?}", failure.handle, failure.rows);
+//! }
+//!
+//! let trace = corset.compute(TraceSource::File("adder.json".as_ref()))?;
+//! let c = trace.column("C").unwrap();
+//! println!("C[0] = {:?}", c.get_u64(0));
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+use anyhow::*;
+use num_bigint::BigUint;
+use std::path::Path;
+
+use crate::{
+    check::{self, CheckReport},
+    compiler::{self, CompileSettings, ConstraintSet},
+    compute,
+    field::Field,
+    Trace,
+};
+
+/// A compiled constraint set, fully expanded and ready to process traces
+pub struct Corset {
+    pub(crate) cs: ConstraintSet,
+}
+
+/// Where to read a trace from
+pub enum TraceSource<'a> {
+    /// a JSON or binary (`.lt`) trace file
+    File(&'a Path),
+    /// a JSON trace
+    Json(&'a [u8]),
+}
+
+impl Corset {
+    /// Compile the given `(name, content)` sources, along the standard library,
+    /// for BLS12-377
+    pub fn compile<S1: AsRef<str>, S2: AsRef<str>>(sources: &[(S1, S2)]) -> Result<Corset> {
+        Corset::compile_for(sources, Field::Bls12_377)
+    }
+
+    /// Compile the given `(name, content)` sources, along the standard library,
+    /// for `field`
+    pub fn compile_for<S1: AsRef<str>, S2: AsRef<str>>(
+        sources: &[(S1, S2)],
+        field: Field,
+    ) -> Result<Corset> {
+        let sources =
+            std::iter::once(("stdlib".to_string(), include_str!("stdlib.lisp").to_owned()))
+                .chain(
+                    sources.iter().map(|(name, content)| {
+                        (name.as_ref().to_owned(), content.as_ref().to_owned())
+                    }),
+                )
+                .collect::<Vec<_>>();
+        let (_, cs) = compiler::make(
+            &sources,
+            &CompileSettings {
+                debug: false,
+                max_errors: 1,
+                field,
+            },
+        )?;
+        Corset::from_constraint_set(cs)
+    }
+
+    /// Load a constraint set compiled with `corset compile`
+    pub fn load<P: AsRef<Path>>(filename: P) -> Result<Corset> {
+        Corset::from_constraint_set(ConstraintSet::from_file(filename)?)
+    }
+
+    /// Decode a constraint set in the binary format
+    pub fn from_bin(bytes: &[u8]) -> Result<Corset> {
+        Corset::from_constraint_set(ConstraintSet::from_bin(bytes)?)
+    }
+
+    /// The field this constraint set is defined over
+    pub fn field(&self) -> Field {
+        self.cs.field
+    }
+
+    /// Encode this constraint set in the binary format
+    pub fn to_bin(&self) -> Result<Vec<u8>> {
+        self.cs.to_bin()
+    }
+
+    pub(crate) fn from_constraint_set(mut cs: ConstraintSet) -> Result<Corset> {
+        crate::transformer::expand_to(
+            &mut cs,
+            crate::transformer::ExpansionLevel::all().into(),
+            crate::transformer::AutoConstraint::all(),
+            None,
+            false,
+            false,
+            false,
+        )?;
+        crate::transformer::concretize(&mut cs);
+        Ok(Corset { cs })
+    }
+
+    /// Import `trace` and compute all the derived columns, discarding any
+    /// previously processed trace
+    fn fill(&mut self, trace: TraceSource, fail_on_missing: bool) -> Result<()> {
+        self.cs.columns.reset();
+        match trace {
+            TraceSource::File(path) => compute::compute_trace(
+                path.to_str()
+                    .ok_or_else(|| anyhow!("invalid path {}", path.display()))?,
+                &mut self.cs,
+                fail_on_missing,
+            ),
+            TraceSource::Json(json) => {
+                compute::compute_trace_str(json, &mut self.cs, fail_on_missing)
+            }
+        }
+    }
+
+    /// Expand `trace` with all the computed columns
+    pub fn compute(&mut self, trace: TraceSource) -> Result<Trace> {
+        self.fill(trace, false)?;
+        Ok(Trace::from_constraints(&self.cs))
+    }
+
+    /// Check `trace` against the constraints, and report those that fail
+    pub fn check(&mut self, trace: TraceSource) -> Result<CheckReport> {
+        self.fill(trace, true)?;
+        check::check(&self.cs, &None, &[], check::DebugSettings::new())
+    }
+
+    /// The names of all the columns of the constraint set
+    pub fn column_names(&self) -> Vec<String> {
+        self.cs
+            .columns
+            .all()
+            .iter()
+            .map(|c| self.cs.handle(c).to_string())
+            .collect()
+    }
+
+    pub(crate) fn from_ptr<'a>(ptr: *const Corset) -> &'a Self {
+        assert!(!ptr.is_null());
+        unsafe { &*ptr }
+    }
+
+    pub(crate) fn mut_from_ptr<'a>(ptr: *mut Corset) -> &'a mut Self {
+        assert!(!ptr.is_null());
+        unsafe { &mut *ptr }
+    }
+}
+
+/// A read-only view on a column of a computed [`Trace`]
+pub struct Column<'a> {
+    padding_value: &'a [u8; 32],
+    values: &'a [[u8; 32]],
+}
+impl<'a> Column<'a> {
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The raw big-endian encoding of the values
+    pub fn bytes(&self) -> &'a [[u8; 32]] {
+        self.values
+    }
+
+    /// The value at row `i`
+    pub fn get(&self, i: usize) -> Option<BigUint> {
+        self.values.get(i).map(|x| BigUint::from_bytes_be(x))
+    }
+
+    /// The value at row `i`, if it fits in a u64
+    pub fn get_u64(&self, i: usize) -> Option<u64> {
+        self.get(i).and_then(|x| x.try_into().ok())
+    }
+
+    /// The value used to pad this column
+    pub fn padding_value(&self) -> BigUint {
+        BigUint::from_bytes_be(self.padding_value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = BigUint> + 'a {
+        self.values.iter().map(|x| BigUint::from_bytes_be(x))
+    }
+}
+
+impl Trace {
+    /// The names of the columns in this trace
+    pub fn column_names(&self) -> &[String] {
+        &self.ids
+    }
+
+    /// The column named `name`, e.g. `module.column`
+    pub fn column(&self, name: &str) -> Option<Column<'_>> {
+        self.ids
+            .iter()
+            .position(|id| id == name)
+            .and_then(|i| self.columns.get(i))
+            .filter(|c| !c.is_empty())
+            .map(|c| Column {
+                padding_value: &c.padding_value,
+                values: &c.values,
+            })
+    }
+}
@@ -0,0 +1,336 @@
+//! Static detection of the commitment columns that may not be sufficiently
+//! constrained, which is the usual source of soundness bugs.
+use num_traits::Zero;
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::column::Column;
+use crate::compiler::{
+    ColumnRef, Constraint, ConstraintSet, Expression, Intrinsic, Kind, Node, RawMagma,
+};
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(tag = "issue", rename_all = "kebab-case")]
+pub(crate) enum Issue {
+    /// the column does not appear in any constraint, even through a computed
+    /// column
+    Unconstrained,
+    /// all the occurrences of the column are multiplied by, or conditioned
+    /// on, these selectors, so that it is free whenever they are all zero
+    Guarded { selectors: Vec<String> },
+    /// the column type is narrower than the field, yet it is neither proven
+    /// nor range-checked
+    Unproven { bits: usize },
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct Warning {
+    pub column: String,
+    #[serde(flatten)]
+    pub issue: Issue,
+}
+
+/// The soundness warnings of a constraint set, per module
+#[derive(Serialize, Debug, Default)]
+pub(crate) struct AuditReport {
+    pub modules: BTreeMap<String, Vec<Warning>>,
+}
+impl AuditReport {
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+}
+impl std::fmt::Display for AuditReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (module, warnings) in self.modules.iter() {
+            writeln!(
+                f,
+                "{} {} warnings",
+                module.bold().bright_white(),
+                warnings.len().to_string().yellow().bold()
+            )?;
+            for w in warnings.iter() {
+                write!(f, "  {}: ", w.column.bold())?;
+                match &w.issue {
+                    Issue::Unconstrained => writeln!(f, "{}", "unconstrained".red())?,
+                    Issue::Guarded { selectors } => writeln!(
+                        f,
+                        "all occurrences may be cancelled by {}",
+                        selectors.join(", ").yellow()
+                    )?,
+                    Issue::Unproven { bits } => writeln!(
+                        f,
+                        "declared on {} bits, but neither proven nor range-checked",
+                        bits.yellow()
+                    )?,
+                }
+            }
+        }
+        std::fmt::Result::Ok(())
+    }
+}
+
+/// How a column is involved in the constraints
+#[derive(Default)]
+struct Usage {
+    /// whether it appears unguarded in a vanishing constraint, or in any other
+    /// kind of constraint
+    constrained: bool,
+    /// the selectors guarding its occurrences in vanishing constraints
+    guards: BTreeSet<ColumnRef>,
+    /// whether it is the subject of a range check
+    range_checked: bool,
+    /// whether it is multiplied by itself, as in a binarity constraint
+    self_guarded: bool,
+}
+
+/// Whether the type of `column`, as used in `usage`, is enforced by the
+/// constraints
+fn is_proven(column: &Column, usage: Option<&Usage>) -> bool {
+    !matches!(column.t.rm(), RawMagma::Native | RawMagma::Any)
+        && (column.must_prove
+            || usage
+                .map(|u| {
+                    u.range_checked || (u.self_guarded && matches!(column.t.rm(), RawMagma::Binary))
+                })
+                .unwrap_or(false))
+}
+
+/// Record in `usages` the columns occurring in `e`, along with the selectors
+/// (`guards`) that may cancel their contribution
+fn occurrences(e: &Node, guards: &[ColumnRef], usages: &mut HashMap<ColumnRef, Usage>) {
+    match e.e() {
+        Expression::Column { handle, .. } | Expression::ExoColumn { handle, .. } => {
+            let usage = usages.entry(handle.clone()).or_default();
+            // A column guarding itself, e.g. in a binarity constraint, is
+            // still constrained
+            if guards.contains(handle) {
+                usage.constrained = true;
+                usage.self_guarded = true;
+            } else if guards.is_empty() {
+                usage.constrained = true;
+            } else {
+                usage.guards.extend(guards.iter().cloned());
+            }
+        }
+        Expression::Funcall { func, args } => match func {
+            Intrinsic::Mul | Intrinsic::VectorMul => {
+                for (i, arg) in args.iter().enumerate() {
+                    let mut guards = guards.to_vec();
+                    for (j, other) in args.iter().enumerate() {
+                        // Non-zero constant factors can not cancel a product
+                        if i != j && other.pure_eval().map(|x| x.is_zero()).unwrap_or(true) {
+                            guards.extend(other.dependencies());
+                        }
+                    }
+                    occurrences(arg, &guards, usages);
+                }
+            }
+            // Conditionals behave as a product of their condition with their
+            // branches once expanded
+            Intrinsic::IfZero | Intrinsic::IfNotZero => {
+                let mut condition_guards = guards.to_vec();
+                condition_guards.extend(args.iter().skip(1).flat_map(|a| a.dependencies()));
+                occurrences(&args[0], &condition_guards, usages);
+
+                let mut guards = guards.to_vec();
+                guards.extend(args[0].dependencies());
+                for arg in args.iter().skip(1) {
+                    occurrences(arg, &guards, usages);
+                }
+            }
+            _ => {
+                for arg in args.iter() {
+                    occurrences(arg, guards, usages);
+                }
+            }
+        },
+        Expression::List(xs) => {
+            for x in xs.iter() {
+                occurrences(x, guards, usages);
+            }
+        }
+        Expression::Const(_) | Expression::ArrayColumn { .. } | Expression::Void => {}
+    }
+}
+
+/// For each commitment column of `cs`, check whether it appears in any
+/// constraint, whether its occurrences are all guarded by selectors that may
+/// be zero, and whether its type is proven.
+///
+/// Columns filling computed columns are considered as constrained by the
+/// constraints on the latter; lookups are considered to range-check the
+/// columns they include, provided that all the including columns are proven;
+/// and binary columns multiplied by themselves are considered to be subject to
+/// a binarity constraint.
+pub(crate) fn audit(cs: &ConstraintSet) -> AuditReport {
+    let mut usages = HashMap::<ColumnRef, Usage>::new();
+    let constrain = |r: &ColumnRef, usages: &mut HashMap<ColumnRef, Usage>| {
+        usages.entry(r.clone()).or_default().constrained = true
+    };
+
+    let mut lookups = Vec::new();
+    for c in cs.constraints.iter() {
+        match c {
+            Constraint::Vanishes { expr, .. } => occurrences(expr, &[], &mut usages),
+            Constraint::Lookup {
+                including,
+                included,
+                ..
+            } => {
+                for r in including
+                    .iter()
+                    .chain(included.iter())
+                    .flat_map(|e| e.dependencies())
+                {
+                    constrain(&r, &mut usages);
+                }
+                lookups.push((including, included));
+            }
+            Constraint::Permutation { from, to, .. } => {
+                for r in from.iter().chain(to.iter()) {
+                    constrain(r, &mut usages);
+                }
+            }
+            Constraint::InRange { exp, .. } => {
+                if let Expression::Column { handle, .. } = exp.e() {
+                    usages.entry(handle.clone()).or_default().range_checked = true;
+                }
+                for r in exp.dependencies() {
+                    constrain(&r, &mut usages);
+                }
+            }
+            Constraint::Normalization {
+                reference,
+                inverted,
+                ..
+            } => {
+                for r in reference.dependencies() {
+                    constrain(&r, &mut usages);
+                }
+                constrain(inverted, &mut usages);
+            }
+        }
+    }
+
+    // A column included in proven ones is range-checked, and may in turn
+    // prove the columns included in it
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (including, included) in lookups.iter() {
+            let including_proven = including.iter().flat_map(|e| e.dependencies()).all(|r| {
+                cs.columns
+                    .column(&r)
+                    .is_ok_and(|c| is_proven(c, usages.get(&r)))
+            });
+            if !including_proven {
+                continue;
+            }
+            for e in included.iter() {
+                if let Expression::Column { handle, .. } = e.e() {
+                    let usage = usages.entry(handle.clone()).or_default();
+                    if !usage.range_checked {
+                        usage.range_checked = true;
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    // The perspective triggers are implicitly multiplied with the constraints
+    // of their perspective, and are thus not considered as guards of its columns
+    for (module, perspectives) in cs.perspectives.iter() {
+        for (name, trigger) in perspectives.iter() {
+            let trigger = trigger.dependencies();
+            for (r, column) in cs.columns.iter_module(module) {
+                if column.handle.perspective.as_ref() == Some(name) {
+                    if let Some(usage) = usages.get_mut(&r) {
+                        usage.guards.retain(|g| !trigger.contains(g));
+                        if usage.guards.is_empty() {
+                            usage.constrained = true;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Propagate the constraints on computed columns to their sources, until
+    // a fixpoint is reached
+    let mut targets = HashMap::<usize, Vec<&ColumnRef>>::new();
+    for (target, i) in cs.computations.dependencies.iter() {
+        targets.entry(*i).or_default().push(target);
+    }
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (i, computation) in cs.computations.iter().enumerate() {
+            let outputs = targets.get(&i).cloned().unwrap_or_default();
+            let constrained = outputs
+                .iter()
+                .any(|t| usages.get(*t).map(|u| u.constrained).unwrap_or(false));
+            if !constrained {
+                continue;
+            }
+            for source in computation
+                .dependencies()
+                .into_iter()
+                .filter(|d| !outputs.contains(&d))
+            {
+                let usage = usages.entry(source).or_default();
+                if !usage.constrained {
+                    usage.constrained = true;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    let name = |r: &ColumnRef| cs.columns.column(r).unwrap().handle.name.clone();
+    let mut report = AuditReport::default();
+    for (r, column) in cs.columns.iter() {
+        if !matches!(column.kind, Kind::Commitment) {
+            continue;
+        }
+        let usage = usages.get(&r);
+        let mut issues = Vec::new();
+        match usage {
+            None => issues.push(Issue::Unconstrained),
+            Some(u) if !u.constrained && u.guards.is_empty() => issues.push(Issue::Unconstrained),
+            Some(u) if !u.constrained => issues.push(Issue::Guarded {
+                selectors: u
+                    .guards
+                    .iter()
+                    .map(name)
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .collect(),
+            }),
+            _ => {}
+        }
+        if !matches!(column.t.rm(), RawMagma::Native | RawMagma::Any) && !is_proven(column, usage) {
+            issues.push(Issue::Unproven {
+                bits: column.t.bit_size_in(cs.field),
+            });
+        }
+
+        for issue in issues {
+            report
+                .modules
+                .entry(column.handle.module.clone())
+                .or_default()
+                .push(Warning {
+                    column: column.handle.name.clone(),
+                    issue,
+                });
+        }
+    }
+    for warnings in report.modules.values_mut() {
+        warnings.sort_by(|a, b| a.column.cmp(&b.column));
+    }
+
+    report
+}
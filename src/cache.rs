@@ -0,0 +1,110 @@
+//! An on-disk cache of compiled constraint sets.
+//!
+//! Compiled constraint sets are stored under a key derived from the content
+//! of every source file and from all the settings that may alter the
+//! compilation output, so that a cached constraint set is only ever reused if
+//! the compilation would yield the exact same result.
+use anyhow::*;
+use log::*;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    compiler::ConstraintSet,
+    field::Field,
+    transformer::{AutoConstraint, ExpansionLevel},
+};
+
+pub(crate) struct CompilationCache {
+    dir: PathBuf,
+}
+impl CompilationCache {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        CompilationCache {
+            dir: dir.as_ref().to_owned(),
+        }
+    }
+
+    /// The cache in the user cache directory, if it can be found
+    pub fn in_user_dir() -> Option<Self> {
+        std::env::var("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|_| std::env::var("HOME").map(|home| Path::new(&home).join(".cache")))
+            .ok()
+            .map(|dir| CompilationCache::new(dir.join("corset")))
+    }
+
+    /// Compute the cache key of the constraint set compiled from `sources`
+    /// with the given settings
+    #[allow(clippy::too_many_arguments)]
+    pub fn key(
+        sources: &[(String, String)],
+        debug: bool,
+        field: Field,
+        expand_to: ExpansionLevel,
+        auto_constraints: &[AutoConstraint],
+        max_degree: Option<usize>,
+        cse: bool,
+        simplify: bool,
+        prune: bool,
+    ) -> String {
+        let mut key = vec![
+            env!("CARGO_PKG_VERSION").to_string(),
+            format!("debug={}", debug),
+            format!("native={}", *crate::IS_NATIVE.read().unwrap()),
+            format!("field={}", field),
+            format!("expand={}", expand_to as u8),
+            format!(
+                "auto={}",
+                auto_constraints
+                    .iter()
+                    .map(|a| (*a as u8).to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            format!("max_degree={:?}", max_degree),
+            format!("cse={}", cse),
+            format!("simplify={}", simplify),
+            format!("prune={}", prune),
+        ];
+        for (name, content) in sources.iter() {
+            key.push(format!("{}={:x}", name, md5::compute(content)));
+        }
+        format!("{:x}", md5::compute(key.join("\n")))
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.bin", key))
+    }
+
+    /// Load the constraint set stored under `key`, if any
+    pub fn load(&self, key: &str) -> Option<ConstraintSet> {
+        let path = self.path(key);
+        let content = std::fs::read(&path).ok()?;
+        match ConstraintSet::from_bin(&content) {
+            Result::Ok(cs) => {
+                info!("Loading cached constraint set {}", path.display());
+                Some(cs)
+            }
+            Err(e) => {
+                warn!("ignoring invalid cache entry {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Store `cs` under `key`
+    pub fn store(&self, key: &str, cs: &ConstraintSet) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| anyhow!("while creating {}", self.dir.display()))?;
+        let path = self.path(key);
+        // Write to a temporary file first, so that concurrent runs never see
+        // a partially written entry
+        let tmp = self.dir.join(format!("{}.{}.tmp", key, std::process::id()));
+        std::fs::write(&tmp, cs.to_bin()?)
+            .with_context(|| anyhow!("while writing {}", tmp.display()))?;
+        std::fs::rename(&tmp, &path)
+            .with_context(|| anyhow!("while writing {}", path.display()))?;
+        debug!("cached constraint set in {}", path.display());
+        Ok(())
+    }
+}
@@ -1,16 +1,25 @@
 use crate::{
-    column::{ColumnSet, Value},
-    compiler::{Constraint, ConstraintSet, Domain, EvalSettings, Expression, Node},
+    column::Value,
+    compiler::{
+        bytecode::{Access, Evaluator, Program, BATCH_SIZE},
+        Constraint, ConstraintSet, Domain, EvalSettings, Expression, Node,
+    },
     pretty::*,
     structs::Handle,
+    utils::strip_ansi,
 };
 use anyhow::*;
-use cached::SizedCache;
 use itertools::Itertools;
 use log::*;
+use num_bigint::BigInt;
 use owo_colors::OwoColorize;
+use rand::{rngs::StdRng, SeedableRng};
 use rayon::prelude::*;
-use std::collections::HashSet;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -18,8 +27,6 @@ enum CheckingError {
     #[error("columns for {} not found in trace file", .0.pretty())]
     NoColumnsFound(Handle),
     #[error("")]
-    FailingConstraint(Handle, String),
-    #[error("")]
     MismatchingLengths(Error),
 }
 
@@ -41,6 +48,12 @@ pub struct DebugSettings {
     full_trace: bool,
     /// whether to display the original source code along the compiled form
     src: bool,
+    /// how many failing rows of a constraint to describe in the report
+    witnesses: usize,
+    /// whether to display the number of checked constraints as they are processed
+    progress: bool,
+    /// which rows of the vanishing constraints to check
+    mode: CheckMode,
 }
 impl DebugSettings {
     pub fn new() -> Self {
@@ -53,6 +66,9 @@ impl DebugSettings {
             context_span_after: 2,
             full_trace: false,
             src: false,
+            witnesses: 1,
+            progress: false,
+            mode: CheckMode::Full,
         }
     }
     pub fn dim(self, x: bool) -> Self {
@@ -109,6 +125,454 @@ impl DebugSettings {
             ..self
         }
     }
+    pub fn witnesses(self, x: usize) -> Self {
+        Self {
+            witnesses: x,
+            ..self
+        }
+    }
+    pub fn progress(self, x: bool) -> Self {
+        Self {
+            progress: x,
+            ..self
+        }
+    }
+    pub fn mode(self, x: CheckMode) -> Self {
+        Self { mode: x, ..self }
+    }
+}
+
+/// Which rows of the vanishing constraints are checked; constraints defined on
+/// an explicit domain are always checked on all of it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+#[serde(tag = "mode", rename_all = "kebab-case")]
+pub enum CheckMode {
+    /// all the rows of the trace
+    #[default]
+    Full,
+    /// the rows of the given half-open window
+    Window { start: usize, end: usize },
+    /// this many random rows per constraint
+    Sample { rows: usize, seed: u64 },
+    /// the padding, the first and the last rows, far enough to cover all the
+    /// shifts in the module
+    Boundary,
+}
+impl CheckMode {
+    /// Parse a `START..END` row window
+    pub fn window(s: &str) -> Result<CheckMode> {
+        let (start, end) = s
+            .split_once("..")
+            .ok_or_else(|| anyhow!("expected a START..END window, found `{}`", s))?;
+        let start = start
+            .parse()
+            .with_context(|| anyhow!("invalid window start `{}`", start))?;
+        let end = end
+            .parse()
+            .with_context(|| anyhow!("invalid window end `{}`", end))?;
+        if start >= end {
+            bail!("empty window {}..{}", start, end)
+        }
+        Ok(CheckMode::Window { start, end })
+    }
+
+    /// The rows to check for `constraint` in a module of `len` rows,
+    /// including a padding of `spilling` rows
+    fn rows(&self, constraint: &Handle, len: usize, spilling: usize) -> Vec<isize> {
+        match self {
+            CheckMode::Full => (0..len as isize).collect(),
+            CheckMode::Window { start, end } => (*start.min(&len)..*end.min(&len))
+                .map(|i| i as isize)
+                .collect(),
+            CheckMode::Sample { rows, seed } => {
+                // Each constraint draws its own rows, so that a faulty row
+                // missed by a constraint may still be caught by another one
+                let digest = md5::compute(constraint.to_string());
+                let mut rng = StdRng::seed_from_u64(
+                    seed ^ u64::from_le_bytes(digest.0[..8].try_into().unwrap()),
+                );
+                rand::seq::index::sample(&mut rng, len, (*rows).min(len))
+                    .into_iter()
+                    .map(|i| i as isize)
+                    .sorted()
+                    .collect()
+            }
+            CheckMode::Boundary => (0..(2 * spilling + 1).min(len))
+                .chain(len.saturating_sub(spilling + 1)..len)
+                .map(|i| i as isize)
+                .sorted()
+                .dedup()
+                .collect(),
+        }
+    }
+}
+impl std::fmt::Display for CheckMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckMode::Full => write!(f, "all rows"),
+            CheckMode::Window { start, end } => write!(f, "rows {}..{}", start, end),
+            CheckMode::Sample { rows, seed } => {
+                write!(f, "{} sampled rows per constraint (seed {})", rows, seed)
+            }
+            CheckMode::Boundary => write!(f, "boundary rows"),
+        }
+    }
+}
+
+/// The outcome of checking a trace against a constraint set
+#[derive(Debug, Default, Serialize)]
+pub struct CheckReport {
+    /// which rows have been checked
+    pub mode: CheckMode,
+    /// the constraints that do not hold, sorted by handle
+    pub failures: Vec<Failure>,
+    /// how long checking each constraint and module took
+    #[serde(skip)]
+    pub profile: Profile,
+}
+impl CheckReport {
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Fail, listing the failing constraints, if the check was not successful
+    pub fn into_result(self) -> Result<()> {
+        if self.is_success() {
+            Ok(())
+        } else {
+            bail!(
+                "constraints failed{}: {}",
+                if self.mode == CheckMode::Full {
+                    String::new()
+                } else {
+                    format!(" on {}", self.mode)
+                },
+                self.failures
+                    .into_iter()
+                    .map(|f| f.handle.to_string().bold().red().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        }
+    }
+}
+
+fn millis(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.
+}
+
+/// The cost of checking a constraint
+#[derive(Debug, Serialize)]
+pub struct ConstraintProfile {
+    pub handle: Handle,
+    /// the wall time spent checking the constraint
+    pub time_ms: f64,
+    /// how many rows have been evaluated
+    pub rows: usize,
+    /// how many rows do not satisfy the constraint; a constraint failing as a
+    /// whole, e.g. on mismatching column lengths, counts as a single failure
+    pub failures: usize,
+}
+
+/// The cost of checking all the constraints of a module
+#[derive(Debug, Default, Serialize)]
+pub struct ModuleProfile {
+    pub module: String,
+    /// the wall time spent checking the constraints of the module, which are
+    /// processed in parallel
+    pub time_ms: f64,
+    pub constraints: usize,
+    pub rows: usize,
+    pub failures: usize,
+}
+
+/// How the entries of a [`Profile`] are sorted
+#[derive(Clone, Copy, Debug)]
+pub enum ProfileOrder {
+    /// slowest first
+    Time,
+    /// most evaluated rows first
+    Rows,
+    /// most failures first
+    Failures,
+    /// alphabetically
+    Name,
+}
+impl ProfileOrder {
+    pub const NAMES: [&'static str; 4] = ["time", "rows", "failures", "name"];
+
+    pub fn from_name(name: &str) -> Result<ProfileOrder> {
+        Ok(match name {
+            "time" => ProfileOrder::Time,
+            "rows" => ProfileOrder::Rows,
+            "failures" => ProfileOrder::Failures,
+            "name" => ProfileOrder::Name,
+            _ => bail!(
+                "unknown profile order `{}`; expected one of {}",
+                name,
+                ProfileOrder::NAMES.join(", ")
+            ),
+        })
+    }
+}
+
+/// Where the time has been spent while checking a trace
+#[derive(Debug, Default, Serialize)]
+pub struct Profile {
+    /// the wall time of the whole check
+    pub time_ms: f64,
+    pub modules: Vec<ModuleProfile>,
+    pub constraints: Vec<ConstraintProfile>,
+}
+impl Profile {
+    pub fn sort(&mut self, order: ProfileOrder) {
+        match order {
+            ProfileOrder::Time => {
+                self.modules.sort_by(|a, b| b.time_ms.total_cmp(&a.time_ms));
+                self.constraints
+                    .sort_by(|a, b| b.time_ms.total_cmp(&a.time_ms));
+            }
+            ProfileOrder::Rows => {
+                self.modules.sort_by_key(|x| std::cmp::Reverse(x.rows));
+                self.constraints.sort_by_key(|x| std::cmp::Reverse(x.rows));
+            }
+            ProfileOrder::Failures => {
+                self.modules.sort_by_key(|x| std::cmp::Reverse(x.failures));
+                self.constraints
+                    .sort_by_key(|x| std::cmp::Reverse(x.failures));
+            }
+            ProfileOrder::Name => {
+                self.modules.sort_by(|a, b| a.module.cmp(&b.module));
+                self.constraints.sort_by(|a, b| a.handle.cmp(&b.handle));
+            }
+        }
+    }
+}
+impl std::fmt::Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} constraints checked in {:.1}ms",
+            self.constraints.len().to_string().bold(),
+            self.time_ms
+        )?;
+
+        writeln!(f, "\n{}", "=== Modules ===".bold().yellow())?;
+        writeln!(
+            f,
+            "{:>12} {:>8} {:>12} {:>8}  Module",
+            "Time (ms)", "#", "Rows", "Failures"
+        )?;
+        for m in self.modules.iter() {
+            writeln!(
+                f,
+                "{:>12.1} {:>8} {:>12} {:>8}  {}",
+                m.time_ms,
+                m.constraints,
+                m.rows,
+                m.failures,
+                m.module.bold()
+            )?;
+        }
+
+        writeln!(f, "\n{}", "=== Constraints ===".bold().yellow())?;
+        writeln!(
+            f,
+            "{:>12} {:>12} {:>8}  Constraint",
+            "Time (ms)", "Rows", "Failures"
+        )?;
+        for c in self.constraints.iter() {
+            let handle = if c.failures > 0 {
+                c.handle.to_string().red().to_string()
+            } else {
+                c.handle.to_string()
+            };
+            writeln!(
+                f,
+                "{:>12.1} {:>12} {:>8}  {}",
+                c.time_ms, c.rows, c.failures, handle
+            )?;
+        }
+        std::fmt::Result::Ok(())
+    }
+}
+
+/// A live count of the checked constraints, displayed on stderr
+struct Progress {
+    enabled: bool,
+    total: usize,
+    done: AtomicUsize,
+}
+impl Progress {
+    fn new(total: usize, enabled: bool) -> Self {
+        Progress {
+            enabled,
+            total,
+            done: AtomicUsize::new(0),
+        }
+    }
+
+    fn tick(&self) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        if self.enabled {
+            let mut stderr = std::io::stderr().lock();
+            let _ = write!(
+                stderr,
+                "\r{} {}/{} constraints",
+                "checking".bold(),
+                done,
+                self.total
+            );
+            let _ = stderr.flush();
+        }
+    }
+
+    fn finish(&self) {
+        if self.enabled {
+            // Clear the progress line
+            eprint!("\r\x1b[2K");
+        }
+    }
+}
+
+/// A constraint that does not hold on the checked trace
+#[derive(Debug, Serialize)]
+pub struct Failure {
+    /// the failing constraint
+    pub handle: Handle,
+    /// the first row where the constraint does not hold, if any
+    pub first_row: Option<isize>,
+    /// all the rows where the constraint does not hold
+    pub rows: Vec<isize>,
+    /// the state of the trace at the first failing rows
+    pub witnesses: Vec<Witness>,
+    /// why the constraint failed, if it is not tied to specific rows
+    pub message: Option<String>,
+    /// the missing tuples, if the constraint is a lookup
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lookup: Option<LookupFailure>,
+}
+impl Failure {
+    fn new(handle: &Handle) -> Self {
+        Failure {
+            handle: handle.to_owned(),
+            first_row: None,
+            rows: Vec::new(),
+            witnesses: Vec::new(),
+            message: None,
+            lookup: None,
+        }
+    }
+
+    /// Register `rows` as failing for `exprs`, describing the first ones
+    fn add_rows(
+        &mut self,
+        cs: &ConstraintSet,
+        exprs: &[&Node],
+        rows: Vec<isize>,
+        wrap: bool,
+        settings: DebugSettings,
+    ) {
+        for &i in rows.iter() {
+            if self.witnesses.len() >= settings.witnesses {
+                break;
+            }
+            self.witnesses.push(Witness::new(cs, exprs, i, wrap));
+        }
+        self.rows.extend(rows);
+    }
+
+    /// Returns the failure, if any has been registered
+    fn done(mut self) -> Option<Self> {
+        self.rows.sort();
+        self.rows.dedup();
+        self.first_row = self.rows.first().cloned();
+        if self.rows.is_empty() && self.message.is_none() {
+            None
+        } else {
+            Some(self)
+        }
+    }
+}
+
+/// The value of an expression at a given row
+#[derive(Debug, Serialize)]
+pub struct Evaluation {
+    pub expression: String,
+    pub value: Option<String>,
+}
+
+/// The state of the trace at a row where a constraint does not hold
+#[derive(Debug, Serialize)]
+pub struct Witness {
+    pub row: isize,
+    /// the values of the failing expressions and of all their sub-expressions
+    pub expressions: Vec<Evaluation>,
+    /// the values of the columns involved in the failing expressions
+    pub columns: BTreeMap<String, Option<String>>,
+}
+impl Witness {
+    fn new(cs: &ConstraintSet, exprs: &[&Node], i: isize, wrap: bool) -> Self {
+        fn describe(n: &Node, cs: &ConstraintSet) -> String {
+            match n.e() {
+                Expression::Column { handle, shift, .. }
+                | Expression::ExoColumn { handle, shift, .. } => {
+                    if *shift != 0 {
+                        format!("(shift {} {})", cs.handle(handle), shift)
+                    } else {
+                        cs.handle(handle).to_string()
+                    }
+                }
+                Expression::Funcall { func, args } => format!(
+                    "({} {})",
+                    func,
+                    args.iter().map(|a| describe(a, cs)).join(" ")
+                ),
+                _ => strip_ansi(&n.pretty_with_handle(cs)),
+            }
+        }
+        fn evaluate(n: &Node, cs: &ConstraintSet, i: isize, wrap: bool, ax: &mut Vec<Evaluation>) {
+            let shifted = matches!(n.e(),
+                Expression::Column { shift, .. } | Expression::ExoColumn { shift, .. } if *shift != 0
+            );
+            if shifted || matches!(n.e(), Expression::Funcall { .. }) {
+                ax.push(Evaluation {
+                    expression: describe(n, cs),
+                    value: n
+                        .eval(
+                            i,
+                            |handle, i, wrap| cs.columns.get(handle, i, wrap),
+                            &mut None,
+                            &EvalSettings::new().wrap(wrap).field(cs.field),
+                        )
+                        .map(|v| v.to_string()),
+                });
+            }
+            if let Expression::Funcall { args, .. } | Expression::List(args) = n.e() {
+                for a in args.iter() {
+                    evaluate(a, cs, i, wrap, ax);
+                }
+            }
+        }
+
+        let mut expressions = Vec::new();
+        let mut columns = BTreeMap::new();
+        for e in exprs {
+            evaluate(e, cs, i, wrap, &mut expressions);
+            for h in e.dependencies() {
+                columns.insert(
+                    cs.handle(&h).to_string(),
+                    cs.columns.get(&h, i, wrap).map(|v| v.to_string()),
+                );
+            }
+        }
+
+        Witness {
+            row: i,
+            expressions,
+            columns,
+        }
+    }
 }
 
 /// Pretty print an expresion and all its intermediate value for debugging (or
@@ -120,13 +584,13 @@ impl DebugSettings {
 /// * `i`        - The evaluation point; may be negative
 /// * `wrap`     - If set, negative indices wrap; otherwise they go into the padding
 /// * `settings` - The global debugging settings
-fn fail(
+fn failure_report(
     cs: &ConstraintSet,
     expr: &Node,
     i: isize,
     wrap: bool,
     settings: DebugSettings,
-) -> Result<()> {
+) -> String {
     let handles = if settings.full_trace {
         let module = &cs
             .handle(
@@ -209,144 +673,213 @@ fn fail(
     }
     trace.push('\n');
 
-    bail!(
-        trace
-            + &expr.debug(
-                &|n| n.eval(
+    trace
+        + &expr.debug(
+            &|n| {
+                n.eval(
                     i,
                     |handle, i, wrap| cs.columns.get(handle, i, wrap),
                     &mut None,
-                    &Default::default(),
-                ),
-                settings.unclutter,
-                settings.dim,
-                settings.src,
-            )
-    )
+                    &EvalSettings::new().field(cs.field),
+                )
+            },
+            settings.unclutter,
+            settings.dim,
+            settings.src,
+        )
 }
 
-fn check_constraint_at(
-    cs: &ConstraintSet,
+/// Returns the rows where `expr` does not reduce to zero
+fn check_inrange(
     expr: &Node,
-    i: isize,
-    wrap: bool,
-    fail_on_oob: bool,
-    cache: &mut Option<SizedCache<Value, Value>>,
+    cs: &ConstraintSet,
+    max: &Value,
+    handle: &Handle,
     settings: DebugSettings,
-) -> Result<()> {
-    let r = expr.eval(
-        i,
-        |handle, i, wrap| cs.columns.get_raw(handle, i, wrap),
-        cache,
-        &EvalSettings::new().wrap(wrap),
-    );
-    if let Some(r) = r {
-        if !r.is_zero() {
-            return fail(cs, expr, i, wrap, settings);
-        }
-    } else if fail_on_oob {
-        return fail(cs, expr, i, wrap, settings);
-    }
-    Ok(())
-}
-
-fn check_inrange(expr: &Node, cs: &ConstraintSet, max: &Value) -> Result<()> {
-    let l = cs.dependencies_len(expr, false)?;
-    if let Some(l) = l {
+) -> Result<Vec<isize>> {
+    let mut failing = Vec::new();
+    if let Some(l) = cs.dependencies_len(expr, false)? {
         for i in 0..l as isize {
             let r = expr
                 .eval(
                     i,
                     |handle, i, wrap| cs.columns.get_raw(handle, i, wrap),
                     &mut None,
-                    &Default::default(),
+                    &EvalSettings::new().field(cs.field),
                 )
                 .unwrap();
             if r.ge(max) {
-                bail!(
-                    "{} = {} > {}",
-                    expr.to_string().white().bold(),
-                    r.pretty().red().bold(),
-                    max.pretty().blue()
-                )
+                if settings.report && (failing.is_empty() || settings.continue_on_error) {
+                    println!(
+                        "{} failed:\n{} = {} > {}\n",
+                        handle.to_string().red().bold(),
+                        expr.to_string().white().bold(),
+                        r.pretty().red().bold(),
+                        max.pretty().blue()
+                    );
+                }
+                failing.push(i);
+            }
+        }
+    }
+    Ok(failing)
+}
+
+/// Returns, among `rows`, those where `expr` does not reduce to zero
+fn check_constraint_at(
+    cs: &ConstraintSet,
+    expr: &Node,
+    rows: impl Iterator<Item = isize>,
+    wrap: bool,
+    fail_on_oob: bool,
+    name: &Handle,
+    settings: DebugSettings,
+) -> Result<Vec<isize>> {
+    let program = Program::compile(expr)?;
+    let mut evaluator = Evaluator::new(&program, &cs.columns, Access::Raw { wrap }, cs.field);
+    let mut failing = Vec::new();
+    for batch in &rows.chunks(BATCH_SIZE) {
+        let batch = batch.collect::<Vec<_>>();
+        let values = evaluator.eval(&batch);
+        for (&i, r) in batch.iter().zip(values.iter()) {
+            let fails = r.as_ref().map(|r| !r.is_zero()).unwrap_or(fail_on_oob);
+            if fails {
+                if settings.report && (failing.is_empty() || settings.continue_on_error) {
+                    println!(
+                        "{} failed:\n{}\n",
+                        name.to_string().red().bold(),
+                        failure_report(cs, expr, i, wrap, settings)
+                    );
+                }
+                failing.push(i);
             }
         }
-        Ok(())
-    } else {
-        Ok(())
     }
+    Ok(failing)
+}
+
+/// The spilling of the module of `expr`
+fn spilling_of_expr(cs: &ConstraintSet, expr: &Node) -> usize {
+    cs.module_of_expr(expr)
+        .and_then(|m| cs.spilling_of(&m))
+        .unwrap_or_default() as usize
 }
 
+/// Returns the rows where `expr` does not reduce to zero, and whether
+/// these rows wrap around the trace
 fn check_constraint(
     cs: &ConstraintSet,
     expr: &Node,
     domain: &Option<Domain<isize>>,
     name: &Handle,
     settings: DebugSettings,
-) -> Result<()> {
+) -> Result<(Vec<isize>, bool)> {
     let l = cs
         .dependencies_len(expr, true)
         .map_err(CheckingError::MismatchingLengths)?;
     if let Some(l) = l {
-        let mut cache = Some(cached::SizedCache::with_size(200000)); // ~1.60MB cache
-        match domain {
-            Some(is) => {
-                for i in is.iter() {
-                    check_constraint_at(cs, expr, i, true, true, &mut cache, settings)?;
-                }
-            }
-            None => {
-                for i in 0..l as isize {
-                    let err = check_constraint_at(cs, expr, i, false, false, &mut cache, settings)
-                        .map_err(|e| CheckingError::FailingConstraint(name.clone(), e.to_string()));
-
-                    if err.is_err() {
-                        if settings.continue_on_error {
-                            eprintln!("{:?}", err);
-                        } else {
-                            bail!(err.err().unwrap());
-                        }
-                    }
-                }
-            }
+        let (failing, wrap) = match domain {
+            Some(is) => (
+                check_constraint_at(cs, expr, is.iter(), true, true, name, settings)?,
+                true,
+            ),
+            None => (
+                check_constraint_at(
+                    cs,
+                    expr,
+                    settings
+                        .mode
+                        .rows(name, l, spilling_of_expr(cs, expr))
+                        .into_iter(),
+                    false,
+                    false,
+                    name,
+                    settings,
+                )?,
+                false,
+            ),
         };
-        info!("{} validated", name.pretty());
-        Ok(())
+        if failing.is_empty() {
+            info!("{} validated", name.pretty());
+        }
+        Ok((failing, wrap))
     } else {
         bail!(CheckingError::NoColumnsFound(name.clone()))
     }
 }
 
+/// How many of the closest parent tuples to report for a missing child tuple
+const CLOSEST_PARENTS: usize = 3;
+
+/// A parent tuple close to a missing child tuple
+#[derive(Debug, Serialize)]
+pub struct ClosestTuple {
+    /// the first parent row holding this tuple
+    pub row: isize,
+    pub tuple: Vec<String>,
+    /// how many coordinates this tuple shares with the child tuple
+    pub matching: usize,
+}
+
+/// A child row of a lookup whose tuple can not be found in the parents
+#[derive(Debug, Serialize)]
+pub struct MissingTuple {
+    pub row: isize,
+    pub tuple: Vec<String>,
+    /// the parent tuples sharing the most coordinates with `tuple`
+    pub closest: Vec<ClosestTuple>,
+}
+
+/// The details of a failing lookup
+#[derive(Debug, Serialize)]
+pub struct LookupFailure {
+    pub parents: Vec<String>,
+    pub children: Vec<String>,
+    /// how many distinct child tuples are missing from the parents
+    pub distinct_missing: usize,
+    /// the first failing child rows
+    pub missing: Vec<MissingTuple>,
+}
+
+/// The tuple formed by `exps` at row `i`
+fn lookup_tuple(exps: &[Node], i: isize, cs: &ConstraintSet) -> Vec<Value> {
+    exps.iter()
+        .map(|exp| {
+            exp.eval(
+                i,
+                |handle, j, _| {
+                    cs.columns.get(handle, j, false).or_else(|| {
+                        cs.columns
+                            .column(handle)
+                            .unwrap()
+                            .padding_value
+                            .as_ref()
+                            .cloned()
+                    })
+                },
+                &mut None,
+                &EvalSettings::new().field(cs.field),
+            )
+            .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// The representation-independent form of a lookup tuple, as the same value
+/// may be held either natively or as a big integer
+fn tuple_key(tuple: &[Value]) -> Vec<BigInt> {
+    tuple.iter().map(Value::to_bi).collect()
+}
+
+/// Returns the rows of the children that can not be found in the parents,
+/// along with the details of the missing tuples
 fn check_lookup(
     cs: &ConstraintSet,
     handle: &Handle,
     parents: &[Node],
     children: &[Node],
-) -> Result<()> {
-    // Compute the LC \sum_k (k+1) × x_k[i]
-    fn pseudo_rlc(exps: &[Node], i: usize, cs: &ColumnSet) -> Value {
-        let mut ax = Value::zero();
-
-        for (j, exp) in exps.iter().enumerate() {
-            let mut x = Value::from(j + 2);
-            let col_value = exp
-                .eval(
-                    i as isize,
-                    |handle, j, _| {
-                        cs.get(handle, j, false)
-                            .or_else(|| cs.column(handle).unwrap().padding_value.as_ref().cloned())
-                    },
-                    &mut None,
-                    &EvalSettings::default(),
-                )
-                .unwrap_or_default();
-
-            x.mul_assign(&col_value);
-            ax.add_assign(&x);
-        }
-        ax
-    }
-
+    settings: DebugSettings,
+) -> Result<(Vec<isize>, Option<LookupFailure>)> {
     // Check that we have the same number of columns; should be guaranteed by the com
     if children.len() != parents.len() {
         bail!("parents and children are not of the same length")
@@ -366,7 +899,7 @@ fn check_lookup(
     match (children_empty, parent_empty) {
         (true, true) | (true, false) => {
             warn!("skipping empty lookup {}", handle.pretty());
-            return Ok(());
+            return Ok((Vec::new(), None));
         }
         (false, true) => bail!(
             "parents ({}) are empty, but not children",
@@ -381,57 +914,224 @@ fn check_lookup(
     let child_module = cs.module_of_exprs(children).unwrap();
     let child_len = cs.iter_len(&child_module);
 
-    let parent_hashes: HashSet<_> = (0..parent_len)
-        .map(|i| pseudo_rlc(parents, i, &cs.columns))
-        .collect();
+    // Map each distinct parent tuple to the first row holding it
+    let mut parent_tuples = HashMap::new();
+    for i in 0..parent_len as isize {
+        parent_tuples
+            .entry(tuple_key(&lookup_tuple(parents, i, cs)))
+            .or_insert(i);
+    }
 
-    for i in 0..child_len {
-        if !parent_hashes.contains(&pseudo_rlc(children, i, &cs.columns)) {
-            let pretty_expected_matches = parents
+    let mut failing = Vec::new();
+    let mut missing_tuples = HashSet::new();
+    let mut missing = Vec::new();
+    for i in 0..child_len as isize {
+        let tuple = lookup_tuple(children, i, cs);
+        let key = tuple_key(&tuple);
+        if parent_tuples.contains_key(&key) {
+            continue;
+        }
+        failing.push(i);
+        if missing.len() < settings.witnesses.max(1) {
+            let mut closest = parent_tuples
                 .iter()
-                .zip(children.iter().zip(children.iter().map(|e| {
-                    e.eval(
-                        i as isize,
-                        |handle, j, _| {
-                            cs.columns.get(handle, j, false).or_else(|| {
-                                cs.columns
-                                    .column(handle)
-                                    .unwrap()
-                                    .padding_value
-                                    .as_ref()
-                                    .cloned()
-                            })
-                        },
-                        &mut None,
-                        &EvalSettings::default(),
-                    )
-                    .unwrap_or_default()
-                })))
-                .map(|(parent, (child, value))| {
-                    format!(
-                        "{} - {}: {}",
-                        parent.pretty(),
-                        child.pretty(),
-                        value.pretty_with_base(Base::Hex)
+                .map(|(parent, row)| {
+                    (
+                        parent
+                            .iter()
+                            .zip(key.iter())
+                            .filter(|(p, c)| p == c)
+                            .count(),
+                        *row,
+                        parent,
                     )
                 })
-                .join("\n");
-            bail!("mismatch line {}:\n{}", i, pretty_expected_matches);
+                .collect::<Vec<_>>();
+            closest.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+            missing.push(MissingTuple {
+                row: i,
+                tuple: tuple.iter().map(|x| x.to_string()).collect(),
+                closest: closest
+                    .into_iter()
+                    .filter(|(matching, ..)| *matching > 0)
+                    .take(CLOSEST_PARENTS)
+                    .map(|(matching, row, parent)| ClosestTuple {
+                        row,
+                        tuple: parent.iter().map(|x| x.to_string()).collect(),
+                        matching,
+                    })
+                    .collect(),
+            });
+        }
+        missing_tuples.insert(key);
+    }
+
+    if failing.is_empty() {
+        return Ok((failing, None));
+    }
+
+    let failure = LookupFailure {
+        parents: parents.iter().map(|p| p.to_string()).collect(),
+        children: children.iter().map(|c| c.to_string()).collect(),
+        distinct_missing: missing_tuples.len(),
+        missing,
+    };
+    if settings.report {
+        println!(
+            "{} failed: {} rows, {} distinct tuples missing from ({})",
+            handle,
+            failing.len(),
+            failure.distinct_missing,
+            failure.parents.join(", ")
+        );
+        for m in failure.missing.iter() {
+            println!("  line {}: ({})", m.row, m.tuple.join(", "));
+            for c in m.closest.iter() {
+                println!(
+                    "    closest: line {}, ({}), {}/{} matching",
+                    c.row,
+                    c.tuple.join(", "),
+                    c.matching,
+                    children.len()
+                );
+            }
+        }
+        println!();
+    }
+
+    Ok((failing, Some(failure)))
+}
+
+/// Check a single constraint, returning its failure if it does not hold
+fn check_one(cs: &ConstraintSet, c: &Constraint, settings: DebugSettings) -> Option<Failure> {
+    match c {
+        Constraint::Vanishes {
+            handle: name,
+            domain,
+            expr,
+        } => {
+            if matches!(expr.e(), Expression::Void) {
+                return None;
+            }
+
+            let mut failure = Failure::new(name);
+            let exprs = match expr.as_ref().e() {
+                Expression::List(es) => es.iter().collect::<Vec<_>>(),
+                _ => vec![expr.as_ref()],
+            };
+            for e in exprs {
+                match check_constraint(cs, e, domain, name, settings) {
+                    Result::Ok((rows, wrap)) => failure.add_rows(cs, &[e], rows, wrap, settings),
+                    Err(err) => match err.downcast_ref::<CheckingError>() {
+                        Some(CheckingError::MismatchingLengths(err)) => {
+                            error!("{err}");
+                            failure.message = Some(strip_ansi(&err.to_string()));
+                            break;
+                        }
+                        _ => {
+                            warn!("{}", err);
+                            break;
+                        }
+                    },
+                }
+            }
+            failure.done()
+        }
+        Constraint::Lookup {
+            handle,
+            including,
+            included,
+        } => {
+            let mut failure = Failure::new(handle);
+            match check_lookup(cs, handle, including, included, settings) {
+                Result::Ok((rows, lookup)) => {
+                    failure.add_rows(
+                        cs,
+                        &included.iter().collect::<Vec<_>>(),
+                        rows,
+                        false,
+                        settings,
+                    );
+                    failure.lookup = lookup;
+                }
+                Err(err) => {
+                    if settings.report {
+                        println!("{} failed:\n{:?}\n", handle, err);
+                    }
+                    failure.message = Some(strip_ansi(&err.to_string()));
+                }
+            }
+            failure.done()
+        }
+        Constraint::Permutation {
+            handle: _name,
+            from: _from,
+            to: _to,
+            ..
+        } => {
+            // warn!("Permutation validation not yet implemented");
+            None
+        }
+        Constraint::InRange { handle, exp, max } => {
+            let mut failure = Failure::new(handle);
+            match check_inrange(exp, cs, max, handle, settings) {
+                Result::Ok(rows) => failure.add_rows(cs, &[exp], rows, false, settings),
+                Err(err) => {
+                    if settings.report {
+                        println!("{} failed:\n{:?}\n", handle, err);
+                    }
+                    failure.message = Some(strip_ansi(&err.to_string()));
+                }
+            }
+            failure.done()
+        }
+        Constraint::Normalization { .. } => {
+            // We trust ourselves
+            None
         }
     }
+}
 
-    Ok(())
+/// How many rows have to be evaluated to check `c`
+fn rows_evaluated(cs: &ConstraintSet, c: &Constraint, mode: CheckMode) -> usize {
+    match c {
+        Constraint::Vanishes { domain, expr, .. } => match domain {
+            Some(domain) => domain.iter().count(),
+            None => match cs.dependencies_len(expr, true).ok().flatten() {
+                Some(l) if mode == CheckMode::Full => l,
+                Some(l) => mode.rows(c.handle(), l, spilling_of_expr(cs, expr)).len(),
+                None => 0,
+            },
+        },
+        Constraint::Lookup {
+            including,
+            included,
+            ..
+        } => [including, included]
+            .into_iter()
+            .filter_map(|exps| cs.module_of_exprs(exps))
+            .map(|module| cs.iter_len(&module))
+            .sum(),
+        Constraint::InRange { exp, .. } => cs
+            .dependencies_len(exp, false)
+            .ok()
+            .flatten()
+            .unwrap_or_default(),
+        Constraint::Permutation { .. } | Constraint::Normalization { .. } => 0,
+    }
 }
 
+/// Check the trace filled in `cs` against its constraints, and report on those
+/// that do not hold
 pub fn check(
     cs: &ConstraintSet,
     only: &Option<Vec<String>>,
     skip: &[String],
     settings: DebugSettings,
-) -> Result<()> {
+) -> Result<CheckReport> {
     if cs.columns.is_empty() {
         info!("Skipping empty trace");
-        return Ok(());
+        return Ok(CheckReport::default());
     }
 
     let todo = cs
@@ -444,136 +1144,66 @@ pub fn check(
         bail!("refusing to check an empty constraint set")
     }
 
-    let failed = todo
-        .par_iter()
-        .filter_map(|c| {
-            match c {
-                Constraint::Vanishes {
-                    handle: name,
-                    domain,
-                    expr,
-                } => {
-                    if matches!(expr.e(), Expression::Void) {
-                        return None;
-                    }
-
-                    match expr.as_ref().e() {
-                        Expression::List(es) => {
-                            for e in es {
-                                if let Err(err) = check_constraint(cs, e, domain, name, settings) {
-                                    match err.downcast_ref::<CheckingError>() {
-                                        Some(err) => match err {
-                                            CheckingError::NoColumnsFound(_) => {
-                                                warn!("{}", err);
-                                                break;
-                                            }
-                                            CheckingError::FailingConstraint(handle, trace) => {
-                                                if settings.report {
-                                                    println!(
-                                                        "{} failed:\n{}\n",
-                                                        handle.to_string().red().bold(),
-                                                        trace
-                                                    );
-                                                }
-                                                return Some(name.to_owned());
-                                            }
-                                            CheckingError::MismatchingLengths(err) => {
-                                                error!("{err}");
-                                                return Some(name.to_owned());
-                                            }
-                                        },
-                                        None => {
-                                            warn!("{}", err);
-                                            break;
-                                        }
-                                    }
-                                }
-                            }
-                            None
-                        }
-                        _ => {
-                            if let Err(err) = check_constraint(cs, expr, domain, name, settings) {
-                                match err.downcast_ref::<CheckingError>() {
-                                    Some(CheckingError::NoColumnsFound(_)) => {
-                                        warn!("{}", err);
-                                        None
-                                    }
-                                    Some(CheckingError::FailingConstraint(handle, trace)) => {
-                                        if settings.report {
-                                            println!(
-                                                "{} failed:\n{}\n",
-                                                handle.to_string().red().bold(),
-                                                trace
-                                            );
-                                        }
-                                        Some(name.to_owned())
-                                    }
-                                    Some(CheckingError::MismatchingLengths(err)) => {
-                                        error!("{err}");
-                                        return Some(name.to_owned());
-                                    }
-                                    None => {
-                                        warn!("{}", err);
-                                        None
-                                    }
-                                }
-                            } else {
-                                None
-                            }
-                        }
-                    }
-                }
-                Constraint::Lookup {
-                    handle,
-                    including,
-                    included,
-                } => {
-                    if let Err(trace) = check_lookup(cs, handle, including, included) {
-                        if settings.report {
-                            println!("{} failed:\n{:?}\n", handle, trace);
-                        }
-                        Some(handle.to_owned())
-                    } else {
-                        None
-                    }
-                }
-                Constraint::Permutation {
-                    handle: _name,
-                    from: _from,
-                    to: _to,
-                    ..
-                } => {
-                    // warn!("Permutation validation not yet implemented");
-                    None
-                }
-                Constraint::InRange { handle, exp, max } => {
-                    if let Err(trace) = check_inrange(exp, &cs, max) {
-                        if settings.report {
-                            println!("{} failed:\n{:?}\n", handle, trace);
-                        }
-                        Some(handle.to_owned())
-                    } else {
-                        None
-                    }
-                }
-                Constraint::Normalization { .. } => {
-                    // We trust ourselves
-                    None
-                }
-            }
+    let start = Instant::now();
+    let progress = Progress::new(todo.len(), settings.progress);
+    // Modules are checked in parallel, as well as the constraints within each
+    // of them
+    let by_module = todo
+        .into_iter()
+        .into_group_map_by(|c| c.handle().module.clone());
+    let checked = by_module
+        .into_par_iter()
+        .map(|(module, constraints)| {
+            let start = Instant::now();
+            let checked = constraints
+                .par_iter()
+                .map(|c| {
+                    let start = Instant::now();
+                    let failure = check_one(cs, c, settings);
+                    let profile = ConstraintProfile {
+                        handle: c.handle().to_owned(),
+                        time_ms: millis(start.elapsed()),
+                        rows: rows_evaluated(cs, c, settings.mode),
+                        failures: failure.as_ref().map(|f| f.rows.len().max(1)).unwrap_or(0),
+                    };
+                    progress.tick();
+                    (failure, profile)
+                })
+                .collect::<Vec<_>>();
+            (module, millis(start.elapsed()), checked)
         })
-        .collect::<HashSet<_>>();
-    if failed.is_empty() {
+        .collect::<Vec<_>>();
+    progress.finish();
+
+    let mut failures = Vec::new();
+    let mut profile = Profile {
+        time_ms: millis(start.elapsed()),
+        ..Default::default()
+    };
+    for (module, time_ms, checked) in checked {
+        let mut module = ModuleProfile {
+            module,
+            time_ms,
+            ..Default::default()
+        };
+        for (failure, constraint) in checked {
+            module.constraints += 1;
+            module.rows += constraint.rows;
+            module.failures += constraint.failures;
+            profile.constraints.push(constraint);
+            failures.extend(failure);
+        }
+        profile.modules.push(module);
+    }
+    failures.sort_by(|a, b| a.handle.cmp(&b.handle));
+    profile.sort(ProfileOrder::Name);
+
+    if failures.is_empty() {
         info!("Validation successful");
-        Ok(())
-    } else {
-        bail!(
-            "constraints failed: {}",
-            failed
-                .into_iter()
-                .map(|x| x.to_string().bold().red().to_string())
-                .collect::<Vec<_>>()
-                .join(", ")
-        )
     }
+    Ok(CheckReport {
+        mode: settings.mode,
+        failures,
+        profile,
+    })
 }
@@ -1,12 +1,13 @@
 use crate::{
     compiler::{ColumnRef, EvalSettings, Intrinsic, Kind, Magma, Node},
     constants, errors,
+    field::Field,
     pretty::{opcodes, Base, Pretty},
     structs::Handle,
 };
 use anyhow::*;
 use ark_bls12_377::fr::Fr;
-use ark_ff::{fields::Field, BigInteger, PrimeField};
+use ark_ff::{fields::Field as _, BigInteger, PrimeField};
 use itertools::Itertools;
 use num_bigint::{BigInt, Sign};
 use num_traits::{Euclid, FromPrimitive, Num, One, ToPrimitive, Zero};
@@ -35,6 +36,37 @@ fn clamp_bi(bi: &mut BigInt) {
     assert!(bi.sign() != Sign::Minus);
 }
 
+/// Whether values are computed natively as arkworks field elements
+fn is_ark_native() -> bool {
+    *crate::IS_NATIVE.read().unwrap()
+}
+
+/// The bit size of the arkworks field elements
+fn ark_bitsize() -> usize {
+    Field::Bls12_377.bit_size()
+}
+
+/// Integers are always reduced in the fields not backed by arkworks
+fn reduce(bi: &mut BigInt, field: Field) {
+    if !field.is_ark() {
+        *bi = bi.rem_euclid(field.modulus());
+    }
+}
+
+/// Reduce `bi` in the non-arkworks `field`; integers too large to fit in a
+/// field element are kept as is, to be later split in limbs
+fn reduce_exo(bi: &mut BigInt, field: Field) {
+    if bi.sign() == Sign::Minus || bi.bits() as usize <= field.bit_size() {
+        *bi = bi.rem_euclid(field.modulus());
+    } else {
+        clamp_bi(bi);
+    }
+}
+
+#[allow(
+    clippy::derive_ord_xor_partial_ord,
+    clippy::derived_hash_with_manual_eq
+)]
 #[derive(Debug, Clone, Eq, Ord, Hash, Serialize, Deserialize)]
 pub enum Value {
     BigInt(BigInt),
@@ -83,7 +115,7 @@ impl Value {
     }
 
     pub(crate) fn zero() -> Self {
-        if *crate::IS_NATIVE.read().unwrap() {
+        if is_ark_native() {
             Value::Native(Fr::zero())
         } else {
             Value::BigInt(BigInt::zero())
@@ -91,20 +123,38 @@ impl Value {
     }
 
     pub(crate) fn one() -> Self {
-        if *crate::IS_NATIVE.read().unwrap() {
+        if is_ark_native() {
             Value::Native(Fr::one())
         } else {
             Value::BigInt(BigInt::one())
         }
     }
 
-    pub(crate) fn add_assign(&mut self, other: &Value) {
+    /// Apply `op` to operands of different representations, once both are
+    /// brought to the one used in `field`
+    fn mixed(&mut self, other: &Value, field: Field, op: fn(&mut Value, &Value, Field)) {
+        if field.is_ark() {
+            self.to_native();
+            op(self, &other.clone().into_native(), field)
+        } else {
+            *self = Value::BigInt(self.to_bi());
+            op(self, &Value::BigInt(other.to_bi()), field)
+        }
+    }
+
+    pub(crate) fn add_assign(&mut self, other: &Value, field: Field) {
         match (self, other) {
-            (Value::BigInt(ref mut i1), Value::BigInt(ref i2)) => *i1 += i2,
-            (Value::BigInt(_), Value::Native(_)) => todo!(),
+            (Value::BigInt(ref mut i1), Value::BigInt(ref i2)) => {
+                *i1 += i2;
+                reduce(i1, field)
+            }
+            (Value::Native(ref mut f1), Value::Native(ref f2)) if field.is_ark() => {
+                f1.add_assign(f2)
+            }
+            (x @ (Value::BigInt(_) | Value::Native(_)), Value::BigInt(_) | Value::Native(_)) => {
+                x.mixed(other, field, Value::add_assign)
+            }
             (Value::BigInt(_), Value::ExoNative(_)) => todo!(),
-            (Value::Native(_), Value::BigInt(_)) => todo!(),
-            (Value::Native(ref mut f1), Value::Native(ref f2)) => f1.add_assign(f2),
             (Value::Native(_), Value::ExoNative(_)) => todo!(),
             (Value::ExoNative(_), Value::BigInt(_)) => todo!(),
             (Value::ExoNative(_), Value::Native(_)) => todo!(),
@@ -112,13 +162,19 @@ impl Value {
         }
     }
 
-    pub(crate) fn sub_assign(&mut self, other: &Value) {
+    pub(crate) fn sub_assign(&mut self, other: &Value, field: Field) {
         match (self, other) {
-            (Value::BigInt(ref mut i1), Value::BigInt(ref i2)) => *i1 -= i2,
-            (Value::BigInt(i), Value::Native(n)) => todo!("{} -= {}", i, n),
+            (Value::BigInt(ref mut i1), Value::BigInt(ref i2)) => {
+                *i1 -= i2;
+                reduce(i1, field)
+            }
+            (Value::Native(ref mut f1), Value::Native(ref f2)) if field.is_ark() => {
+                f1.sub_assign(f2)
+            }
+            (x @ (Value::BigInt(_) | Value::Native(_)), Value::BigInt(_) | Value::Native(_)) => {
+                x.mixed(other, field, Value::sub_assign)
+            }
             (Value::BigInt(_), Value::ExoNative(_)) => todo!(),
-            (Value::Native(_), Value::BigInt(_)) => todo!(),
-            (Value::Native(ref mut f1), Value::Native(ref f2)) => f1.sub_assign(f2),
             (Value::Native(_), Value::ExoNative(_)) => todo!(),
             (Value::ExoNative(_), Value::BigInt(_)) => todo!(),
             (Value::ExoNative(_), Value::Native(_)) => todo!(),
@@ -126,13 +182,19 @@ impl Value {
         }
     }
 
-    pub(crate) fn mul_assign(&mut self, other: &Value) {
+    pub(crate) fn mul_assign(&mut self, other: &Value, field: Field) {
         match (self, other) {
-            (Value::BigInt(ref mut i1), Value::BigInt(ref i2)) => *i1 *= i2,
-            (Value::BigInt(x), Value::Native(y)) => todo!("{} *= {}", x, y),
+            (Value::BigInt(ref mut i1), Value::BigInt(ref i2)) => {
+                *i1 *= i2;
+                reduce(i1, field)
+            }
+            (Value::Native(ref mut f1), Value::Native(ref f2)) if field.is_ark() => {
+                f1.mul_assign(f2)
+            }
+            (x @ (Value::BigInt(_) | Value::Native(_)), Value::BigInt(_) | Value::Native(_)) => {
+                x.mixed(other, field, Value::mul_assign)
+            }
             (Value::BigInt(_), Value::ExoNative(_)) => todo!(),
-            (Value::Native(_), Value::BigInt(_)) => todo!(),
-            (Value::Native(ref mut f1), Value::Native(ref f2)) => f1.mul_assign(f2),
             (Value::Native(_), Value::ExoNative(_)) => todo!(),
             (Value::ExoNative(_), Value::BigInt(_)) => todo!(),
             (Value::ExoNative(_), Value::Native(_)) => todo!(),
@@ -140,72 +202,61 @@ impl Value {
         }
     }
 
-    pub(crate) fn negate(&mut self) {
+    pub(crate) fn negate(&mut self, field: Field) {
         let mut ax = Value::zero().same_as(self);
-        ax.sub_assign(self);
+        ax.sub_assign(self, field);
         *self = ax;
     }
 
-    pub(crate) fn vector_add_assign(&mut self, other: &Value) {
+    pub(crate) fn vector_add_assign(&mut self, other: &Value, field: Field) {
         match (self, other) {
-            (Value::BigInt(ref mut i1), Value::BigInt(ref i2)) => *i1 += i2,
-            (Value::BigInt(_), Value::Native(_)) => todo!(),
-            (Value::BigInt(_), Value::ExoNative(_)) => todo!(),
-            (Value::Native(_), Value::BigInt(_)) => todo!(),
-            (Value::Native(ref mut f1), Value::Native(ref f2)) => f1.add_assign(f2),
-            (Value::Native(_), Value::ExoNative(_)) => todo!(),
-            (Value::ExoNative(_), Value::BigInt(_)) => todo!(),
-            (Value::ExoNative(_), Value::Native(_)) => todo!(),
             (Value::ExoNative(f1s), Value::ExoNative(f2s)) => f1s
                 .iter_mut()
                 .zip(f2s.iter())
                 .for_each(|(f1, f2)| f1.add_assign(f2)),
+            (x, y) => x.add_assign(y, field),
         }
     }
 
-    pub(crate) fn vector_sub_assign(&mut self, other: &Value) {
+    pub(crate) fn vector_sub_assign(&mut self, other: &Value, field: Field) {
         match (self, other) {
-            (Value::BigInt(ref mut i1), Value::BigInt(ref i2)) => *i1 -= i2,
-            (Value::BigInt(_), Value::Native(_)) => todo!(),
-            (Value::BigInt(_), Value::ExoNative(_)) => todo!(),
-            (Value::Native(_), Value::BigInt(_)) => todo!(),
-            (Value::Native(ref mut f1), Value::Native(ref f2)) => f1.sub_assign(f2),
-            (Value::Native(_), Value::ExoNative(_)) => todo!(),
-            (Value::ExoNative(_), Value::BigInt(_)) => todo!(),
-            (Value::ExoNative(_), Value::Native(_)) => todo!(),
             (Value::ExoNative(f1s), Value::ExoNative(f2s)) => f1s
                 .iter_mut()
                 .zip(f2s.iter())
                 .for_each(|(f1, f2)| f1.sub_assign(f2)),
+            (x, y) => x.sub_assign(y, field),
         }
     }
 
-    pub(crate) fn vector_mul_assign(&mut self, other: &Value) {
+    pub(crate) fn vector_mul_assign(&mut self, other: &Value, field: Field) {
         match (self, other) {
-            (Value::BigInt(ref mut i1), Value::BigInt(ref i2)) => *i1 *= i2,
-            (Value::BigInt(_), Value::Native(_)) => todo!(),
-            (Value::BigInt(_), Value::ExoNative(_)) => todo!(),
-            (Value::Native(_), Value::BigInt(_)) => todo!(),
-            (Value::Native(ref mut f1), Value::Native(ref f2)) => f1.mul_assign(f2),
-            (Value::Native(_), Value::ExoNative(_)) => todo!(),
-            (Value::ExoNative(_), Value::BigInt(_)) => todo!(),
-            (Value::ExoNative(_), Value::Native(_)) => todo!(),
             (Value::ExoNative(f1s), Value::ExoNative(f2s)) => f1s
                 .iter_mut()
                 .zip(f2s.iter())
                 .for_each(|(f1, f2)| f1.mul_assign(f2)),
+            (x, y) => x.mul_assign(y, field),
         }
     }
 
-    pub(crate) fn inverse(&self) -> Value {
+    pub(crate) fn inverse(&self, field: Field) -> Value {
         match &self {
-            Value::Native(f) => Value::Native(f.inverse().unwrap_or_else(Fr::zero)),
+            Value::Native(f) if field.is_ark() => {
+                Value::Native(f.inverse().unwrap_or_else(Fr::zero))
+            }
             Value::ExoNative(fs) => Value::ExoNative(
                 fs.iter()
                     .map(|f| f.inverse().unwrap_or_else(Fr::zero))
                     .collect(),
             ),
-            Value::BigInt(_) => panic!("can not inverse BigInt"),
+            Value::BigInt(_) | Value::Native(_) => {
+                let p = field.modulus();
+                let i = self.to_bi().rem_euclid(p);
+                Value::BigInt(if i.is_zero() {
+                    BigInt::zero()
+                } else {
+                    i.modpow(&(p - 2), p)
+                })
+            }
         }
     }
 
@@ -228,26 +279,39 @@ impl Value {
         match &self {
             Value::BigInt(bi) => bi.to_bytes_be().1,
             Value::Native(f) => f.into_bigint().to_bytes_be(),
-            Value::ExoNative(_) => todo!(),
+            Value::ExoNative(_) => self.to_bi().to_bytes_be().1,
         }
     }
 
-    pub(crate) fn to_native(&mut self) {
-        if let Value::BigInt(i) = self {
-            clamp_bi(i);
-            *self = if i.bits() as usize > crate::constants::FIELD_BITSIZE {
-                let bs = i.to_bytes_le();
-                let mut r = Vec::new();
-                for bytes in &bs.1.iter().chunks(crate::constants::FIELD_BITSIZE / 8) {
-                    let bb = bytes.cloned().collect_vec();
-                    let small_big_int = BigInt::from_bytes_le(Sign::Plus, &bb);
-                    r.push(Fr::from_str(&small_big_int.to_string()).unwrap());
-                }
-                r.reverse();
-                Value::ExoNative(r)
+    /// Bring `self` to the representation used when computing in `field`,
+    /// i.e. reduced integers in the fields not backed by arkworks
+    pub(crate) fn in_field(self, field: Field) -> Value {
+        if field.is_ark() {
+            if is_ark_native() {
+                self.into_native()
             } else {
-                Value::Native(Fr::from_str(&i.to_string()).unwrap())
-            };
+                self
+            }
+        } else {
+            let mut i = self.to_bi();
+            reduce_exo(&mut i, field);
+            Value::BigInt(i)
+        }
+    }
+
+    /// Parse the decimal integer `x` as an element of `field`
+    pub(crate) fn from_str_in(x: &str, field: Field) -> Value {
+        if field.is_ark() {
+            Value::from(x)
+        } else {
+            Value::BigInt(BigInt::from_str(x).unwrap()).in_field(field)
+        }
+    }
+
+    /// Convert an integer to the arkworks field element(s) representing it
+    pub(crate) fn to_native(&mut self) {
+        if let Value::BigInt(_) = self {
+            *self = std::mem::replace(self, Value::bi_zero()).into_native();
         }
     }
 
@@ -255,7 +319,12 @@ impl Value {
         match self {
             Value::BigInt(x) => x.clone(),
             Value::Native(fr) => BigInt::from_bytes_be(Sign::Plus, &fr.into_bigint().to_bytes_be()),
-            Value::ExoNative(_) => unimplemented!(),
+            // Exo-values are split in chunks of FIELD_BITSIZE/8 bytes, most
+            // significant first
+            Value::ExoNative(fs) => fs.iter().fold(BigInt::zero(), |ax, f| {
+                (ax << (ark_bitsize() / 8 * 8))
+                    + BigInt::from_bytes_be(Sign::Plus, &f.into_bigint().to_bytes_be())
+            }),
         }
     }
 
@@ -277,11 +346,14 @@ impl Value {
     pub(crate) fn into_native(self) -> Value {
         match self {
             Value::BigInt(mut i) => {
+                if i.sign() == Sign::Minus {
+                    i = i.rem_euclid(Field::Bls12_377.modulus());
+                }
                 clamp_bi(&mut i);
-                if i.bits() as usize > crate::constants::FIELD_BITSIZE {
+                if i.bits() as usize > ark_bitsize() {
                     let bs = i.to_bytes_le();
                     let mut r = Vec::new();
-                    for bytes in &bs.1.iter().chunks(crate::constants::FIELD_BITSIZE / 8) {
+                    for bytes in &bs.1.iter().chunks(ark_bitsize() / 8) {
                         let bb = bytes.cloned().collect_vec();
                         let small_big_int = BigInt::from_bytes_le(Sign::Plus, &bb);
                         r.push(Fr::from_str(&small_big_int.to_string()).unwrap());
@@ -334,8 +406,8 @@ impl Value {
     pub(crate) fn bit_size(&self) -> usize {
         match self {
             Value::BigInt(i) => i.bits() as usize,
-            Value::Native(_) => crate::constants::FIELD_BITSIZE,
-            Value::ExoNative(fs) => fs.len() * crate::constants::FIELD_BITSIZE,
+            Value::Native(_) => ark_bitsize(),
+            Value::ExoNative(_) => self.to_bi().bits() as usize,
         }
     }
 }
@@ -349,31 +421,21 @@ impl TryFrom<BigInt> for Value {
     type Error = errors::RuntimeError;
 
     fn try_from(int: BigInt) -> Result<Self, Self::Error> {
-        if int.bits() as usize > constants::FIELD_BITSIZE {
-            return Err(errors::RuntimeError::InvalidValue(
-                "field element",
-                Value::BigInt(int),
-            ));
-        }
-        let mut v = Value::BigInt(int);
-        if *crate::IS_NATIVE.read().unwrap() {
-            v.to_native();
-        }
-        Result::Ok(v)
+        Value::try_from(&int)
     }
 }
 impl TryFrom<&BigInt> for Value {
     type Error = errors::RuntimeError;
 
     fn try_from(int: &BigInt) -> Result<Self, Self::Error> {
-        if int.bits() as usize > constants::FIELD_BITSIZE {
+        if int.bits() as usize > constants::MAX_BIT_SIZE {
             return Err(errors::RuntimeError::InvalidValue(
-                "field element",
+                "integer",
                 Value::BigInt(int.to_owned()),
             ));
         }
         let mut v = Value::BigInt(int.to_owned());
-        if *crate::IS_NATIVE.read().unwrap() {
+        if is_ark_native() && int.sign() != Sign::Minus {
             v.to_native();
         }
         Result::Ok(v)
@@ -386,7 +448,7 @@ impl From<Fr> for Value {
 }
 impl From<usize> for Value {
     fn from(x: usize) -> Self {
-        if *crate::IS_NATIVE.read().unwrap() {
+        if is_ark_native() {
             Value::Native(Fr::from(x as u64))
         } else {
             Value::BigInt(BigInt::from_usize(x).unwrap())
@@ -395,8 +457,9 @@ impl From<usize> for Value {
 }
 impl From<isize> for Value {
     fn from(x: isize) -> Self {
-        if *crate::IS_NATIVE.read().unwrap() {
-            Value::Native(Fr::from(x as i64))
+        // Negative integers only have a meaning once the field is known
+        if is_ark_native() && x >= 0 {
+            Value::Native(Fr::from(x as u64))
         } else {
             Value::BigInt(BigInt::from_isize(x).unwrap())
         }
@@ -404,7 +467,7 @@ impl From<isize> for Value {
 }
 impl From<u64> for Value {
     fn from(x: u64) -> Self {
-        if *crate::IS_NATIVE.read().unwrap() {
+        if is_ark_native() {
             Value::Native(Fr::from(x))
         } else {
             Value::BigInt(BigInt::from_u64(x).unwrap())
@@ -413,8 +476,8 @@ impl From<u64> for Value {
 }
 impl From<i32> for Value {
     fn from(x: i32) -> Self {
-        if *crate::IS_NATIVE.read().unwrap() {
-            Value::Native(Fr::from(x))
+        if is_ark_native() && x >= 0 {
+            Value::Native(Fr::from(x as u64))
         } else {
             Value::BigInt(BigInt::from_i32(x).unwrap())
         }
@@ -422,7 +485,8 @@ impl From<i32> for Value {
 }
 impl From<&str> for Value {
     fn from(x: &str) -> Self {
-        if *crate::IS_NATIVE.read().unwrap() {
+        // Integers of more than 76 digits may not fit in a field element
+        if is_ark_native() && x.len() <= 76 {
             Value::Native(Fr::from_str(x).unwrap())
         } else {
             Value::BigInt(BigInt::from_str(x).unwrap())
@@ -474,14 +538,10 @@ impl std::cmp::PartialOrd for Value {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         match (self, other) {
             (Value::BigInt(i1), Value::BigInt(i2)) => Some(i1.cmp(i2)),
-            (Value::BigInt(_), Value::Native(_)) => todo!(),
-            (Value::BigInt(_), Value::ExoNative(_)) => todo!(),
-            (Value::Native(_), Value::BigInt(_)) => todo!(),
             (Value::Native(f1), Value::Native(f2)) => Some(f1.cmp(f2)),
-            (Value::Native(_), Value::ExoNative(_)) => todo!(),
-            (Value::ExoNative(_), Value::BigInt(_)) => todo!(),
-            (Value::ExoNative(_), Value::Native(_)) => todo!(),
-            (Value::ExoNative(_), Value::ExoNative(_)) => todo!(),
+            // Integers and field elements mix when computing outside of the
+            // arkworks fields
+            _ => Some(self.to_bi().cmp(&other.to_bi())),
         }
     }
 }
@@ -612,6 +672,15 @@ impl ValueBacking {
         }
     }
 
+    /// An estimation of the memory used by the values of this backing; lazy
+    /// backings do not store any
+    pub(crate) fn memory(&self) -> usize {
+        match self {
+            ValueBacking::Vector { v, .. } => v.len() * std::mem::size_of::<Value>(),
+            ValueBacking::Expression { .. } | ValueBacking::Function { .. } => 0,
+        }
+    }
+
     fn update_value(&mut self, _v: Vec<Value>, _spilling: isize) -> Result<()> {
         match self {
             ValueBacking::Vector { v, spilling } => {
@@ -627,7 +696,7 @@ impl ValueBacking {
                     if !x.is_zero() {
                         bail!("overwriting non-zero value in shared register")
                     } else {
-                        x.add_assign(y)
+                        *x = y.clone()
                     }
                 }
             }
@@ -668,7 +737,7 @@ impl ValueBacking {
                         .or_else(|| cs.column(handle).unwrap().padding_value.as_ref().cloned())
                 },
                 &mut None,
-                &EvalSettings { wrap: false },
+                &EvalSettings::new().wrap(false),
             ),
             ValueBacking::Function { f, .. } => f(i, cs),
         }
@@ -695,7 +764,7 @@ impl ValueBacking {
                         .or_else(|| cs.column(handle).unwrap().padding_value.as_ref().cloned())
                 },
                 &mut None,
-                &EvalSettings { wrap: false },
+                &EvalSettings::new().wrap(false),
             ),
             ValueBacking::Function { f, .. } => f(i, cs),
         }
@@ -791,8 +860,10 @@ impl<'a> Iterator for ValueBackingIter<'a> {
 pub struct Register {
     pub handle: Option<Handle>,
     pub magma: Magma,
+    /// may be filled through a shared reference, so that computations running
+    /// concurrently can fill their targets
     #[serde(skip_serializing, skip_deserializing, default)]
-    value: Option<ValueBacking>,
+    value: OnceLock<ValueBacking>,
     width: usize,
 }
 
@@ -806,7 +877,7 @@ impl Register {
     }
 
     pub fn is_empty(&self) -> bool {
-        self.value.is_none()
+        self.value.get().is_none()
     }
 
     pub fn width(&self) -> usize {
@@ -814,10 +885,10 @@ impl Register {
     }
 
     fn set_value(&mut self, v: Vec<Value>, spilling: isize) -> Result<()> {
-        if let Some(ref mut provider) = self.value.as_mut() {
+        if let Some(provider) = self.value.get_mut() {
             provider.update_value(v, spilling)
         } else {
-            let _ = self.value.insert(ValueBacking::from_vec(
+            let _ = self.value.set(ValueBacking::from_vec(
                 Self::make_with_spilling(
                     &mut |i| v.get(i as usize).cloned().unwrap_or_else(Value::zero),
                     v.len(),
@@ -830,43 +901,43 @@ impl Register {
     }
 
     fn set_raw_value(&mut self, v: Vec<Value>, spilling: isize) -> Result<()> {
-        if let Some(ref mut provider) = self.value.as_mut() {
+        if let Some(provider) = self.value.get_mut() {
             provider.update_value(v, spilling)
         } else {
-            let _ = self.value.insert(ValueBacking::from_vec(v, spilling));
+            let _ = self.value.set(ValueBacking::from_vec(v, spilling));
             Ok(())
         }
     }
 
-    pub fn set_backing(&mut self, v: ValueBacking) -> Result<()> {
-        if self.value.is_some() {
-            bail!("backing already set");
-        }
-        self.value = Some(v);
-        Ok(())
+    pub fn set_backing(&self, v: ValueBacking) -> Result<()> {
+        self.value
+            .set(v)
+            .map_err(|_| anyhow!("backing already set"))
     }
 
     pub fn padded_len(&self) -> Option<usize> {
-        self.value.as_ref().map(|v| v.padded_len())
+        self.value.get().map(|v| v.padded_len())
     }
 
     pub fn len(&self) -> Option<usize> {
-        self.value.as_ref().map(|v| v.len())
+        self.value.get().map(|v| v.len())
+    }
+
+    pub fn backing(&self) -> Option<&ValueBacking> {
+        self.value.get()
     }
 
     pub fn get(&self, i: isize, wrap: bool, columns: &ColumnSet) -> Option<Value> {
-        self.value.as_ref().and_then(|v| v.get(i, wrap, columns))
+        self.value.get().and_then(|v| v.get(i, wrap, columns))
     }
 
     pub fn get_raw(&self, i: isize, wrap: bool, columns: &ColumnSet) -> Option<Value> {
-        self.value
-            .as_ref()
-            .and_then(|v| v.get_raw(i, wrap, columns))
+        self.value.get().and_then(|v| v.get_raw(i, wrap, columns))
     }
 
     pub fn concretize(&mut self) {
         if let Some(v) = self.value.take() {
-            let _ = self.value.insert(v.concretize());
+            let _ = self.value.set(v.concretize());
         }
     }
 }
@@ -882,7 +953,6 @@ pub struct Column {
     pub intrinsic_size_factor: Option<usize>,
     pub base: Base,
     pub handle: Handle,
-    computed: bool,
 }
 #[buildstructor::buildstructor]
 impl Column {
@@ -907,7 +977,6 @@ impl Column {
             t: t.unwrap_or(Magma::native()),
             intrinsic_size_factor,
             base: base.unwrap_or(Base::Dec),
-            computed: false,
             handle,
         }
     }
@@ -1108,12 +1177,17 @@ impl ColumnSet {
         self._cols.iter()
     }
 
-    pub(crate) fn new_register(&mut self, handle: Handle, magma: Magma) -> RegisterID {
+    pub(crate) fn new_register(
+        &mut self,
+        handle: Handle,
+        magma: Magma,
+        field: Field,
+    ) -> RegisterID {
         self.registers.push(Register {
             handle: Some(handle),
             magma,
-            value: None,
-            width: crate::constants::col_count_magma(magma),
+            value: OnceLock::new(),
+            width: crate::constants::col_count_magma(magma, field),
         });
         self.registers.len() - 1
     }
@@ -1163,16 +1237,24 @@ impl ColumnSet {
         }
     }
 
-    pub fn insert_column_and_register(&mut self, mut column: Column) -> Result<ColumnRef> {
-        column.register = Some(self.new_register(column.handle.clone(), column.t));
+    pub fn insert_column_and_register(
+        &mut self,
+        mut column: Column,
+        field: Field,
+    ) -> Result<ColumnRef> {
+        column.register = Some(self.new_register(column.handle.clone(), column.t, field));
         self.insert_column(column)
     }
 
-    pub fn maybe_insert_column_and_register(&mut self, mut column: Column) -> Option<ColumnRef> {
+    pub fn maybe_insert_column_and_register(
+        &mut self,
+        mut column: Column,
+        field: Field,
+    ) -> Option<ColumnRef> {
         if self.cols.contains_key(&column.handle) {
             None
         } else {
-            column.register = Some(self.new_register(column.handle.clone(), column.t));
+            column.register = Some(self.new_register(column.handle.clone(), column.t, field));
             self.maybe_insert_column(column)
         }
     }
@@ -1211,11 +1293,26 @@ impl ColumnSet {
     }
 
     pub fn backing(&self, h: &ColumnRef) -> Option<&ValueBacking> {
-        self.register_of(h).value.as_ref()
+        self.register_of(h).backing()
     }
 
+    /// Whether the register backing `h` has been filled
     pub fn is_computed(&self, h: &ColumnRef) -> bool {
-        self.column(h).unwrap().computed
+        self.column(h)
+            .unwrap()
+            .register
+            .is_some_and(|r| !self.registers[r].is_empty())
+    }
+
+    /// Drop the values of all the columns, so that a new trace may be imported
+    pub fn reset(&mut self) {
+        for r in self.registers.iter_mut() {
+            r.value.take();
+        }
+        for r in self.field_registers.iter_mut() {
+            r.value = None;
+        }
+        self.effective_len.clear();
     }
 
     pub fn set_column_value(
@@ -1224,7 +1321,6 @@ impl ColumnSet {
         v: Vec<Value>,
         spilling: isize,
     ) -> Result<()> {
-        self.get_col_mut(h).unwrap().computed = true;
         self.register_of_mut(h)
             .set_value(v, spilling)
             .with_context(|| anyhow!("while filling {}", h.pretty()))
@@ -1236,29 +1332,6 @@ impl ColumnSet {
         v: Vec<Value>,
         spilling: isize,
     ) -> Result<()> {
-        let reg_id = if h.is_id() {
-            h.as_id()
-        } else {
-            self.registers
-                .iter()
-                .enumerate()
-                .find(|(_, r)| {
-                    r.handle
-                        .as_ref()
-                        .map(|handle| handle == h.as_handle())
-                        .unwrap_or(false)
-                })
-                .map(|(id, _)| id)
-                .unwrap()
-        };
-        for column in self
-            ._cols
-            .iter_mut()
-            .filter(|c| c.register.map(|r| r == reg_id).unwrap_or(false))
-        {
-            column.computed = true;
-        }
-
         self.get_register_mut(h)
             .unwrap()
             .set_value(v, spilling)
@@ -1266,13 +1339,19 @@ impl ColumnSet {
     }
 
     pub fn set_raw_value(&mut self, h: &ColumnRef, v: Vec<Value>, spilling: isize) -> Result<()> {
-        self.get_col_mut(h).unwrap().computed = true;
         self.register_of_mut(h).set_raw_value(v, spilling)
     }
 
-    pub fn set_backing(&mut self, h: &ColumnRef, v: ValueBacking) -> Result<()> {
-        self.get_col_mut(h).unwrap().computed = true;
-        self.register_of_mut(h).set_backing(v)
+    /// Fill the register backing `h`; as it only requires a shared reference,
+    /// concurrent computations may fill their respective targets
+    pub fn set_backing(&self, h: &ColumnRef, v: ValueBacking) -> Result<()> {
+        self.register_of(h).set_backing(v)
+    }
+
+    /// Drop the values of the register `reg`, so that the columns it backs are
+    /// not computed anymore
+    pub(crate) fn evict(&mut self, reg: RegisterID) {
+        self.registers[reg].value.take();
     }
 }
 
@@ -1343,6 +1422,21 @@ pub enum Computation {
         froms: Vec<ColumnRef>,
         sorted: Vec<ColumnRef>,
     },
+    /// Splits `source` into `bits`-wide limbs, least significant first
+    Limbs {
+        source: ColumnRef,
+        limbs: Vec<ColumnRef>,
+        bits: usize,
+    },
+    /// The carries of a limb-wise vanishing sum, where `carries[i]` is the
+    /// carry out of `sums[i] + carries[i - 1]`; rows where the sum does not
+    /// vanish, or where a carry exceeds `bound`, are filled with zeroes
+    Carries {
+        sums: Vec<Node>,
+        carries: Vec<ColumnRef>,
+        bits: usize,
+        bound: usize,
+    },
 }
 impl std::fmt::Display for Computation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -1389,6 +1483,23 @@ impl std::fmt::Display for Computation {
                 "Sorting constraints for {}",
                 sorted.iter().map(|c| c.pretty()).join(", ")
             ),
+            Computation::Limbs {
+                source,
+                limbs,
+                bits,
+            } => write!(
+                f,
+                "[{}] ⊞{} {}",
+                limbs.iter().map(|c| c.pretty()).join(" "),
+                bits,
+                source.pretty()
+            ),
+            Computation::Carries { sums, carries, .. } => write!(
+                f,
+                "[{}] carries of [{}]",
+                carries.iter().map(|c| c.pretty()).join(" "),
+                sums.iter().map(|s| s.pretty()).join(", ")
+            ),
         }
     }
 }
@@ -1410,6 +1521,14 @@ impl Computation {
                 .map(|t| t.to_string())
                 .collect::<Vec<_>>()
                 .join(", "),
+            Computation::Limbs { limbs: targets, .. }
+            | Computation::Carries {
+                carries: targets, ..
+            } => targets
+                .iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
         }
     }
 
@@ -1422,10 +1541,151 @@ impl Computation {
             Computation::Sorted { tos, .. } => cs.module_for(tos).unwrap(),
             Computation::CyclicFrom { target, .. } => cs.module_of(target),
             Computation::SortingConstraints { sorted, .. } => cs.module_for(sorted).unwrap(),
+            Computation::Limbs { source, .. } => cs.module_of(source),
+            Computation::Carries { carries, .. } => cs.module_for(carries).unwrap(),
         }
     }
 
     pub fn is_interleaved(&self) -> bool {
         matches!(self, Computation::Interleaved { .. })
     }
+
+    /// Return the columns filled by this computation
+    pub(crate) fn targets(&self) -> Vec<ColumnRef> {
+        match self {
+            Computation::Composite { target, .. }
+            | Computation::ExoOperation { target, .. }
+            | Computation::ExoConstant { target, .. }
+            | Computation::Interleaved { target, .. }
+            | Computation::CyclicFrom { target, .. } => vec![target.clone()],
+            Computation::Sorted { tos, .. } => tos.clone(),
+            Computation::SortingConstraints {
+                ats,
+                eq,
+                delta,
+                delta_bytes,
+                ..
+            } => ats
+                .iter()
+                .chain([eq, delta])
+                .chain(delta_bytes.iter())
+                .cloned()
+                .collect(),
+            Computation::Limbs { limbs, .. } => limbs.clone(),
+            Computation::Carries { carries, .. } => carries.clone(),
+        }
+    }
+
+    /// Return the columns read by this computation
+    pub(crate) fn sources(&self) -> HashSet<ColumnRef> {
+        let targets = self.targets();
+        self.dependencies()
+            .into_iter()
+            .filter(|c| !targets.contains(c))
+            .collect()
+    }
+
+    /// Return all the columns, targets and sources, involved in this computation
+    pub(crate) fn dependencies(&self) -> HashSet<ColumnRef> {
+        match self {
+            Computation::Composite { target, exp } => {
+                let mut r = exp.dependencies();
+                r.insert(target.clone());
+                r
+            }
+            Computation::ExoOperation {
+                sources, target, ..
+            } => {
+                let mut r = sources
+                    .iter()
+                    .flat_map(|s| s.dependencies())
+                    .collect::<HashSet<_>>();
+                r.insert(target.clone());
+                r
+            }
+            Computation::ExoConstant { target, .. } => HashSet::from([target.clone()]),
+            Computation::Interleaved { target, froms }
+            | Computation::CyclicFrom { target, froms, .. } => std::iter::once(target)
+                .chain(froms.iter())
+                .cloned()
+                .collect(),
+            Computation::Sorted { froms, tos, .. } => {
+                froms.iter().chain(tos.iter()).cloned().collect()
+            }
+            Computation::SortingConstraints {
+                ats,
+                eq,
+                delta,
+                delta_bytes,
+                froms,
+                sorted,
+                ..
+            } => ats
+                .iter()
+                .chain([eq, delta])
+                .chain(delta_bytes.iter())
+                .chain(froms.iter())
+                .chain(sorted.iter())
+                .cloned()
+                .collect(),
+            Computation::Limbs { source, limbs, .. } => std::iter::once(source)
+                .chain(limbs.iter())
+                .cloned()
+                .collect(),
+            Computation::Carries { sums, carries, .. } => sums
+                .iter()
+                .flat_map(|s| s.dependencies())
+                .chain(carries.iter().cloned())
+                .collect(),
+        }
+    }
+
+    pub fn add_id_to_handles(&mut self, set_id: &dyn Fn(&mut ColumnRef)) {
+        match self {
+            Computation::Composite { target, exp } => {
+                set_id(target);
+                exp.add_id_to_handles(set_id);
+            }
+            Computation::ExoOperation {
+                sources, target, ..
+            } => {
+                for source in sources.iter_mut() {
+                    source.add_id_to_handles(set_id);
+                }
+                set_id(target);
+            }
+            Computation::ExoConstant { target, .. } => set_id(target),
+            Computation::Interleaved { target, froms }
+            | Computation::CyclicFrom { target, froms, .. } => std::iter::once(target)
+                .chain(froms.iter_mut())
+                .for_each(set_id),
+            Computation::Sorted { froms, tos, .. } => {
+                froms.iter_mut().chain(tos.iter_mut()).for_each(set_id)
+            }
+            Computation::SortingConstraints {
+                ats,
+                eq,
+                delta,
+                delta_bytes,
+                froms,
+                sorted,
+                ..
+            } => ats
+                .iter_mut()
+                .chain([eq, delta])
+                .chain(delta_bytes.iter_mut())
+                .chain(froms.iter_mut())
+                .chain(sorted.iter_mut())
+                .for_each(set_id),
+            Computation::Limbs { source, limbs, .. } => std::iter::once(source)
+                .chain(limbs.iter_mut())
+                .for_each(set_id),
+            Computation::Carries { sums, carries, .. } => {
+                for sum in sums.iter_mut() {
+                    sum.add_id_to_handles(set_id);
+                }
+                carries.iter_mut().for_each(set_id);
+            }
+        }
+    }
 }
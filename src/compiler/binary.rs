@@ -0,0 +1,79 @@
+//! A compact, versioned binary serialization of [`ConstraintSet`].
+//!
+//! A binary constraint set is laid out as:
+//!   - the [`MAGIC`] header;
+//!   - the [`FORMAT_VERSION`] of the layout, as a little-endian u16;
+//!   - the version of corset that produced it;
+//!   - the bincode-encoded [`ConstraintSet`].
+//!
+//! Files lacking the magic header are assumed to be legacy RON-encoded
+//! constraint sets.
+use anyhow::*;
+use std::path::Path;
+use thiserror::Error;
+
+use super::ConstraintSet;
+
+/// The header identifying binary constraint sets
+pub const MAGIC: &[u8; 8] = b"CORSETCS";
+/// Must be bumped whenever the layout of [`ConstraintSet`] changes
+pub const FORMAT_VERSION: u16 = 3;
+
+#[derive(Error, Debug)]
+pub enum BinaryError {
+    #[error("not a binary constraint set")]
+    NotBinary,
+    #[error("constraint set format v{0} is not supported; expected v{FORMAT_VERSION}")]
+    IncompatibleFormat(u16),
+    #[error("constraint set compiled by corset {0} can not be used by corset {}", env!("CARGO_PKG_VERSION"))]
+    IncompatibleVersion(String),
+}
+
+/// Two versions of corset produce compatible constraint sets if they share the
+/// same major version
+fn is_compatible(version: &str) -> bool {
+    version.split('.').next() == env!("CARGO_PKG_VERSION").split('.').next()
+}
+
+impl ConstraintSet {
+    /// Encode the constraint set in the binary format
+    pub fn to_bin(&self) -> Result<Vec<u8>> {
+        let mut r = MAGIC.to_vec();
+        r.extend(FORMAT_VERSION.to_le_bytes());
+        bincode::serialize_into(&mut r, env!("CARGO_PKG_VERSION"))?;
+        bincode::serialize_into(&mut r, self)?;
+        Ok(r)
+    }
+
+    /// Decode a constraint set in the binary format
+    pub fn from_bin(bytes: &[u8]) -> Result<ConstraintSet> {
+        let bytes = bytes.strip_prefix(MAGIC).ok_or(BinaryError::NotBinary)?;
+        if bytes.len() < 2 {
+            bail!(BinaryError::NotBinary)
+        }
+        let format = u16::from_le_bytes([bytes[0], bytes[1]]);
+        if format != FORMAT_VERSION {
+            bail!(BinaryError::IncompatibleFormat(format))
+        }
+        let mut payload = &bytes[2..];
+        let version: String = bincode::deserialize_from(&mut payload)?;
+        if !is_compatible(&version) {
+            bail!(BinaryError::IncompatibleVersion(version))
+        }
+        bincode::deserialize(payload).with_context(|| "while decoding the constraint set")
+    }
+
+    /// Load a constraint set from a file, either in the binary or in the
+    /// legacy RON format
+    pub fn from_file<P: AsRef<Path>>(filename: P) -> Result<ConstraintSet> {
+        let filename = filename.as_ref();
+        let bytes = std::fs::read(filename)
+            .with_context(|| anyhow!("while reading `{}`", filename.display()))?;
+        if bytes.starts_with(MAGIC) {
+            ConstraintSet::from_bin(&bytes)
+        } else {
+            ron::from_str(std::str::from_utf8(&bytes)?).map_err(Error::from)
+        }
+        .with_context(|| anyhow!("while parsing `{}`", filename.display()))
+    }
+}
@@ -0,0 +1,484 @@
+//! Expressions compiled into a flat sequence of instructions, evaluated over
+//! batches of rows rather than by walking the expression tree once per row.
+use anyhow::*;
+use cached::{Cached, SizedCache};
+use num_traits::ToPrimitive;
+use std::collections::HashMap;
+
+use crate::{
+    column::{ColumnSet, Value, ValueBacking},
+    field::Field,
+};
+
+use super::{ColumnRef, Expression, Intrinsic, Node};
+
+/// How many rows are evaluated at once; an evaluator holds this many values
+/// per level of the evaluated expression
+pub const BATCH_SIZE: usize = 1024;
+
+/// The index of an instruction, standing for its result
+type Operand = usize;
+
+#[derive(Debug, Clone)]
+enum Instruction {
+    Const(Value),
+    /// read the given input of the program
+    Load(usize),
+    Add(Vec<Operand>),
+    Sub(Vec<Operand>),
+    /// the arguments following the first zero one are ignored
+    Mul(Vec<Operand>),
+    VectorAdd(Vec<Operand>),
+    VectorSub(Vec<Operand>),
+    VectorMul(Vec<Operand>),
+    Exp(Operand, usize),
+    Neg(Operand),
+    Inv(Operand),
+    Normalize(Operand),
+    IfZero(Operand, Operand, Option<Operand>),
+    IfNotZero(Operand, Operand, Option<Operand>),
+    /// the first non-zero element, or zero
+    List(Vec<Operand>),
+}
+
+/// How a program reads the columns of the trace
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// as when checking constraints: out-of-bounds cells are missing,
+    /// negative ones wrapping around the trace if required
+    Raw { wrap: bool },
+    /// as when computing columns: cells before the trace are padding, and
+    /// missing cells default to the padding value of their column
+    Padded,
+}
+
+/// An expression compiled into a sequence of instructions, the last one
+/// yielding its value
+#[derive(Debug, Clone)]
+pub struct Program {
+    instructions: Vec<Instruction>,
+    /// the register each instruction writes its result to
+    registers: Vec<usize>,
+    /// how many registers are needed to run the program
+    register_count: usize,
+    /// the shifted columns read by the program
+    inputs: Vec<(ColumnRef, isize)>,
+}
+impl Program {
+    pub fn compile(e: &Node) -> Result<Program> {
+        let mut r = Program {
+            instructions: Vec::new(),
+            registers: Vec::new(),
+            register_count: 0,
+            inputs: Vec::new(),
+        };
+        let mut inputs = HashMap::new();
+        r.emit(e, 0, &mut inputs)?;
+        Ok(r)
+    }
+
+    /// Instructions are run depth-first, and their result is consumed by
+    /// their parent right after being computed; hence, only the results of
+    /// the instructions on the path from the root are live at once, and an
+    /// instruction may write to the register indexed by its depth
+    fn push(&mut self, i: Instruction, depth: usize) -> Operand {
+        self.instructions.push(i);
+        self.registers.push(depth);
+        self.register_count = self.register_count.max(depth + 1);
+        self.instructions.len() - 1
+    }
+
+    fn emit_all(
+        &mut self,
+        args: &[Node],
+        depth: usize,
+        inputs: &mut HashMap<(ColumnRef, isize), usize>,
+    ) -> Result<Vec<Operand>> {
+        args.iter().map(|a| self.emit(a, depth, inputs)).collect()
+    }
+
+    fn emit(
+        &mut self,
+        e: &Node,
+        depth: usize,
+        inputs: &mut HashMap<(ColumnRef, isize), usize>,
+    ) -> Result<Operand> {
+        let d = depth + 1;
+        match e.e() {
+            Expression::Const(v) => Ok(self.push(Instruction::Const(v.clone()), depth)),
+            Expression::Column { handle, shift, .. }
+            | Expression::ExoColumn { handle, shift, .. } => {
+                let key = (handle.clone(), *shift as isize);
+                let input = *inputs.entry(key.clone()).or_insert_with(|| {
+                    self.inputs.push(key);
+                    self.inputs.len() - 1
+                });
+                Ok(self.push(Instruction::Load(input), depth))
+            }
+            Expression::Funcall { func, args } => {
+                let i = match func {
+                    Intrinsic::Add => Instruction::Add(self.emit_all(args, d, inputs)?),
+                    Intrinsic::Sub => Instruction::Sub(self.emit_all(args, d, inputs)?),
+                    Intrinsic::Mul => Instruction::Mul(self.emit_all(args, d, inputs)?),
+                    Intrinsic::VectorAdd => Instruction::VectorAdd(self.emit_all(args, d, inputs)?),
+                    Intrinsic::VectorSub => Instruction::VectorSub(self.emit_all(args, d, inputs)?),
+                    Intrinsic::VectorMul => Instruction::VectorMul(self.emit_all(args, d, inputs)?),
+                    Intrinsic::Exp => {
+                        let exp = args[1]
+                            .pure_eval()?
+                            .to_usize()
+                            .ok_or_else(|| anyhow!("invalid exponent {}", args[1]))?;
+                        Instruction::Exp(self.emit(&args[0], d, inputs)?, exp)
+                    }
+                    Intrinsic::Neg => Instruction::Neg(self.emit(&args[0], d, inputs)?),
+                    Intrinsic::Inv => Instruction::Inv(self.emit(&args[0], d, inputs)?),
+                    Intrinsic::Normalize => Instruction::Normalize(self.emit(&args[0], d, inputs)?),
+                    Intrinsic::IfZero | Intrinsic::IfNotZero => {
+                        let cond = self.emit(&args[0], d, inputs)?;
+                        let then = self.emit(&args[1], d, inputs)?;
+                        let otherwise = args.get(2).map(|a| self.emit(a, d, inputs)).transpose()?;
+                        if *func == Intrinsic::IfZero {
+                            Instruction::IfZero(cond, then, otherwise)
+                        } else {
+                            Instruction::IfNotZero(cond, then, otherwise)
+                        }
+                    }
+                    Intrinsic::Begin => bail!("unexpected {} in compiled expression", func),
+                };
+                Ok(self.push(i, depth))
+            }
+            Expression::List(xs) => {
+                let xs = self.emit_all(xs, d, inputs)?;
+                Ok(self.push(Instruction::List(xs), depth))
+            }
+            Expression::ArrayColumn { .. } | Expression::Void => {
+                bail!("unable to compile {}", e)
+            }
+        }
+    }
+}
+
+/// Where the values of an input of a program are read from
+enum Source<'a> {
+    /// a column stored in memory, read directly
+    Slice {
+        v: &'a [Value],
+        spilling: isize,
+        padding: Option<&'a Value>,
+    },
+    /// a column computed on the fly, read through the column set
+    Lazy(&'a ColumnRef),
+}
+
+/// A program bound to the columns it reads, along with its registers
+pub struct Evaluator<'a> {
+    program: &'a Program,
+    columns: &'a ColumnSet,
+    access: Access,
+    /// the field the program computes in
+    field: Field,
+    sources: Vec<(Source<'a>, isize)>,
+    registers: Vec<Vec<Option<Value>>>,
+    /// field inversions are expensive, and often applied to the same values
+    inverses: SizedCache<Value, Value>,
+}
+impl<'a> Evaluator<'a> {
+    pub fn new(program: &'a Program, columns: &'a ColumnSet, access: Access, field: Field) -> Self {
+        let sources = program
+            .inputs
+            .iter()
+            .map(|(handle, shift)| {
+                let source = match columns.backing(handle) {
+                    Some(ValueBacking::Vector { v, spilling }) => Source::Slice {
+                        v,
+                        spilling: *spilling,
+                        padding: columns
+                            .column(handle)
+                            .ok()
+                            .and_then(|c| c.padding_value.as_ref()),
+                    },
+                    _ => Source::Lazy(handle),
+                };
+                (source, *shift)
+            })
+            .collect();
+        Evaluator {
+            program,
+            columns,
+            access,
+            field,
+            sources,
+            registers: vec![Vec::new(); program.register_count],
+            inverses: SizedCache::with_size(200000), // ~1.60MB cache
+        }
+    }
+
+    /// The register holding the result of `r`
+    fn register(&mut self, r: Operand) -> &mut [Option<Value>] {
+        &mut self.registers[self.program.registers[r]]
+    }
+
+    /// Fill the register of `r` with the values of `input` at the `active` rows
+    fn load(&mut self, r: Operand, input: usize, rows: &[isize], active: &[usize]) {
+        let (source, shift) = &self.sources[input];
+        let out = &mut self.registers[self.program.registers[r]];
+        match (source, self.access) {
+            (Source::Slice { v, spilling, .. }, Access::Raw { wrap }) => {
+                for &k in active {
+                    let i = rows[k] + shift;
+                    out[k] = if i >= 0 {
+                        v.get((i + spilling) as usize)
+                    } else if wrap {
+                        v.get((v.len() as isize + i) as usize)
+                    } else {
+                        None
+                    }
+                    .cloned();
+                }
+            }
+            (
+                Source::Slice {
+                    v,
+                    spilling,
+                    padding,
+                },
+                Access::Padded,
+            ) => {
+                for &k in active {
+                    let i = rows[k] + shift;
+                    out[k] = if i < -spilling {
+                        v.first()
+                    } else {
+                        v.get((i + spilling) as usize)
+                    }
+                    .or(*padding)
+                    .cloned();
+                }
+            }
+            (Source::Lazy(handle), Access::Raw { wrap }) => {
+                for &k in active {
+                    out[k] = self.columns.get_raw(handle, rows[k] + shift, wrap);
+                }
+            }
+            (Source::Lazy(handle), Access::Padded) => {
+                let padding = self
+                    .columns
+                    .column(handle)
+                    .ok()
+                    .and_then(|c| c.padding_value.as_ref());
+                for &k in active {
+                    out[k] = self
+                        .columns
+                        .get(handle, rows[k] + shift, false)
+                        .or_else(|| padding.cloned());
+                }
+            }
+        }
+    }
+
+    /// The register of `r`, along with the one of its argument `arg`, which
+    /// lies one level deeper; as registers are filled right before being
+    /// read, their values may be moved rather than copied
+    fn pair(&mut self, r: Operand, arg: Operand) -> (&mut [Option<Value>], &mut [Option<Value>]) {
+        let (r, arg) = (self.program.registers[r], self.program.registers[arg]);
+        let (before, after) = self.registers.split_at_mut(arg);
+        (&mut before[r], &mut after[0])
+    }
+
+    /// Evaluate the program on each of `rows`, at most [`BATCH_SIZE`] of
+    /// them at once; `None` stands for an out-of-bounds access
+    pub fn eval(&mut self, rows: &[isize]) -> &[Option<Value>] {
+        // Operands are always filled right before being read, so that there
+        // is no need to reset them between batches
+        for r in self.registers.iter_mut() {
+            r.resize(rows.len(), None);
+        }
+        let root = self.program.instructions.len() - 1;
+        self.run(root, rows, &(0..rows.len()).collect::<Vec<_>>());
+        self.register(root)
+    }
+
+    /// Fill the register of `r` for the `active` rows of the batch; as in
+    /// [`Node::eval`], sub-expressions that can not affect the result, e.g.
+    /// the factors following a zero one, are not evaluated
+    fn run(&mut self, r: Operand, rows: &[isize], active: &[usize]) {
+        if active.is_empty() {
+            return;
+        }
+        let program = self.program;
+        match &program.instructions[r] {
+            Instruction::Const(v) => {
+                for &k in active {
+                    self.register(r)[k] = Some(v.clone());
+                }
+            }
+            Instruction::Load(input) => self.load(r, *input, rows, active),
+            Instruction::Add(args) => self.fold(r, args, rows, active, Value::add_assign),
+            Instruction::Sub(args) => self.fold(r, args, rows, active, Value::sub_assign),
+            Instruction::VectorAdd(args) => {
+                self.fold(r, args, rows, active, Value::vector_add_assign)
+            }
+            Instruction::VectorSub(args) => {
+                self.fold(r, args, rows, active, Value::vector_sub_assign)
+            }
+            Instruction::VectorMul(args) => {
+                self.fold(r, args, rows, active, Value::vector_mul_assign)
+            }
+            Instruction::Mul(args) => {
+                self.run(args[0], rows, active);
+                let field = self.field;
+                let mut live = Vec::with_capacity(active.len());
+                let (out, x) = self.pair(r, args[0]);
+                for &k in active {
+                    out[k] = x[k].take();
+                    if out[k].as_ref().map(|x| !x.is_zero()).unwrap_or(false) {
+                        live.push(k);
+                    }
+                }
+                for &arg in args.iter().skip(1) {
+                    self.run(arg, rows, &live);
+                    let (out, x) = self.pair(r, arg);
+                    live.retain(|&k| match x[k].as_ref() {
+                        Some(x) => {
+                            let ax = out[k].as_mut().unwrap();
+                            ax.mul_assign(x, field);
+                            !ax.is_zero()
+                        }
+                        None => {
+                            out[k] = None;
+                            false
+                        }
+                    });
+                }
+            }
+            Instruction::Exp(arg, exp) => {
+                self.run(*arg, rows, active);
+                let field = self.field;
+                let (out, x) = self.pair(r, *arg);
+                for &k in active {
+                    out[k] = x[k].as_ref().map(|mantissa| {
+                        let mut ax = mantissa.clone();
+                        for _ in 1..*exp {
+                            ax.mul_assign(mantissa, field);
+                        }
+                        ax
+                    });
+                }
+            }
+            Instruction::Neg(arg) => {
+                self.run(*arg, rows, active);
+                let field = self.field;
+                let (out, x) = self.pair(r, *arg);
+                for &k in active {
+                    out[k] = x[k].take().map(|mut x| {
+                        x.negate(field);
+                        x
+                    });
+                }
+            }
+            Instruction::Inv(arg) => {
+                self.run(*arg, rows, active);
+                let (before, after) = self.registers.split_at_mut(self.program.registers[*arg]);
+                let (out, x) = (&mut before[self.program.registers[r]], &after[0]);
+                for &k in active {
+                    out[k] = x[k].as_ref().map(|x| {
+                        self.inverses
+                            .cache_get_or_set_with(x.clone(), || x.inverse(self.field))
+                            .to_owned()
+                    });
+                }
+            }
+            Instruction::Normalize(arg) => {
+                self.run(*arg, rows, active);
+                let (out, x) = self.pair(r, *arg);
+                for &k in active {
+                    out[k] = x[k].as_ref().map(|x| x.normalize());
+                }
+            }
+            Instruction::IfZero(cond, then, otherwise)
+            | Instruction::IfNotZero(cond, then, otherwise) => {
+                let if_zero = matches!(program.instructions[r], Instruction::IfZero(..));
+                self.run(*cond, rows, active);
+                let mut then_rows = Vec::new();
+                let mut otherwise_rows = Vec::new();
+                let (out, c) = self.pair(r, *cond);
+                for &k in active {
+                    match c[k].as_ref() {
+                        Some(c) if c.is_zero() == if_zero => then_rows.push(k),
+                        Some(_) => otherwise_rows.push(k),
+                        None => out[k] = None,
+                    }
+                }
+
+                self.run(*then, rows, &then_rows);
+                let (out, x) = self.pair(r, *then);
+                for &k in then_rows.iter() {
+                    out[k] = x[k].take();
+                }
+                if let Some(otherwise) = otherwise {
+                    self.run(*otherwise, rows, &otherwise_rows);
+                    let (out, x) = self.pair(r, *otherwise);
+                    for &k in otherwise_rows.iter() {
+                        out[k] = x[k].take();
+                    }
+                } else {
+                    for &k in otherwise_rows.iter() {
+                        self.register(r)[k] = Some(Value::zero());
+                    }
+                }
+            }
+            Instruction::List(xs) => {
+                let mut pending = active.to_vec();
+                for &x in xs.iter() {
+                    self.run(x, rows, &pending);
+                    let (out, x) = self.pair(r, x);
+                    pending.retain(|&k| match x[k].take() {
+                        Some(x) if !x.is_zero() => {
+                            out[k] = Some(x);
+                            false
+                        }
+                        _ => true,
+                    });
+                }
+                for k in pending {
+                    self.register(r)[k] = Some(Value::zero());
+                }
+            }
+        }
+    }
+
+    /// Fill the register of `r` with the left fold of `f` over `args`
+    fn fold(
+        &mut self,
+        r: Operand,
+        args: &[Operand],
+        rows: &[isize],
+        active: &[usize],
+        f: fn(&mut Value, &Value, Field),
+    ) {
+        self.run(args[0], rows, active);
+        let field = self.field;
+        let mut live = Vec::with_capacity(active.len());
+        let (out, x) = self.pair(r, args[0]);
+        for &k in active {
+            out[k] = x[k].take();
+            if out[k].is_some() {
+                live.push(k);
+            }
+        }
+        for &arg in args.iter().skip(1) {
+            self.run(arg, rows, &live);
+            let (out, x) = self.pair(r, arg);
+            live.retain(|&k| match x[k].as_ref() {
+                Some(x) => {
+                    f(out[k].as_mut().unwrap(), x, field);
+                    true
+                }
+                None => {
+                    out[k] = None;
+                    false
+                }
+            });
+        }
+    }
+}
@@ -156,7 +156,7 @@ impl std::fmt::Display for Builtin {
 
 /// An intrinsic is a function that can appear in the final compiled form
 /// of an expression
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum Intrinsic {
     Add,
     Sub,
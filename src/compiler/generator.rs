@@ -22,7 +22,8 @@ use super::tables::{ComputationTable, Scope};
 use super::{common::*, CompileSettings, Conditioning, Expression, Magma, Node, Type};
 use crate::column::{Column, ColumnSet, Computation, RegisterID, Value, ValueBacking};
 use crate::dag::ComputationDag;
-use crate::errors::{self, CompileError, RuntimeError};
+use crate::errors::{self, diagnostics::Locate, CompileError, RuntimeError};
+use crate::field::Field;
 use crate::pretty::Pretty;
 use crate::structs::Handle;
 use crate::utils::hash_strings;
@@ -69,16 +70,20 @@ pub enum Constraint {
     },
 }
 impl Constraint {
-    pub fn name(&self) -> String {
+    pub fn handle(&self) -> &Handle {
         match self {
-            Constraint::Vanishes { handle, .. } => handle.to_string(),
-            Constraint::Lookup { handle, .. } => handle.to_string(),
-            Constraint::Permutation { handle, .. } => handle.to_string(),
-            Constraint::InRange { handle, .. } => handle.to_string(),
-            Constraint::Normalization { handle, .. } => handle.to_string(),
+            Constraint::Vanishes { handle, .. }
+            | Constraint::Lookup { handle, .. }
+            | Constraint::Permutation { handle, .. }
+            | Constraint::InRange { handle, .. }
+            | Constraint::Normalization { handle, .. } => handle,
         }
     }
 
+    pub fn name(&self) -> String {
+        self.handle().to_string()
+    }
+
     pub fn add_id_to_handles(&mut self, set_id: &dyn Fn(&mut ColumnRef)) {
         match self {
             Constraint::Vanishes { expr, .. } => expr.add_id_to_handles(set_id),
@@ -105,6 +110,35 @@ impl Constraint {
         }
     }
 
+    /// Return all the columns involved in this constraint
+    pub(crate) fn dependencies(&self) -> HashSet<ColumnRef> {
+        match self {
+            Constraint::Vanishes { expr, .. } => expr.dependencies(),
+            Constraint::Lookup {
+                including,
+                included,
+                ..
+            } => including
+                .iter()
+                .chain(included.iter())
+                .flat_map(|e| e.dependencies())
+                .collect(),
+            Constraint::Permutation { from, to, .. } => {
+                from.iter().chain(to.iter()).cloned().collect()
+            }
+            Constraint::InRange { exp, .. } => exp.dependencies(),
+            Constraint::Normalization {
+                reference,
+                inverted,
+                ..
+            } => {
+                let mut r = reference.dependencies();
+                r.insert(inverted.clone());
+                r
+            }
+        }
+    }
+
     pub(crate) fn size(&self) -> usize {
         match self {
             Constraint::Vanishes { expr, .. } => expr.size(),
@@ -121,10 +155,15 @@ pub struct EvalSettings {
     /// If true, negative indices will loop from the end of the column;
     /// otherwise, they will go up in the padding.
     pub wrap: bool,
+    /// The field in which the expression is computed
+    pub field: Field,
 }
 impl Default for EvalSettings {
     fn default() -> Self {
-        EvalSettings { wrap: true }
+        EvalSettings {
+            wrap: true,
+            field: Field::Bls12_377,
+        }
     }
 }
 impl EvalSettings {
@@ -135,6 +174,10 @@ impl EvalSettings {
     pub fn wrap(self, w: bool) -> Self {
         Self { wrap: w, ..self }
     }
+
+    pub fn field(self, field: Field) -> Self {
+        Self { field, ..self }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -355,6 +398,9 @@ pub struct ConstraintSet {
     pub perspectives: PerspectiveTable,
     pub transformations: u32,
     pub auto_constraints: u32,
+    /// the field the constraints are defined over
+    #[serde(default)]
+    pub field: Field,
 }
 impl ConstraintSet {
     pub fn new(
@@ -363,6 +409,7 @@ impl ConstraintSet {
         constants: HashMap<Handle, BigInt>,
         computations: ComputationTable,
         perspectives: PerspectiveTable,
+        field: Field,
     ) -> Result<Self> {
         let mut r = ConstraintSet {
             constraints,
@@ -372,6 +419,7 @@ impl ConstraintSet {
             perspectives,
             transformations: 0,
             auto_constraints: 0,
+            field,
         };
         r.convert_refs_to_ids()?;
         r.allocate_registers();
@@ -429,6 +477,7 @@ impl ConstraintSet {
             let reg = self.columns.new_register(
                 self.handle(&c).to_owned(),
                 self.columns.column(&c).unwrap().t,
+                self.field,
             );
             self.columns.assign_register(&c, reg).unwrap();
         }
@@ -451,9 +500,11 @@ impl ConstraintSet {
                             .filter_map(|v| v.get(i))
                             .map(|r| self.handle(r).name.to_owned())
                             .join("_xor_");
-                        let reg = self
-                            .columns
-                            .new_register(Handle::new(module, names), *magma);
+                        let reg = self.columns.new_register(
+                            Handle::new(module, names),
+                            *magma,
+                            self.field,
+                        );
                         for cols in sets.values() {
                             if let Some(col_id) = cols.get(i) {
                                 self.columns.assign_register(col_id, reg).unwrap();
@@ -486,7 +537,9 @@ impl ConstraintSet {
                     | Computation::CyclicFrom { target, .. }
                     | Computation::Composite { target, .. } => {
                         let col = self.columns.column(&target).unwrap();
-                        let reg = self.columns.new_register(col.handle.clone(), col.t);
+                        let reg = self
+                            .columns
+                            .new_register(col.handle.clone(), col.t, self.field);
                         self.columns.assign_register(&target, reg).unwrap();
                     }
                     Computation::Sorted { froms, tos, .. } => {
@@ -496,7 +549,8 @@ impl ConstraintSet {
                                 .entry(self.columns.column(f).unwrap().register.unwrap())
                                 .or_insert_with(|| {
                                     let col = self.columns.column(t).unwrap();
-                                    self.columns.new_register(col.handle.clone(), col.t)
+                                    self.columns
+                                        .new_register(col.handle.clone(), col.t, self.field)
                                 });
                             self.columns.assign_register(t, *reg).unwrap();
                         }
@@ -514,7 +568,9 @@ impl ConstraintSet {
                             .chain(delta_bytes.iter())
                         {
                             let col = self.columns.column(r).unwrap();
-                            let reg = self.columns.new_register(col.handle.clone(), col.t);
+                            let reg =
+                                self.columns
+                                    .new_register(col.handle.clone(), col.t, self.field);
                             self.columns.assign_register(r, reg).unwrap();
                         }
                     }
@@ -555,6 +611,7 @@ impl ConstraintSet {
                                             .kind(Kind::Computed)
                                             .handle(srt_guard_col_handle)
                                             .build(),
+                                        self.field,
                                     )
                                 {
                                     let srt_guard = Node::column()
@@ -610,6 +667,7 @@ impl ConstraintSet {
                                             .kind(Kind::Computed)
                                             .handle(srt_guard_col_handle.clone())
                                             .build(),
+                                        self.field,
                                     )
                                     .unwrap();
                                 let srt_guard = Node::column()
@@ -789,36 +847,7 @@ impl ConstraintSet {
             .collect();
 
         for c in self.computations.iter_mut() {
-            match c {
-                Computation::Composite { target, exp } => {
-                    convert_to_id(target);
-                    exp.add_id_to_handles(&convert_to_id);
-                }
-                Computation::Interleaved { target, froms } => std::iter::once(target)
-                    .chain(froms.iter_mut())
-                    .for_each(convert_to_id),
-                Computation::Sorted { froms, tos, .. } => froms
-                    .iter_mut()
-                    .chain(tos.iter_mut())
-                    .for_each(convert_to_id),
-                Computation::CyclicFrom { target, froms, .. } => std::iter::once(target)
-                    .chain(froms.iter_mut())
-                    .for_each(convert_to_id),
-                Computation::SortingConstraints { .. } => {
-                    // These computations are built with IDs from the very start
-                }
-                Computation::ExoOperation {
-                    sources, target, ..
-                } => {
-                    for source in sources.iter_mut() {
-                        source.add_id_to_handles(&convert_to_id);
-                    }
-                    convert_to_id(target);
-                }
-                Computation::ExoConstant { target, .. } => {
-                    convert_to_id(target);
-                }
-            }
+            c.add_id_to_handles(&convert_to_id);
         }
 
         for p in self.perspectives.values_mut().flat_map(|k| k.values_mut()) {
@@ -926,6 +955,13 @@ impl ConstraintSet {
                     .map(|c| self.length_multiplier(&c))
                     .unwrap_or(1),
                 Computation::ExoConstant { .. } => 1,
+                Computation::Limbs { source, .. } => self.length_multiplier(source),
+                Computation::Carries { sums, .. } => sums
+                    .iter()
+                    .flat_map(|s| s.dependencies())
+                    .next()
+                    .map(|c| self.length_multiplier(&c))
+                    .unwrap_or(1),
             })
             .unwrap_or(1)
             * self
@@ -972,7 +1008,7 @@ impl ConstraintSet {
                                         0,
                                         |_, _, _| Some(Value::zero()),
                                         &mut None,
-                                        &EvalSettings::default(),
+                                        &EvalSettings::new().field(self.field),
                                     )
                                     .unwrap_or_else(Value::zero),
                                 Computation::Interleaved { .. } => Value::zero(),
@@ -981,6 +1017,8 @@ impl ConstraintSet {
                                 Computation::SortingConstraints { .. } => Value::zero(),
                                 Computation::ExoOperation { .. } => Value::zero(), // TODO: FIXME:
                                 Computation::ExoConstant { .. } => Value::zero(),  // TODO: FIXME:
+                                Computation::Limbs { .. } => Value::zero(),
+                                Computation::Carries { .. } => Value::zero(),
                             })
                             .unwrap_or_else(Value::zero)
                     })
@@ -1155,6 +1193,25 @@ impl ConstraintSet {
                         ))
                     }
                 }
+                Computation::Limbs { source, limbs, .. } => {
+                    if !source.is_id() || limbs.iter().any(|r| !r.is_id()) {
+                        bail!(errors::compiler::Error::ComputationWithHandles(
+                            c.to_string()
+                        ))
+                    }
+                }
+                Computation::Carries { sums, carries, .. } => {
+                    if carries
+                        .iter()
+                        .cloned()
+                        .chain(sums.iter().flat_map(|s| s.dependencies()))
+                        .any(|r| !r.is_id())
+                    {
+                        bail!(errors::compiler::Error::ComputationWithHandles(
+                            c.to_string()
+                        ))
+                    }
+                }
             }
         }
 
@@ -1536,10 +1593,7 @@ pub fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Resul
                 },
             ),
         )),
-        Token::Symbol(name) => Ok(Some(
-            ctx.resolve_symbol(name)
-                .with_context(|| make_ast_error(e))?,
-        )),
+        Token::Symbol(name) => Ok(Some(ctx.resolve_symbol(name).at(e)?)),
         Token::IndexedSymbol { name, index } => {
             let symbol = ctx.resolve_symbol(name)?;
             if let Expression::ArrayColumn {
@@ -1559,6 +1613,7 @@ pub fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Resul
                             .kind(Kind::Commitment)
                             .base(*base)
                             .t(symbol.t().m())
+                            .field(settings.field)
                             .build(),
                     ))
                 } else {
@@ -1576,9 +1631,7 @@ pub fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Resul
             if args.is_empty() {
                 Ok(Some(Expression::List(vec![]).into()))
             } else if let Token::Symbol(verb) = &args[0].class {
-                let func = ctx
-                    .resolve_function(verb)
-                    .with_context(|| make_ast_error(e))?;
+                let func = ctx.resolve_function(verb).at(e)?;
 
                 let r = apply(&func, &args[1..], ctx, settings);
                 match func.class {
@@ -1588,7 +1641,7 @@ pub fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Resul
                     _ => r,
                 }
             } else {
-                Err(anyhow!("not a function: `{:?}`", args[0])).with_context(|| make_ast_error(e))
+                Err(anyhow!("not a function: `{:?}`", args[0])).at(e)
             }
         }
         Token::DefColumn {
@@ -1674,7 +1727,7 @@ pub fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Resul
         | Token::DefInrange(..) => Ok(None),
         Token::BlockComment(_) | Token::InlineComment(_) => unreachable!(),
     }
-    .with_context(|| make_ast_error(e))
+    .at(e)
 }
 
 pub(crate) fn reduce_toplevel(
@@ -1911,15 +1964,15 @@ pub(crate) fn reduce_toplevel(
     }
 }
 
-pub fn make_ast_error(exp: &AstNode) -> String {
-    errors::parser::make_src_error(&exp.src, exp.lc)
-}
-
 pub fn pass(ast: &Ast, ctx: Scope, settings: &CompileSettings) -> Vec<Result<Constraint>> {
     let mut module = ctx;
 
     ast.exprs
         .iter()
-        .filter_map(|exp| reduce_toplevel(exp, &mut module, settings).transpose())
+        .filter_map(|exp| {
+            reduce_toplevel(exp, &mut module, settings)
+                .at(exp)
+                .transpose()
+        })
         .collect()
 }
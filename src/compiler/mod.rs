@@ -1,5 +1,6 @@
 use crate::{
     column::{ColumnSet, Computation},
+    field::Field,
     structs::Handle,
 };
 use anyhow::*;
@@ -15,8 +16,17 @@ pub use tables::ComputationTable;
 pub use types::*;
 
 use self::parser::Ast;
-use crate::{column::Column, compiler::tables::Symbol, errors::CompileError};
+use crate::{
+    column::Column,
+    compiler::tables::{Scope, Symbol},
+    errors::{
+        diagnostics::{Diagnostic, ErrorCollector},
+        CompileError,
+    },
+};
 
+mod binary;
+pub mod bytecode;
 pub mod codetyper;
 mod common;
 pub mod generator;
@@ -29,38 +39,61 @@ pub(crate) const MAIN_MODULE: &str = "<prelude>";
 
 pub struct CompileSettings {
     pub debug: bool,
+    /// how many errors to collect before giving up; 0 for no limit
+    pub max_errors: usize,
+    /// the field to compile the constraints for
+    pub field: Field,
 }
 
 pub fn make<S1: AsRef<str>, S2: AsRef<str>>(
     sources: &[(S1, S2)],
     settings: &CompileSettings,
 ) -> Result<(Vec<Ast>, ConstraintSet)> {
-    let (mut ctx, asts) = parser::parse(sources, settings)?;
+    let mut errors = ErrorCollector::new(settings.max_errors);
+    let (ctx, asts) = parser::parse(sources, settings, &mut errors)?;
+    generate(sources, ctx, asts, settings, &mut errors)
+}
 
+/// Reduce the `asts` parsed from `sources`, whose definitions have been
+/// gathered in `ctx`, into a constraint system.
+pub fn generate<S1: AsRef<str>, S2: AsRef<str>>(
+    sources: &[(S1, S2)],
+    mut ctx: Scope,
+    asts: Vec<(String, Ast)>,
+    settings: &CompileSettings,
+    errors: &mut ErrorCollector,
+) -> Result<(Vec<Ast>, ConstraintSet)> {
     //
     // Reduce the AST and create the constraints
     //
     let mut constraints = vec![];
-    for (name, ast) in asts.iter() {
+    for ((name, ast), (_, content)) in asts.iter().zip(sources.iter()) {
+        errors.enter(
+            name,
+            content.as_ref(),
+            format!("compiling {}", name.bright_white().bold()),
+        );
         for constraint in generator::pass(ast, ctx.clone(), settings) {
-            constraints.push(
-                constraint.with_context(|| anyhow!("compiling {}", name.bright_white().bold()))?,
-            );
+            if let Some(constraint) = errors.check(constraint)? {
+                constraints.push(constraint);
+            }
         }
     }
+    errors.finish()?;
     // Sort by decreasing complexity for more efficient multi-threaded computation
     constraints.sort_by_cached_key(|x| -(x.size() as isize));
 
     let mut columns: ColumnSet = Default::default();
     let mut constants: HashMap<Handle, BigInt> = Default::default();
     let mut computations = ctx.computations();
+    let mut unused = Vec::new();
 
     ctx.visit_mut::<()>(&mut |handle, symbol| {
         match symbol {
             Symbol::Alias(_) => {}
             Symbol::Final(symbol, used) => {
                 if !*used {
-                    warn!("{}", CompileError::NotUsed(handle.clone()));
+                    unused.push(handle.clone());
                 }
 
                 match symbol.e() {
@@ -135,6 +168,16 @@ pub fn make<S1: AsRef<str>, S2: AsRef<str>>(
         Ok(())
     })?;
 
+    for handle in unused.into_iter() {
+        warn!(
+            "{}",
+            Diagnostic::warning(
+                CompileError::NotUsed(handle.clone()).to_string(),
+                ctx.definition_site(&handle),
+            )
+        );
+    }
+
     let perspectives = ctx
         .tree
         .borrow()
@@ -154,7 +197,14 @@ pub fn make<S1: AsRef<str>, S2: AsRef<str>>(
         })
         .collect::<HashMap<_, _>>();
 
-    let mut cs = ConstraintSet::new(columns, constraints, constants, computations, perspectives)?;
+    let mut cs = ConstraintSet::new(
+        columns,
+        constraints,
+        constants,
+        computations,
+        perspectives,
+        settings.field,
+    )?;
     crate::transformer::precompute(&mut cs);
     Ok((asts.into_iter().map(|x| x.1).collect(), cs))
 }
@@ -1,4 +1,5 @@
 use crate::column::{ColumnID, Value};
+use crate::field::Field;
 use anyhow::*;
 use cached::Cached;
 use num_bigint::BigInt;
@@ -254,7 +255,10 @@ impl Node {
     pub fn with_debug(self, dbg: Option<String>) -> Self {
         Node { dbg, ..self }
     }
+    /// A column of type `t`; if `field` is set and its values may not fit in
+    /// one of its elements, an exo-column
     #[builder(entry = "column", exit = "build", visibility = "pub")]
+    #[allow(clippy::too_many_arguments)]
     pub fn new_column(
         handle: ColumnRef,
         shift: Option<i16>,
@@ -263,9 +267,10 @@ impl Node {
         padding_value: Option<i64>,
         must_prove: Option<bool>,
         t: Option<Magma>,
+        field: Option<Field>,
     ) -> Node {
         let magma = t.unwrap_or(Magma::native());
-        if magma.bit_size() > Magma::NATIVE.bit_size() {
+        if field.is_some_and(|field| magma.bit_size_in(field) > field.bit_size()) {
             Node {
                 _e: Expression::ExoColumn {
                     handle: handle.clone(),
@@ -433,8 +438,8 @@ impl Node {
         }
     }
 
-    pub fn bit_size(&self) -> usize {
-        self.t().m().bit_size()
+    pub fn bit_size_in(&self, field: Field) -> usize {
+        self.t().m().bit_size_in(field)
     }
 
     /// Return whether this [`Expression`] is susceptible to overflow withtin the field
@@ -482,6 +487,61 @@ impl Node {
         }
     }
 
+    /// The degree of NORM(self), once expanded to self × INV(self), unless
+    /// self is already binary
+    fn normalized_degree(&self) -> usize {
+        match self.degree() {
+            0 => 0,
+            d if self.t().is_binary() => d,
+            d => d + 1,
+        }
+    }
+
+    /// Compute the degree of the polynomial represented by [`Expression`]
+    /// once fully expanded; inverses are eventually replaced by columns, and
+    /// thus count as degree 1
+    pub fn degree(&self) -> usize {
+        match self.e() {
+            Expression::Funcall { func, args } => match func {
+                Intrinsic::Add
+                | Intrinsic::Sub
+                | Intrinsic::VectorAdd
+                | Intrinsic::VectorSub
+                | Intrinsic::Neg
+                | Intrinsic::Begin => args.iter().map(Node::degree).max().unwrap_or_default(),
+                Intrinsic::Mul | Intrinsic::VectorMul => args.iter().map(Node::degree).sum(),
+                Intrinsic::Exp => {
+                    args[0].degree()
+                        * args[1]
+                            .pure_eval()
+                            .ok()
+                            .and_then(|x| x.to_usize())
+                            .unwrap_or(1)
+                }
+                Intrinsic::Inv => args[0].degree().min(1),
+                Intrinsic::Normalize => args[0].normalized_degree(),
+                // Expanded to (1 - NORM(cond)) × then + cond × else, and
+                // conversely for IfNotZero
+                Intrinsic::IfZero | Intrinsic::IfNotZero => {
+                    let cond = args[0].degree();
+                    let norm = args[0].normalized_degree();
+                    let (then_factor, else_factor) = if matches!(func, Intrinsic::IfZero) {
+                        (norm, cond)
+                    } else {
+                        (cond, norm)
+                    };
+                    (then_factor + args[1].degree())
+                        .max(args.get(2).map(|e| else_factor + e.degree()).unwrap_or(0))
+                }
+            },
+            Expression::List(xs) => xs.iter().map(Node::degree).max().unwrap_or_default(),
+            Expression::Column { .. }
+            | Expression::ExoColumn { .. }
+            | Expression::ArrayColumn { .. } => 1,
+            Expression::Const(..) | Expression::Void => 0,
+        }
+    }
+
     /// Compute the maximum past (negative) shift coefficient in the AST rooted at `self`
     pub fn past_spill(&self) -> isize {
         self.leaves()
@@ -632,14 +692,14 @@ impl Node {
                 Intrinsic::Add => {
                     let mut ax = args[0].eval_fold(i, get, cache, settings, f)?;
                     for arg in args.iter().skip(1) {
-                        ax.add_assign(&arg.eval_fold(i, get, cache, settings, f)?)
+                        ax.add_assign(&arg.eval_fold(i, get, cache, settings, f)?, settings.field)
                     }
                     Some(ax)
                 }
                 Intrinsic::Sub => {
                     let mut ax = args[0].eval_fold(i, get, cache, settings, f)?;
                     for arg in args.iter().skip(1) {
-                        ax.sub_assign(&arg.eval_fold(i, get, cache, settings, f)?)
+                        ax.sub_assign(&arg.eval_fold(i, get, cache, settings, f)?, settings.field)
                     }
                     Some(ax)
                 }
@@ -649,28 +709,37 @@ impl Node {
                         if ax.is_zero() {
                             return Some(ax);
                         }
-                        ax.mul_assign(&arg.eval_fold(i, get, cache, settings, f)?)
+                        ax.mul_assign(&arg.eval_fold(i, get, cache, settings, f)?, settings.field)
                     }
                     Some(ax)
                 }
                 Intrinsic::VectorAdd => {
                     let mut ax = args[0].eval_fold(i, get, cache, settings, f)?;
                     for arg in args.iter().skip(1) {
-                        ax.vector_add_assign(&arg.eval_fold(i, get, cache, settings, f)?)
+                        ax.vector_add_assign(
+                            &arg.eval_fold(i, get, cache, settings, f)?,
+                            settings.field,
+                        )
                     }
                     Some(ax)
                 }
                 Intrinsic::VectorSub => {
                     let mut ax = args[0].eval_fold(i, get, cache, settings, f)?;
                     for arg in args.iter().skip(1) {
-                        ax.vector_sub_assign(&arg.eval_fold(i, get, cache, settings, f)?)
+                        ax.vector_sub_assign(
+                            &arg.eval_fold(i, get, cache, settings, f)?,
+                            settings.field,
+                        )
                     }
                     Some(ax)
                 }
                 Intrinsic::VectorMul => {
                     let mut ax = args[0].eval_fold(i, get, cache, settings, f)?;
                     for arg in args.iter().skip(1) {
-                        ax.vector_mul_assign(&arg.eval_fold(i, get, cache, settings, f)?)
+                        ax.vector_mul_assign(
+                            &arg.eval_fold(i, get, cache, settings, f)?,
+                            settings.field,
+                        )
                     }
                     Some(ax)
                 }
@@ -679,12 +748,12 @@ impl Node {
                     let mut ax = mantissa.clone();
                     let exp = args[1].pure_eval().unwrap().to_usize().unwrap();
                     for _ in 1..exp {
-                        ax.mul_assign(&mantissa);
+                        ax.mul_assign(&mantissa, settings.field);
                     }
                     Some(ax)
                 }
                 Intrinsic::Neg => args[0].eval_fold(i, get, cache, settings, f).map(|mut x| {
-                    x.negate();
+                    x.negate(settings.field);
                     x
                 }),
                 Intrinsic::Inv => {
@@ -692,11 +761,11 @@ impl Node {
                     if let Some(ref mut rcache) = cache {
                         x.map(|x| {
                             rcache
-                                .cache_get_or_set_with(x.clone(), || x.inverse())
+                                .cache_get_or_set_with(x.clone(), || x.inverse(settings.field))
                                 .to_owned()
                         })
                     } else {
-                        x.map(|x| x.inverse())
+                        x.map(|x| x.inverse(settings.field))
                     }
                 }
                 Intrinsic::Normalize => args[0]
@@ -984,7 +1053,7 @@ impl Node {
         }
 
         let mut tty = Tty::new().with_guides();
-        let faulty = f(self).unwrap_or_else(Value::fr_zero);
+        let faulty = f(self).unwrap_or_else(Value::zero);
         _debug(
             self, &mut tty, f, &faulty, unclutter, dim, false, true, src, true,
         );
@@ -1004,6 +1073,80 @@ impl Node {
         ax
     }
 }
+/// Nodes are compared and hashed structurally, i.e. regardless of their
+/// types, column kinds or debug information; constants are compared by value,
+/// whatever their representation
+impl std::cmp::PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.e(), other.e()) {
+            (
+                Expression::Funcall { func: f1, args: a1 },
+                Expression::Funcall { func: f2, args: a2 },
+            ) => f1 == f2 && a1 == a2,
+            (Expression::Const(x1), Expression::Const(x2)) => x1.to_bi() == x2.to_bi(),
+            (
+                Expression::Column {
+                    handle: h1,
+                    shift: s1,
+                    ..
+                },
+                Expression::Column {
+                    handle: h2,
+                    shift: s2,
+                    ..
+                },
+            )
+            | (
+                Expression::ExoColumn {
+                    handle: h1,
+                    shift: s1,
+                    ..
+                },
+                Expression::ExoColumn {
+                    handle: h2,
+                    shift: s2,
+                    ..
+                },
+            ) => h1 == h2 && s1 == s2,
+            (
+                Expression::ArrayColumn {
+                    handle: h1,
+                    domain: d1,
+                    ..
+                },
+                Expression::ArrayColumn {
+                    handle: h2,
+                    domain: d2,
+                    ..
+                },
+            ) => h1 == h2 && d1.to_string() == d2.to_string(),
+            (Expression::List(xs1), Expression::List(xs2)) => xs1 == xs2,
+            (Expression::Void, Expression::Void) => true,
+            _ => false,
+        }
+    }
+}
+impl std::cmp::Eq for Node {}
+impl std::hash::Hash for Node {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self.e()).hash(state);
+        match self.e() {
+            Expression::Funcall { func, args } => {
+                func.hash(state);
+                args.hash(state);
+            }
+            Expression::Const(x) => x.to_bi().hash(state),
+            Expression::Column { handle, shift, .. }
+            | Expression::ExoColumn { handle, shift, .. } => {
+                handle.hash(state);
+                shift.hash(state);
+            }
+            Expression::ArrayColumn { handle, .. } => handle.hash(state),
+            Expression::List(xs) => xs.hash(state),
+            Expression::Void => {}
+        }
+    }
+}
 impl Display for Node {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         fn format_list(cs: &[Node]) -> String {
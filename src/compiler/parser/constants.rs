@@ -1,6 +1,7 @@
 use anyhow::*;
 
-use crate::compiler::{generator::make_ast_error, tables::Scope, CompileSettings, Node};
+use crate::compiler::{tables::Scope, CompileSettings, Node};
+use crate::errors::diagnostics::{ErrorCollector, Locate};
 
 use super::{Ast, AstNode, Token};
 
@@ -22,11 +23,8 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
                     }
                     _ => crate::compiler::generator::reduce(exp, ctx, settings)?.unwrap(),
                 };
-                ctx.insert_constant(
-                    name,
-                    value.pure_eval().with_context(|| make_ast_error(exp))?,
-                    true,
-                )?;
+                ctx.insert_constant(name, value.pure_eval().at(exp)?, true)?;
+                ctx.set_definition_site(name, e);
             }
             Ok(())
         }
@@ -37,10 +35,20 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
 /// The `Definitions` pass skim through an [`Ast`] and fill the
 /// [`SymbolTableTree`] with all the required elements (columns, functions,
 /// perspectives, constraints, aliases, ...)
-pub fn pass(ast: &Ast, ctx: Scope, settings: &CompileSettings) -> Result<()> {
+pub fn pass(
+    ast: &Ast,
+    ctx: Scope,
+    settings: &CompileSettings,
+    errors: &mut ErrorCollector,
+) -> Result<()> {
     let mut module = ctx;
     for e in ast.exprs.iter() {
-        reduce(e, &mut module, settings)?;
+        if let Err(err) = reduce(e, &mut module, settings).at(e) {
+            for name in e.defined_names() {
+                module.poison(name);
+            }
+            errors.push(err)?;
+        }
     }
 
     Ok(())
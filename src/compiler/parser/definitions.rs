@@ -7,6 +7,7 @@ use owo_colors::OwoColorize;
 use crate::compiler::generator::{self, Defined, Function, FunctionClass, Specialization};
 use crate::compiler::tables::Scope;
 use crate::compiler::{CompileSettings, Magma, Node};
+use crate::errors::diagnostics::{ErrorCollector, Locate};
 use crate::structs::Handle;
 use crate::utils::hash_strings;
 
@@ -32,14 +33,14 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
         }
         Token::DefColumns(columns) => columns
             .iter()
-            .fold(Ok(()), |ax, col| ax.and(reduce(col, ctx, settings))),
+            .fold(Ok(()), |ax, col| ax.and(reduce(col, ctx, settings).at(col))),
         Token::DefPerspective { name, columns, .. } => {
             let mut new_ctx = ctx
                 .derive(&format!("in-{}", name))?
                 .public(true)
                 .with_perspective(name)?;
             columns.iter().fold(Ok(()), |ax, col| {
-                ax.and(reduce(col, &mut new_ctx, settings))
+                ax.and(reduce(col, &mut new_ctx, settings).at(col))
             })
         }
         Token::DefColumn {
@@ -64,10 +65,13 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
                 })
                 .and_padding_value(*padding_value)
                 .t(t.m())
+                .field(settings.field)
                 .must_prove(*must_prove)
                 .base(*base)
                 .build();
-            ctx.insert_symbol(name, symbol)
+            ctx.insert_symbol(name, symbol)?;
+            ctx.set_definition_site(name, e);
+            Ok(())
         }
         Token::DefArrayColumn {
             name,
@@ -104,6 +108,7 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
                         .kind(Kind::Commitment)
                         .and_padding_value(*padding_value)
                         .t(t.m())
+                        .field(settings.field)
                         .must_prove(*must_prove)
                         .base(*base)
                         .build(),
@@ -120,6 +125,7 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
                     .t(t.m())
                     .build(),
             )?;
+            ctx.set_definition_site(name, e);
             Ok(())
         }
         Token::DefInterleaving { target, froms } => {
@@ -138,9 +144,12 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
                         .unwrap()
                         .map(|s| s.t().m().max(ax))
                 })?)
+                .field(settings.field)
                 .build();
 
-            ctx.insert_symbol(&target.name, node)
+            ctx.insert_symbol(&target.name, node)?;
+            ctx.set_definition_site(&target.name, e);
+            Ok(())
         }
         Token::DefPermutation {
             from: froms,
@@ -183,16 +192,18 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
                         ))
                         .kind(Kind::Computed)
                         .t(from_m)
+                        .field(settings.field)
                         .base(to.base)
                         .build(),
                 )
                 .with_context(|| anyhow!("while defining permutation: {}", e))?;
+                ctx.set_definition_site(&to.name, e);
             }
             Ok(())
         }
-        Token::DefAliases(aliases) => aliases
-            .iter()
-            .fold(Ok(()), |ax, alias| ax.and(reduce(alias, ctx, settings))),
+        Token::DefAliases(aliases) => aliases.iter().fold(Ok(()), |ax, alias| {
+            ax.and(reduce(alias, ctx, settings).at(alias))
+        }),
         Token::Defun {
             name,
             args,
@@ -217,7 +228,9 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
                         }],
                     }),
                 },
-            )
+            )?;
+            ctx.set_definition_site(name, e);
+            Ok(())
         }
         Token::DefAlias(from, to) => {
             let _ = ctx
@@ -225,7 +238,9 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
                 .with_context(|| anyhow!("while defining alias `{}`", from))?;
 
             ctx.insert_alias(from, to)
-                .with_context(|| anyhow!("defining {} -> {}", from, to))
+                .with_context(|| anyhow!("defining {} -> {}", from, to))?;
+            ctx.set_definition_site(from, e);
+            Ok(())
         }
         Token::DefunAlias(from, to) => ctx
             .insert_funalias(from, to)
@@ -237,10 +252,20 @@ fn reduce(e: &AstNode, ctx: &mut Scope, settings: &CompileSettings) -> Result<()
 /// The `Definitions` pass skim through an [`Ast`] and fill the
 /// [`SymbolTableTree`] with all the required elements (columns, functions,
 /// perspectives, constraints, aliases, ...)
-pub fn pass(ast: &Ast, ctx: Scope, settings: &CompileSettings) -> Result<()> {
+pub fn pass(
+    ast: &Ast,
+    ctx: Scope,
+    settings: &CompileSettings,
+    errors: &mut ErrorCollector,
+) -> Result<()> {
     let mut module = ctx;
     for e in ast.exprs.iter() {
-        reduce(e, &mut module, settings)?;
+        if let Err(err) = reduce(e, &mut module, settings).at(e) {
+            for name in e.defined_names() {
+                module.poison(name);
+            }
+            errors.push(err)?;
+        }
     }
 
     Ok(())
@@ -7,7 +7,10 @@ use self::parser::DisplayableColumn;
 
 use crate::{
     compiler::{tables::Scope, Type},
-    errors::symbols,
+    errors::{
+        diagnostics::{self, ErrorCollector, Locate},
+        symbols,
+    },
     pretty::Base,
 };
 
@@ -19,7 +22,7 @@ mod fmtparser;
 pub(crate) mod parser;
 mod purefuns;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Ast {
     pub exprs: Vec<AstNode>,
 }
@@ -113,6 +116,25 @@ impl AstNode {
     pub fn is_inline_comment(&self) -> bool {
         matches!(self.class, Token::InlineComment(_))
     }
+    /// Returns the names of the symbols and functions defined by this node
+    pub fn defined_names(&self) -> Vec<&str> {
+        match &self.class {
+            Token::DefConsts(cs) => cs.iter().map(|(name, _)| name.as_str()).collect(),
+            Token::DefColumns(cs) | Token::DefPerspective { columns: cs, .. } => {
+                cs.iter().flat_map(|c| c.defined_names()).collect()
+            }
+            Token::DefColumn { name, .. }
+            | Token::DefArrayColumn { name, .. }
+            | Token::Defun { name, .. }
+            | Token::Defpurefun { name, .. }
+            | Token::DefAlias(name, _)
+            | Token::DefunAlias(name, _) => vec![name],
+            Token::DefAliases(aliases) => aliases.iter().flat_map(|a| a.defined_names()).collect(),
+            Token::DefInterleaving { target, .. } => vec![&target.name],
+            Token::DefPermutation { to, .. } => to.iter().map(|c| c.name.as_str()).collect(),
+            _ => vec![],
+        }
+    }
 }
 impl std::fmt::Debug for AstNode {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -421,7 +443,7 @@ impl std::fmt::Debug for Token {
 }
 
 pub(crate) fn maybe_bail<R>(errs: Vec<Result<R>>) -> Result<Vec<R>> {
-    let mut err_count = 0;
+    let mut errors = vec![];
     let mut r = vec![];
 
     for e in errs.into_iter() {
@@ -430,14 +452,13 @@ pub(crate) fn maybe_bail<R>(errs: Vec<Result<R>>) -> Result<Vec<R>> {
                 r.push(o);
             }
             Err(e) => {
-                err_count += 1;
-                error!("{:?}", e);
+                errors.push(e);
             }
         }
     }
 
-    if err_count > 0 {
-        bail!("{} errors found", err_count)
+    if !errors.is_empty() {
+        bail!(diagnostics::Errors(errors))
     } else {
         Ok(r)
     }
@@ -454,7 +475,11 @@ pub(crate) fn parse_ast<S1: AsRef<str>, S2: AsRef<str>>(
             .map(|(name, content)| {
                 info!("Parsing {}", name.as_ref().bright_white().bold());
                 parser::parse(content.as_ref())
-                    .with_context(|| anyhow!("parsing `{}`", name.as_ref()))
+                    .in_file(
+                        name.as_ref(),
+                        content.as_ref(),
+                        format!("parsing `{}`", name.as_ref()),
+                    )
                     .map(|ast| (name.as_ref().to_string(), ast))
             })
             .collect::<Vec<_>>(),
@@ -475,19 +500,27 @@ pub fn parse_simple_ast<S1: AsRef<str>, S2: AsRef<str>>(
             .map(|(name, content)| {
                 info!("Parsing {}", name.as_ref().bright_white().bold());
                 fmtparser::parse(content.as_ref())
-                    .with_context(|| anyhow!("parsing `{}`", name.as_ref()))
+                    .in_file(
+                        name.as_ref(),
+                        content.as_ref(),
+                        format!("parsing `{}`", name.as_ref()),
+                    )
                     .map(|ast| (name.as_ref().to_string(), ast))
             })
             .collect::<Vec<_>>(),
     )
 }
 
+/// Given a list of sources and their names, parse them and fill a [`Scope`]
+/// with their definitions.
+///
+/// Failing definitions are collected in `errors` and poisoned in the scope,
+/// so that the caller may carry on and find further errors.
 pub fn parse<S1: AsRef<str>, S2: AsRef<str>>(
     sources: &[(S1, S2)],
     settings: &CompileSettings,
+    errors: &mut ErrorCollector,
 ) -> Result<(Scope, Vec<(String, Ast)>)> {
-    let ctx = Scope::new();
-
     //
     // Parse the source into an AST
     //
@@ -497,12 +530,32 @@ pub fn parse<S1: AsRef<str>, S2: AsRef<str>>(
             .map(|(name, content)| {
                 info!("Parsing {}", name.as_ref().bright_white().bold());
                 parser::parse(content.as_ref())
-                    .with_context(|| anyhow!("parsing `{}`", name.as_ref()))
+                    .in_file(
+                        name.as_ref(),
+                        content.as_ref(),
+                        format!("parsing `{}`", name.as_ref()),
+                    )
                     .map(|ast| (name.as_ref().to_string(), ast))
             })
             .collect::<Vec<_>>(),
     )?;
 
+    define(sources, &asts, settings, errors).map(|ctx| (ctx, asts))
+}
+
+/// Fill a [`Scope`] with the definitions found in the already parsed `asts`
+/// of the given sources.
+///
+/// Failing definitions are collected in `errors` and poisoned in the scope,
+/// so that the caller may carry on and find further errors.
+pub fn define<S1: AsRef<str>, S2: AsRef<str>>(
+    sources: &[(S1, S2)],
+    asts: &[(String, Ast)],
+    settings: &CompileSettings,
+    errors: &mut ErrorCollector,
+) -> Result<Scope> {
+    let ctx = Scope::new();
+
     // The parsing order is crucial to make const. expr. work. Therefore, it
     // must be:
     // 1 - pure functions, which are dependent on constants at run-time but
@@ -512,20 +565,35 @@ pub fn parse<S1: AsRef<str>, S2: AsRef<str>>(
     // 3 - the remaining elements, which may be dependent on everything else.
 
     // 1. Pure functions
-    for (name, ast) in asts.iter() {
-        purefuns::pass(&ast, ctx.clone())
-            .with_context(|| anyhow!("parsing definitions in `{}`", name))?;
+    for ((name, content), (_, ast)) in sources.iter().zip(asts.iter()) {
+        ctx.set_source(name.as_ref(), content.as_ref());
+        errors.enter(
+            name.as_ref(),
+            content.as_ref(),
+            format!("parsing definitions in `{}`", name.as_ref()),
+        );
+        purefuns::pass(ast, ctx.clone(), errors)?;
     }
     // 2. Constants
-    for (name, ast) in asts.iter() {
-        constants::pass(&ast, ctx.clone(), settings)
-            .with_context(|| anyhow!("parsing definitions in `{}`", name))?;
+    for ((name, content), (_, ast)) in sources.iter().zip(asts.iter()) {
+        ctx.set_source(name.as_ref(), content.as_ref());
+        errors.enter(
+            name.as_ref(),
+            content.as_ref(),
+            format!("parsing definitions in `{}`", name.as_ref()),
+        );
+        constants::pass(ast, ctx.clone(), settings, errors)?;
     }
     // 3. The rest
-    for (name, ast) in asts.iter() {
-        definitions::pass(&ast, ctx.clone(), settings)
-            .with_context(|| anyhow!("parsing definitions in `{}`", name))?;
+    for ((name, content), (_, ast)) in sources.iter().zip(asts.iter()) {
+        ctx.set_source(name.as_ref(), content.as_ref());
+        errors.enter(
+            name.as_ref(),
+            content.as_ref(),
+            format!("parsing definitions in `{}`", name.as_ref()),
+        );
+        definitions::pass(ast, ctx.clone(), settings, errors)?;
     }
 
-    Ok((ctx, asts))
+    Ok(ctx)
 }
@@ -261,8 +261,11 @@ fn parse_column_attributes(source: AstNode) -> Result<ColumnAttributes> {
                             if let Some(caps) = re_type.captures(kw) {
                                 let raw_magma = if let Some(integer) = caps.name("Integer") {
                                     let bit_size = integer.as_str().parse::<usize>().unwrap();
-                                    if bit_size > crate::constants::FIELD_BITSIZE {
-                                        panic!("Not yet :(");
+                                    if bit_size > crate::constants::MAX_BIT_SIZE {
+                                        bail!(
+                                            "integers wider than {} bits are not supported",
+                                            crate::constants::MAX_BIT_SIZE
+                                        );
                                     }
                                     RawMagma::Integer(bit_size)
                                 } else {
@@ -2,6 +2,7 @@ use anyhow::*;
 
 use crate::compiler::generator::{Defined, Function, FunctionClass, Specialization};
 use crate::compiler::tables::Scope;
+use crate::errors::diagnostics::{ErrorCollector, Locate};
 use crate::structs::Handle;
 
 use super::{Ast, AstNode, Token};
@@ -36,7 +37,9 @@ fn reduce(e: &AstNode, ctx: &mut Scope) -> Result<()> {
                         }],
                     }),
                 },
-            )
+            )?;
+            ctx.set_definition_site(name, e);
+            Ok(())
         }
         _ => Ok(()),
     }
@@ -45,10 +48,15 @@ fn reduce(e: &AstNode, ctx: &mut Scope) -> Result<()> {
 /// The `Definitions` pass skim through an [`Ast`] and fill the
 /// [`SymbolTableTree`] with all the required elements (columns, functions,
 /// perspectives, constraints, aliases, ...)
-pub fn pass(ast: &Ast, ctx: Scope) -> Result<()> {
+pub fn pass(ast: &Ast, ctx: Scope, errors: &mut ErrorCollector) -> Result<()> {
     let mut module = ctx;
     for e in ast.exprs.iter() {
-        reduce(e, &mut module)?;
+        if let Err(err) = reduce(e, &mut module).at(e) {
+            for name in e.defined_names() {
+                module.poison(name);
+            }
+            errors.push(err)?;
+        }
     }
 
     Ok(())
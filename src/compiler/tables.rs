@@ -1,8 +1,12 @@
 use super::{generator::Function, ColumnRef, Expression, Magma, Node, Type};
 use crate::{
     column::Computation,
+    compiler::parser::AstNode,
     compiler::{generator::FunctionClass, Builtin, Form, Intrinsic},
-    errors::symbols,
+    errors::{
+        diagnostics::{Label, Labelled, Span},
+        symbols,
+    },
     structs::{Handle, PERSPECTIVE_SEPARATOR},
 };
 use anyhow::*;
@@ -195,6 +199,12 @@ pub enum Symbol {
 pub struct GlobalData {
     computations: ComputationTable,
     pub perspectives: HashMap<String, HashMap<String, Option<Node>>>, // module -> {Perspectives}
+    /// the source file currently being processed, and its content
+    source: Option<(String, String)>,
+    /// where the symbols and functions have been defined
+    definition_sites: HashMap<Handle, Span>,
+    /// the symbols and functions whose definition failed
+    poisoned: HashSet<Handle>,
 }
 impl GlobalData {
     pub fn set_perspective_trigger(
@@ -417,6 +427,86 @@ impl Scope {
         data!(self).perspective.clone()
     }
 
+    /// Returns the scopes of all the modules defined in the tree
+    pub fn modules(&self) -> Vec<Scope> {
+        self.root().children()
+    }
+
+    /// Returns the names of the symbols directly defined in this scope
+    pub fn symbol_names(&self) -> Vec<String> {
+        data!(self).symbols.keys().cloned().collect()
+    }
+
+    /// Returns the names of the functions directly defined in this scope
+    pub fn function_names(&self) -> Vec<String> {
+        data!(self).funcs.keys().cloned().collect()
+    }
+
+    /// Set the source file from which the symbols are currently defined
+    pub fn set_source(&self, name: &str, content: &str) {
+        self.tree.borrow_mut().metadata_mut().source = Some((name.to_owned(), content.to_owned()));
+    }
+
+    /// Record that `name` is defined by `node` in the current module
+    pub fn set_definition_site(&self, name: &str, node: &AstNode) {
+        let handle = Handle::new(self.module(), name);
+        let mut tree = self.tree.borrow_mut();
+        let metadata = tree.metadata_mut();
+        if let Some((file, content)) = metadata.source.as_ref() {
+            let span = Span::new(file, content, node.lc, &node.src);
+            metadata.definition_sites.insert(handle, span);
+        }
+    }
+
+    /// Returns where the given symbol has been defined, if it is known
+    pub fn definition_site(&self, handle: &Handle) -> Option<Span> {
+        self.tree
+            .borrow()
+            .metadata()
+            .definition_sites
+            .get(handle)
+            .cloned()
+    }
+
+    /// Attach to `err` the location where `name` has previously been defined
+    /// in the current module, if it is known
+    fn previously_defined(&self, name: &str, err: symbols::Error) -> Error {
+        match self.definition_site(&Handle::new(self.module(), name)) {
+            Some(span) => Labelled {
+                label: Label {
+                    span,
+                    message: "previously defined here".to_owned(),
+                },
+                source: err.into(),
+            }
+            .into(),
+            None => err.into(),
+        }
+    }
+
+    /// Mark `name` as poisoned in the current module, i.e. its definition
+    /// failed and any further error caused by its absence is a consequence
+    /// of this failure
+    pub fn poison(&self, name: &str) {
+        let handle = Handle::new(self.module(), name);
+        self.tree
+            .borrow_mut()
+            .metadata_mut()
+            .poisoned
+            .insert(handle);
+    }
+
+    /// Whether `name` is poisoned in this scope or in one of its parents
+    fn is_poisoned(&self, name: &str) -> bool {
+        let poisoned = self
+            .tree
+            .borrow()
+            .metadata()
+            .poisoned
+            .contains(&Handle::new(self.module(), name));
+        poisoned || self.parent().map(|p| p.is_poisoned(name)).unwrap_or(false)
+    }
+
     pub fn computations(&self) -> ComputationTable {
         self.tree.borrow().metadata().computations.clone()
     }
@@ -508,13 +598,16 @@ impl Scope {
             let name = s
                 .next()
                 .ok_or_else(|| symbols::Error::MissingPerspective(name.into()))?;
-            Self::_resolve_symbol_in_perspective(
+            let r = Self::_resolve_symbol_in_perspective(
                 self.id,
                 &mut self.tree.borrow_mut(),
                 name,
                 perspective,
-            )
-            .map_err(|e| match e {
+            );
+            r.map_err(|e| match e {
+                symbols::Error::SymbolNotFound(s, _, _) if self.is_poisoned(&s) => {
+                    symbols::Error::Poisoned(s)
+                }
                 symbols::Error::SymbolNotFound(s, _, _) => {
                     symbols::Error::SymbolNotFound(s, module, Some(perspective.into()))
                 }
@@ -524,15 +617,21 @@ impl Scope {
                 _ => unreachable!(),
             })
         } else {
-            Self::_resolve_symbol(
+            let r = Self::_resolve_symbol(
                 self.id,
                 &mut self.tree.borrow_mut(),
                 name,
                 &mut HashSet::new(),
                 false,
                 false,
-            )
-            .map_err(|_| symbols::Error::SymbolNotFound(name.to_owned(), module, None))
+            );
+            r.map_err(|_| {
+                if self.is_poisoned(name) {
+                    symbols::Error::Poisoned(name.to_owned())
+                } else {
+                    symbols::Error::SymbolNotFound(name.to_owned(), module, None)
+                }
+            })
         }
     }
 
@@ -696,9 +795,9 @@ impl Scope {
             bail!("names starting with `#` are reserved for intenal usage")
         }
         if data!(self).symbols.contains_key(name) {
-            bail!(symbols::Error::SymbolAlreadyExists(
-                name.to_owned(),
-                data!(self).name.to_owned()
+            Err(self.previously_defined(
+                name,
+                symbols::Error::SymbolAlreadyExists(name.to_owned(), data!(self).name.to_owned()),
             ))
         } else {
             data_mut!(self)
@@ -722,17 +821,23 @@ impl Scope {
         // functions, thus they can only be defined once.
         match &f.class {
             FunctionClass::UserDefined(new_specialization) => {
+                let conflicting = data!(self)
+                    .funcs
+                    .get(name)
+                    .map(|f| !matches!(f.class, FunctionClass::UserDefined(_)))
+                    .unwrap_or(false);
+                if conflicting {
+                    return Err(self.previously_defined(
+                        name,
+                        symbols::Error::FunctionAlreadyExists(name.to_owned(), my_name),
+                    ));
+                }
                 if let Some(Function { ref mut class, .. }) = data_mut!(self).funcs.get_mut(name) {
                     return match class {
                         FunctionClass::UserDefined(ref mut defined) => defined
                             .add_specialization(new_specialization)
                             .with_context(|| anyhow!("while defining {}", name.yellow())),
-                        _ => {
-                            bail!(symbols::Error::FunctionAlreadyExists(
-                                name.to_owned(),
-                                my_name,
-                            ))
-                        }
+                        _ => unreachable!(),
                     };
                 }
 
@@ -742,9 +847,12 @@ impl Scope {
             }
             _ => {
                 if data!(self).funcs.contains_key(name) {
-                    bail!(symbols::Error::FunctionAlreadyExists(
-                        name.to_owned(),
-                        data!(self).name.to_owned()
+                    Err(self.previously_defined(
+                        name,
+                        symbols::Error::FunctionAlreadyExists(
+                            name.to_owned(),
+                            data!(self).name.to_owned(),
+                        ),
                     ))
                 } else {
                     data_mut!(self).funcs.insert(name.to_owned(), f);
@@ -756,9 +864,9 @@ impl Scope {
 
     pub fn insert_alias(&mut self, from: &str, to: &str) -> Result<()> {
         if data!(self).symbols.contains_key(from) {
-            bail!(symbols::Error::SymbolAlreadyExists(
-                from.to_owned(),
-                data!(self).name.to_owned()
+            Err(self.previously_defined(
+                from,
+                symbols::Error::SymbolAlreadyExists(from.to_owned(), data!(self).name.to_owned()),
             ))
         } else {
             data_mut!(self)
@@ -799,6 +907,13 @@ impl Scope {
 
     pub fn resolve_function(&self, name: &str) -> Result<Function> {
         self._resolve_function(name, &mut HashSet::new())
+            .map_err(|e| {
+                if self.is_poisoned(name) {
+                    anyhow!(symbols::Error::Poisoned(name.to_owned()))
+                } else {
+                    e
+                }
+            })
     }
 
     pub fn insert_constant(&mut self, name: &str, value: BigInt, replace: bool) -> Result<()> {
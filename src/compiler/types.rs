@@ -1,10 +1,11 @@
 #![allow(dead_code)]
 use anyhow::*;
+use num_bigint::BigInt;
 use owo_colors::OwoColorize;
 use serde::{Deserialize, Serialize};
-use std::{cmp::Ordering, sync::OnceLock};
+use std::cmp::Ordering;
 
-use crate::{column::Value, constants, errors::RuntimeError};
+use crate::{column::Value, constants, errors::RuntimeError, field::Field};
 
 pub fn max_type<'a, TS: IntoIterator<Item = &'a Type>>(ts: TS) -> Result<Type> {
     ts.into_iter().try_fold(Type::INFIMUM, |a, b| a.maxed(b))
@@ -292,8 +293,10 @@ impl TryFrom<&str> for Conditioning {
     }
 }
 
-static F_15: OnceLock<Value> = OnceLock::new();
-static F_255: OnceLock<Value> = OnceLock::new();
+/// The width at which field elements are ranked against integers in the type
+/// lattice, so that typing does not depend on the field; this is the width of
+/// the elements of the widest supported field
+const NATIVE_RANK: usize = 254;
 
 // TODO: implement PartialOrd
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash, Ord, PartialOrd)]
@@ -312,24 +315,34 @@ pub enum RawMagma {
     Any,
 }
 impl RawMagma {
-    fn bit_size(&self) -> usize {
+    /// The bit size of the values of this magma in `field`
+    fn bit_size_in(&self, field: Field) -> usize {
         match self {
             RawMagma::None => 0,
             RawMagma::Binary => 1,
             RawMagma::Nibble => 4,
             RawMagma::Byte => 8,
-            RawMagma::Native => crate::constants::FIELD_BITSIZE,
+            RawMagma::Native => field.bit_size(),
             RawMagma::Integer(x) => *x,
-            RawMagma::Any => crate::constants::FIELD_BITSIZE,
+            RawMagma::Any => field.bit_size(),
         }
     }
 
-    fn byte_size(&self) -> usize {
-        let bit_size = self.bit_size();
-        (bit_size + 8 - 1) / 8
+    /// The width at which this magma is ranked in the type lattice, which
+    /// is the same whatever the field
+    fn rank(&self) -> usize {
+        match self {
+            RawMagma::None => 0,
+            RawMagma::Binary => 1,
+            RawMagma::Nibble => 4,
+            RawMagma::Byte => 8,
+            RawMagma::Native | RawMagma::Any => NATIVE_RANK,
+            RawMagma::Integer(x) => *x,
+        }
     }
 
-    pub fn validate(&self, x: Value) -> Result<Value> {
+    /// Ensure that `x`, an element of `field`, belongs to this magma
+    pub fn validate(&self, x: Value, field: Field) -> Result<Value> {
         match self {
             RawMagma::None => unreachable!(),
             RawMagma::Binary => {
@@ -340,24 +353,32 @@ impl RawMagma {
                 }
             }
             RawMagma::Nibble => {
-                if x.le(F_15.get_or_init(|| Value::from(15))) {
+                if x.to_bi() <= BigInt::from(15) {
                     Ok(x)
                 } else {
                     bail!(RuntimeError::InvalidValue("nibble", x))
                 }
             }
             RawMagma::Byte => {
-                if x.le(F_255.get_or_init(|| Value::from(255))) {
+                if x.to_bi() <= BigInt::from(255) {
                     Ok(x)
                 } else {
                     bail!(RuntimeError::InvalidValue("byte", x))
                 }
             }
-            RawMagma::Native => {
+            RawMagma::Native if field.is_ark() => {
                 let bit_size = x.bit_size();
-                if bit_size > constants::FIELD_BITSIZE {
+                if bit_size > field.bit_size() {
                     Err(anyhow!(RuntimeError::InvalidValue("field element", x)))
-                        .with_context(|| format!("{}b > {}b", bit_size, constants::FIELD_BITSIZE))
+                        .with_context(|| format!("{}b > {}b", bit_size, field.bit_size()))
+                } else {
+                    Ok(x)
+                }
+            }
+            RawMagma::Native => {
+                if &x.to_bi() >= field.modulus() {
+                    Err(anyhow!(RuntimeError::InvalidValue("field element", x)))
+                        .with_context(|| format!("not smaller than {}", field.modulus()))
                 } else {
                     Ok(x)
                 }
@@ -448,12 +469,13 @@ impl Magma {
         matches!(self.m, RawMagma::Binary)
     }
 
-    pub fn bit_size(&self) -> usize {
-        self.m.bit_size()
+    /// The bit size of the values of this magma in `field`
+    pub fn bit_size_in(&self, field: Field) -> usize {
+        self.m.bit_size_in(field)
     }
 
-    pub fn byte_size(&self) -> usize {
-        (self.m.bit_size() + 7) / 8
+    pub fn byte_size_in(&self, field: Field) -> usize {
+        (self.m.bit_size_in(field) + 7) / 8
     }
 
     pub fn invert(&self) -> Magma {
@@ -499,11 +521,9 @@ impl Magma {
             (RawMagma::Integer(_), RawMagma::Nibble)
             | (RawMagma::Integer(_), RawMagma::Byte)
             | (RawMagma::Integer(_), RawMagma::Native)
-            | (RawMagma::Integer(_), RawMagma::Integer(_)) => {
-                self.m.bit_size() <= other.m.bit_size()
-            }
+            | (RawMagma::Integer(_), RawMagma::Integer(_)) => self.m.rank() <= other.m.rank(),
 
-            (_, RawMagma::Integer(_)) => self.m.bit_size() >= other.m.bit_size(),
+            (_, RawMagma::Integer(_)) => self.m.rank() >= other.m.rank(),
 
             (RawMagma::Any, RawMagma::Any) => true,
             (RawMagma::Any, _) => false,
@@ -572,8 +592,11 @@ impl std::convert::TryFrom<&str> for Magma {
 
             let raw_magma = if let Some(integer) = caps.name("Integer") {
                 let bit_size = integer.as_str().parse::<usize>().unwrap();
-                if bit_size > constants::FIELD_BITSIZE {
-                    panic!("Not yet :(");
+                if bit_size > constants::MAX_BIT_SIZE {
+                    bail!(
+                        "integers wider than {} bits are not supported",
+                        constants::MAX_BIT_SIZE
+                    );
                 }
                 RawMagma::Integer(bit_size)
             } else {
@@ -630,9 +653,7 @@ impl std::cmp::PartialOrd for Magma {
             (RawMagma::Native, RawMagma::Nibble) => Some(Ordering::Greater),
             (RawMagma::Native, RawMagma::Byte) => Some(Ordering::Greater),
             (RawMagma::Native, RawMagma::Native) => Some(Ordering::Equal),
-            (RawMagma::Native, RawMagma::Integer(_)) => {
-                Some(self.m.bit_size().cmp(&other.m.bit_size()))
-            }
+            (RawMagma::Native, RawMagma::Integer(_)) => Some(self.m.rank().cmp(&other.m.rank())),
 
             (RawMagma::Any, RawMagma::Any) => Some(Ordering::Equal),
             (RawMagma::Any, _) => Some(Ordering::Greater),
@@ -642,11 +663,9 @@ impl std::cmp::PartialOrd for Magma {
             (RawMagma::Integer(_), RawMagma::Binary) => Some(Ordering::Greater),
             (RawMagma::Integer(_), RawMagma::Nibble) => Some(Ordering::Greater),
             (RawMagma::Integer(_), RawMagma::Byte) => Some(Ordering::Greater),
-            (RawMagma::Integer(_), RawMagma::Native) => {
-                Some(self.m.bit_size().cmp(&other.m.bit_size()))
-            }
+            (RawMagma::Integer(_), RawMagma::Native) => Some(self.m.rank().cmp(&other.m.rank())),
             (RawMagma::Integer(_), RawMagma::Integer(_)) => {
-                Some(self.m.bit_size().cmp(&other.m.bit_size()))
+                Some(self.m.rank().cmp(&other.m.rank()))
             }
         }
     }
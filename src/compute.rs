@@ -2,14 +2,26 @@ use anyhow::{anyhow, bail, Context, Result};
 use itertools::Itertools;
 use log::*;
 use logging_timer::time;
+use num_bigint::BigInt;
+use num_traits::{Euclid, One, Signed, Zero};
 use owo_colors::OwoColorize;
 use rayon::prelude::*;
-use std::{cmp::Ordering, collections::HashSet};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicUsize, Ordering as AtomicOrdering},
+        Mutex, RwLock,
+    },
+};
 
 use crate::{
-    column::{ColumnSet, Computation, ExoOperation, Value, ValueBacking},
-    compiler::{ColumnRef, ConstraintSet, EvalSettings, Kind, Node},
-    dag::ComputationDag,
+    column::{ColumnID, ColumnSet, Computation, ExoOperation, RegisterID, Value, ValueBacking},
+    compiler::{
+        bytecode::{Access, Evaluator, Program, BATCH_SIZE},
+        ColumnRef, Constraint, ConstraintSet, EvalSettings, Kind, Node,
+    },
+    dag::{ComputationDag, Schedule},
     errors::RuntimeError,
     import,
     pretty::Pretty,
@@ -39,13 +51,13 @@ fn compute_ancillaries(
         let mut r = arg1.clone();
         match op {
             ExoOperation::Add => {
-                r.add_assign(&arg2);
+                r.add_assign(&arg2, cs.field);
             }
             ExoOperation::Sub => {
-                r.sub_assign(&arg2);
+                r.sub_assign(&arg2, cs.field);
             }
             ExoOperation::Mul => {
-                r.mul_assign(&arg2);
+                r.mul_assign(&arg2, cs.field);
             }
         }
 
@@ -126,52 +138,259 @@ fn compute_ancillaries(
     Ok(())
 }
 
-#[time("info", "Computing expanded columns")]
-fn compute_all(cs: &mut ConstraintSet) -> Result<()> {
-    // Computations are split in sequentially dependent sets, where each set as
-    // to be completely computed before the next one is started, but all
-    // computations within a set can be processed in parallel
-    let jobs = ComputationDag::from_computations(cs.computations.iter());
-
-    let mut exo_operations = HashSet::new();
-
-    for processing_slice in jobs.job_slices() {
-        trace!(
-            "Processing computation slice {}",
-            processing_slice.iter().join(" ")
-        );
-        let comps = processing_slice
+/// Drops the values of the computed columns that are not needed anymore as
+/// soon as the values held in memory exceed a budget
+struct Evictor {
+    /// the memory budget, in bytes
+    budget: usize,
+    /// register -> how many computations yet to be processed read it; only
+    /// the registers that may be evicted are tracked
+    readers: HashMap<RegisterID, usize>,
+    evicted: HashSet<RegisterID>,
+    warned: bool,
+}
+impl Evictor {
+    fn new(cs: &ConstraintSet, budget: usize) -> Self {
+        // The columns read by the constraints or the perspectives must stay in
+        // memory; composite columns are materialized like any other computed
+        // column, so their sources only live until they are computed
+        let pinned = cs
+            .constraints
             .iter()
-            .filter_map(|h| cs.computations.computation_idx_for(h))
-            .collect::<HashSet<_>>()
+            .flat_map(Constraint::dependencies)
+            .chain(
+                cs.perspectives
+                    .values()
+                    .flat_map(|ps| ps.values())
+                    .flat_map(|p| p.dependencies()),
+            )
+            .filter_map(|c| cs.columns.column(&c).ok()?.register)
+            .collect::<HashSet<_>>();
+
+        let mut evictable = HashMap::<RegisterID, bool>::new();
+        for c in cs.columns.iter_cols() {
+            if let Some(r) = c.register {
+                *evictable.entry(r).or_insert(true) &=
+                    matches!(c.kind, Kind::Computed) && !pinned.contains(&r);
+            }
+        }
+        let mut readers = evictable
+            .into_iter()
+            .filter_map(|(r, evictable)| evictable.then_some((r, 0)))
+            .collect::<HashMap<_, _>>();
+        for c in cs.computations.iter() {
+            for r in Self::registers_read_by(cs, c) {
+                if let Some(n) = readers.get_mut(&r) {
+                    *n += 1;
+                }
+            }
+        }
+
+        Evictor {
+            budget,
+            readers,
+            evicted: HashSet::new(),
+            warned: false,
+        }
+    }
+
+    fn registers_read_by(cs: &ConstraintSet, c: &Computation) -> HashSet<RegisterID> {
+        c.sources()
             .iter()
-            .map(|i| cs.computations.get(*i).unwrap().to_owned())
-            .collect::<Vec<_>>();
+            .filter_map(|s| cs.columns.column(s).ok()?.register)
+            .collect()
+    }
+
+    /// Record that `c` has been processed, and does not need its sources anymore
+    fn done(&mut self, cs: &ConstraintSet, c: &Computation) {
+        for r in Self::registers_read_by(cs, c) {
+            if let Some(n) = self.readers.get_mut(&r) {
+                *n = n.saturating_sub(1);
+            }
+        }
+    }
 
-        for r in comps
+    /// Evict the largest unneeded registers until the budget is respected
+    fn enforce(&mut self, cs: &mut ConstraintSet) {
+        let mut used = cs
+            .columns
+            .registers
             .iter()
-            // .into_par_iter() // TODO: is that a bottleneck?
-            .filter_map(|comp| apply_computation(cs, comp, &mut exo_operations))
-            .collect::<Vec<_>>()
-            .into_iter()
-        {
-            match r {
-                Ok(xs) => {
-                    for (h, backing) in xs.into_iter() {
-                        trace!("Filling {} ({})", h.pretty(), backing.len());
-                        cs.columns
-                            .set_backing(&h, backing)
-                            .with_context(|| anyhow!("while filling {}", h.pretty()))?;
+            .filter_map(|r| r.backing())
+            .map(ValueBacking::memory)
+            .sum::<usize>();
+        if used <= self.budget {
+            return;
+        }
+
+        let candidates = self
+            .readers
+            .iter()
+            .filter(|(_, n)| **n == 0)
+            .filter_map(|(r, _)| Some((*r, cs.columns.registers[*r].backing()?.memory())))
+            .filter(|(_, size)| *size > 0)
+            .sorted_by_key(|(r, size)| (std::cmp::Reverse(*size), *r))
+            .collect::<Vec<_>>();
+        for (r, size) in candidates {
+            if used <= self.budget {
+                break;
+            }
+            debug!("evicting register #{} ({} bytes)", r, size);
+            cs.columns.evict(r);
+            self.readers.remove(&r);
+            self.evicted.insert(r);
+            used -= size;
+        }
+
+        if used > self.budget && !self.warned {
+            warn!(
+                "memory budget exceeded: {}MB are still required",
+                (used + (1 << 20) - 1) >> 20
+            );
+            self.warned = true;
+        }
+    }
+}
+
+/// The state shared by the computations running concurrently in
+/// [`compute_all`]
+struct Run<'a> {
+    /// computations only read the column set and fill their targets through a
+    /// shared reference; exclusive access is only required to evict registers,
+    /// and is only ever tried, so that a thread stealing work while holding a
+    /// read guard can never deadlock
+    cs: RwLock<&'a mut ConstraintSet>,
+    schedule: Mutex<Schedule>,
+    /// the computations to mark as processed without applying them
+    skipped: HashSet<usize>,
+    evictor: Option<Mutex<Evictor>>,
+    exo_operations: Mutex<HashSet<(ExoOperation, Value, Value)>>,
+    processed: AtomicUsize,
+    /// the first error met while filling a column
+    error: Mutex<Option<anyhow::Error>>,
+}
+impl<'a> Run<'a> {
+    /// Process the computation `i` on `scope`, then spawn the ones that were
+    /// only waiting for it, without waiting for any other computation
+    fn spawn<'s>(&'s self, scope: &rayon::Scope<'s>, i: usize) {
+        scope.spawn(move |scope| {
+            {
+                let cs = self.cs.read().unwrap();
+                let c = cs.computations.get(i).unwrap();
+                if !self.skipped.contains(&i) {
+                    trace!("Processing computation {}", c.pretty_target());
+                    let mut exos = HashSet::new();
+                    match apply_computation(&cs, c, &mut exos) {
+                        Some(Ok(xs)) => {
+                            for (h, backing) in xs.into_iter() {
+                                trace!("Filling {} ({})", h.pretty(), backing.len());
+                                if let Err(e) = cs
+                                    .columns
+                                    .set_backing(&h, backing)
+                                    .with_context(|| anyhow!("while filling {}", h.pretty()))
+                                {
+                                    self.error.lock().unwrap().get_or_insert(e);
+                                }
+                            }
+                        }
+                        Some(Err(e)) => warn!("{}", e),
+                        None => {}
                     }
+                    self.exo_operations.lock().unwrap().extend(exos);
+                }
+                if let Some(evictor) = self.evictor.as_ref() {
+                    evictor.lock().unwrap().done(&cs, c);
+                }
+            }
+            self.processed.fetch_add(1, AtomicOrdering::Relaxed);
+
+            // Evicting requires an exclusive access to the column set, and is
+            // thus postponed while other computations are running
+            if let Some(evictor) = self.evictor.as_ref() {
+                if let Result::Ok(mut cs) = self.cs.try_write() {
+                    evictor.lock().unwrap().enforce(&mut cs);
                 }
-                Err(e) => warn!("{}", e),
             }
+
+            let next = self.schedule.lock().unwrap().done(i);
+            for j in next {
+                self.spawn(scope, j);
+            }
+        });
+    }
+}
+
+#[time("info", "Computing expanded columns")]
+fn compute_all(
+    cs: &mut ConstraintSet,
+    scope: Option<&HashSet<ColumnID>>,
+    memory_budget: Option<usize>,
+) -> Result<HashSet<RegisterID>> {
+    // Each computation is processed as soon as all the columns it reads are
+    // available, in parallel with all the other available ones
+    let schedule = Schedule::from_computations(cs.computations.iter());
+    let roots = schedule.roots();
+    let count = cs.computations.iter().count();
+
+    // Out-of-scope computations are skipped, but still have to be marked as
+    // processed to carry on with the schedule
+    let skipped = scope
+        .map(|scope| {
+            cs.computations
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| {
+                    !c.targets()
+                        .iter()
+                        .any(|t| scope.contains(&cs.columns.id_of(t)))
+                })
+                .map(|(i, _)| i)
+                .collect::<HashSet<_>>()
+        })
+        .unwrap_or_default();
+
+    let run = Run {
+        evictor: memory_budget.map(|budget| Mutex::new(Evictor::new(cs, budget))),
+        cs: RwLock::new(cs),
+        schedule: Mutex::new(schedule),
+        skipped,
+        exo_operations: Mutex::new(HashSet::new()),
+        processed: AtomicUsize::new(0),
+        error: Mutex::new(None),
+    };
+    rayon::scope(|scope| {
+        for i in roots {
+            run.spawn(scope, i);
         }
+    });
+
+    let Run {
+        cs,
+        evictor,
+        exo_operations,
+        processed,
+        error,
+        ..
+    } = run;
+    let cs = cs.into_inner().unwrap();
+    if let Some(e) = error.into_inner().unwrap() {
+        return Err(e);
+    }
+    let mut evictor = evictor.map(|e| e.into_inner().unwrap());
+    if let Some(evictor) = evictor.as_mut() {
+        evictor.enforce(cs);
+    }
+    let processed = processed.into_inner();
+    if processed < count {
+        warn!(
+            "{} computations depend on each other and have been skipped",
+            count - processed
+        );
     }
 
-    compute_ancillaries(cs, exo_operations)?;
+    compute_ancillaries(cs, exo_operations.into_inner().unwrap())?;
 
-    Ok(())
+    Ok(evictor.map(|e| e.evicted).unwrap_or_default())
 }
 
 fn ensure_is_computed(h: &ColumnRef, cs: &ConstraintSet) -> Result<()> {
@@ -312,25 +531,22 @@ fn compute_exooperation(
         })
     };
 
+    let settings = EvalSettings::new().wrap(false).field(cs.field);
     let value: Vec<Value> = (-spilling..=len)
         .map(|i| {
-            let mut r1 = sources[0]
-                .eval(i, getter, &mut cache, &EvalSettings { wrap: false })
-                .unwrap();
-            let r2 = sources[1]
-                .eval(i, getter, &mut cache, &EvalSettings { wrap: false })
-                .unwrap();
+            let mut r1 = sources[0].eval(i, getter, &mut cache, &settings).unwrap();
+            let r2 = sources[1].eval(i, getter, &mut cache, &settings).unwrap();
             exo_operations.insert((op, r1.clone(), r2.clone()));
 
             match op {
                 ExoOperation::Add => {
-                    r1.add_assign(&r2);
+                    r1.add_assign(&r2, cs.field);
                 }
                 ExoOperation::Sub => {
-                    r1.sub_assign(&r2);
+                    r1.sub_assign(&r2, cs.field);
                 }
                 ExoOperation::Mul => {
-                    r1.mul_assign(&r2);
+                    r1.mul_assign(&r2, cs.field);
                 }
             }
             r1
@@ -343,6 +559,131 @@ fn compute_exooperation(
     )])
 }
 
+fn compute_limbs(
+    cs: &ConstraintSet,
+    source: &ColumnRef,
+    limbs: &[ColumnRef],
+    bits: usize,
+) -> Result<Vec<ComputedColumn>> {
+    ensure_is_computed(source, cs)?;
+    let spilling = cs.spilling_for_column(source).unwrap();
+    let len = cs.columns.len(source).unwrap() as isize;
+    let mask = (BigInt::one() << bits) - 1;
+
+    let mut values = vec![Vec::with_capacity((spilling + len) as usize); limbs.len()];
+    for i in -spilling..len {
+        let mut x = cs
+            .columns
+            .get(source, i, false)
+            .map(|x| x.to_bi())
+            .unwrap_or_default();
+        for limb in values.iter_mut() {
+            limb.push(Value::try_from(&x & &mask)?.in_field(cs.field));
+            x >>= bits;
+        }
+        if !x.is_zero() {
+            bail!(
+                "{} does not fit in {} limbs",
+                cs.handle(source).pretty(),
+                limbs.len()
+            )
+        }
+    }
+
+    Ok(limbs
+        .iter()
+        .cloned()
+        .zip(
+            values
+                .into_iter()
+                .map(|v| ValueBacking::from_vec(v, spilling)),
+        )
+        .collect())
+}
+
+fn compute_carries(
+    cs: &ConstraintSet,
+    sums: &[Node],
+    carries: &[ColumnRef],
+    bits: usize,
+    bound: usize,
+) -> Result<Vec<ComputedColumn>> {
+    for from in sums.iter().flat_map(|s| s.dependencies()) {
+        ensure_is_computed(&from, cs)?;
+    }
+    let module = cs.columns.module_of(&carries[0]);
+    let spilling = cs.spilling_of(&module).unwrap();
+    let len = sums
+        .iter()
+        .filter_map(|s| cs.dependencies_len(s, false).ok().flatten())
+        .max()
+        .unwrap_or(0) as isize;
+
+    let getter = |handle: &ColumnRef, j, _| {
+        cs.columns.get(handle, j, false).or_else(|| {
+            cs.columns
+                .column(handle)
+                .unwrap()
+                .padding_value
+                .as_ref()
+                .cloned()
+        })
+    };
+    // Natively computed sums are field elements, that have to be mapped back
+    // to signed integers
+    let modulus = cs.field.modulus();
+    let native = *crate::IS_NATIVE.read().unwrap() || !cs.field.is_ark();
+    let base = BigInt::one() << bits;
+    let bound = BigInt::from(bound);
+
+    let mut values = vec![Vec::with_capacity((spilling + len) as usize); carries.len()];
+    for i in -spilling..len {
+        let mut row = Vec::with_capacity(carries.len());
+        let mut carry = BigInt::zero();
+        let mut valid = true;
+        for (k, sum) in sums.iter().enumerate() {
+            let mut s = sum
+                .eval(
+                    i,
+                    getter,
+                    &mut None,
+                    &EvalSettings::new().wrap(false).field(cs.field),
+                )
+                .map(|x| x.to_bi())
+                .unwrap_or_default();
+            if native && s > modulus >> 1 {
+                s -= modulus;
+            }
+            s += &carry;
+            if k == sums.len() - 1 {
+                valid &= s.is_zero();
+            } else {
+                valid &= s.rem_euclid(&base).is_zero();
+                carry = s.div_euclid(&base);
+                valid &= carry.abs() <= bound;
+                row.push(carry.clone());
+            }
+        }
+        for (column, carry) in values.iter_mut().zip(row) {
+            column.push(if valid {
+                Value::BigInt(carry).in_field(cs.field)
+            } else {
+                Value::zero()
+            });
+        }
+    }
+
+    Ok(carries
+        .iter()
+        .cloned()
+        .zip(
+            values
+                .into_iter()
+                .map(|v| ValueBacking::from_vec(v, spilling)),
+        )
+        .collect())
+}
+
 fn compute_cyclic(
     cs: &ConstraintSet,
     froms: &[ColumnRef],
@@ -392,7 +733,7 @@ pub fn compute_expression(
     Ok(vec![(
         target.to_owned(),
         if let Result::Ok(cst) = exp.pure_eval() {
-            let v = Value::try_from(cst).unwrap();
+            let v = Value::try_from(cst).unwrap().in_field(cs.field);
             ValueBacking::from_fn(
                 Box::new(move |_, _: &ColumnSet| Some(v.clone())),
                 cs.iter_len(&module),
@@ -400,35 +741,23 @@ pub fn compute_expression(
             )
         } else {
             let length = cs.dependencies_len(exp, false).unwrap().unwrap();
-            let captured_exp = exp.clone();
-            ValueBacking::from_expression(captured_exp, length, spilling)
-
-            // The old way of doing things
-            // let mut cache = Some(cached::SizedCache::with_size(200000)); // ~1.60MB cache
-            // let values: Vec<_> = (-spilling..length as isize)
-            //     .map(|i| {
-            //         exp.eval(
-            //             i,
-            //             |handle, j, _| {
-            //                 cs.columns.get(handle, j, false).or_else(|| {
-            //                     cs.columns
-            //                         .column(handle)
-            //                         .unwrap()
-            //                         .padding_value
-            //                         .as_ref()
-            //                         .cloned()
-            //                 })
-            //             },
-            //             &mut cache,
-            //             &EvalSettings { wrap: false },
-            //         )
-            //         .unwrap_or_else(Value::zero)
-            //     })
-            //     .collect();
-            // ValueBacking::Vector {
-            //     v: values,
-            //     spilling: spilling,
-            // }
+            let program = Program::compile(exp)?;
+            let rows = (-spilling..length as isize).collect::<Vec<_>>();
+            let values = rows
+                .par_chunks(BATCH_SIZE)
+                .map_init(
+                    || Evaluator::new(&program, &cs.columns, Access::Padded, cs.field),
+                    |evaluator, batch| {
+                        evaluator
+                            .eval(batch)
+                            .iter()
+                            .map(|x| x.clone().unwrap_or_else(Value::zero))
+                            .collect::<Vec<_>>()
+                    },
+                )
+                .flatten()
+                .collect();
+            ValueBacking::from_vec(values, spilling)
         },
     )])
 }
@@ -484,9 +813,12 @@ fn compute_sorting_auxs(cs: &ConstraintSet, comp: &Computation) -> Result<Vec<Co
                     } else {
                         found = true;
                         delta = cs.columns.get(&sorted[l], i, false).unwrap().clone();
-                        delta.sub_assign(&cs.columns.get(&sorted[l], i - 1, false).unwrap());
+                        delta.sub_assign(
+                            &cs.columns.get(&sorted[l], i - 1, false).unwrap(),
+                            cs.field,
+                        );
                         if !signs[l] {
-                            delta.negate();
+                            delta.negate(cs.field);
                         }
                         Value::one()
                     }
@@ -601,6 +933,29 @@ pub fn apply_computation(
                 None
             }
         }
+        Computation::Limbs {
+            source,
+            limbs,
+            bits,
+        } => {
+            if !cs.columns.is_computed(&limbs[0]) {
+                Some(compute_limbs(cs, source, limbs, *bits))
+            } else {
+                None
+            }
+        }
+        Computation::Carries {
+            sums,
+            carries,
+            bits,
+            bound,
+        } => {
+            if !cs.columns.is_computed(&carries[0]) {
+                Some(compute_carries(cs, sums, carries, *bits, *bound))
+            } else {
+                None
+            }
+        }
         comp @ Computation::SortingConstraints { eq, .. } => {
             // NOTE all are computed at once, checking an arbitrary one (here
             // eq) is enough
@@ -622,9 +977,35 @@ fn err_missing_column(c: &crate::column::Column) -> RuntimeError {
 }
 
 pub fn prepare(cs: &mut ConstraintSet, fail_on_missing: bool) -> Result<()> {
-    compute_all(cs).with_context(|| "while computing columns")?;
+    prepare_within(cs, fail_on_missing, None, None)
+}
+
+/// Fill the computed columns of `cs`; if `scope` is set, only the computed
+/// columns whose ID it contains are filled. If `memory_budget` is set, the
+/// computed columns not needed anymore are dropped as soon as more than this
+/// many bytes are held in memory.
+pub fn prepare_within(
+    cs: &mut ConstraintSet,
+    fail_on_missing: bool,
+    scope: Option<&HashSet<ColumnID>>,
+    memory_budget: Option<usize>,
+) -> Result<()> {
+    let evicted =
+        compute_all(cs, scope, memory_budget).with_context(|| "while computing columns")?;
     for h in cs.columns.all() {
+        if scope.is_some_and(|scope| !scope.contains(&h.as_id())) {
+            continue;
+        }
         if !cs.columns.is_computed(&h) {
+            if cs
+                .columns
+                .column(&h)?
+                .register
+                .is_some_and(|r| evicted.contains(&r))
+            {
+                debug!("{} has been evicted", h.pretty());
+                continue;
+            }
             let err = err_missing_column(cs.columns.column(&h).unwrap());
             if fail_on_missing {
                 bail!(err)
@@ -637,17 +1018,95 @@ pub fn prepare(cs: &mut ConstraintSet, fail_on_missing: bool) -> Result<()> {
     Ok(())
 }
 
-pub fn compute_trace(tracefile: &str, cs: &mut ConstraintSet, fail_on_missing: bool) -> Result<()> {
-    if tracefile.ends_with("lt") {
-        import::parse_binary_trace(tracefile, cs, false)?;
+/// Import `tracefile` into `cs`, without computing any column
+pub fn import_trace(tracefile: &str, cs: &mut ConstraintSet) -> Result<()> {
+    if import::is_binary_trace(tracefile) {
+        import::parse_binary_trace(tracefile, cs, false)
     } else {
-        import::parse_json_trace(tracefile, cs, false)?;
+        import::parse_json_trace(tracefile, cs, false)
     }
-    prepare(cs, fail_on_missing)
 }
 
-// This is only used by the lib
-#[allow(dead_code)]
+pub fn compute_trace(tracefile: &str, cs: &mut ConstraintSet, fail_on_missing: bool) -> Result<()> {
+    compute_trace_within(tracefile, cs, fail_on_missing, None, None)
+}
+
+/// Import `tracefile` into `cs`, then fill the computed columns within `scope`
+/// under the given `memory_budget`
+pub fn compute_trace_within(
+    tracefile: &str,
+    cs: &mut ConstraintSet,
+    fail_on_missing: bool,
+    scope: Option<&HashSet<ColumnID>>,
+    memory_budget: Option<usize>,
+) -> Result<()> {
+    import_trace(tracefile, cs)?;
+    prepare_within(cs, fail_on_missing, scope, memory_budget)
+}
+
+/// The IDs of the columns required to fill the columns of the given
+/// `modules`, the given `columns` and the columns read by the given
+/// `constraints`, i.e. these columns and all the ones they are transitively
+/// computed from; or `None` if nothing has been selected.
+///
+/// Columns are designated either by their name, or by their module-qualified
+/// name.
+pub fn dependency_closure(
+    cs: &ConstraintSet,
+    modules: &[String],
+    columns: &[String],
+    constraints: Option<&Vec<String>>,
+) -> Result<Option<HashSet<ColumnID>>> {
+    if modules.is_empty() && columns.is_empty() && constraints.is_none() {
+        return Ok(None);
+    }
+
+    let known_modules = cs.columns.modules();
+    let mut targets = Vec::new();
+    for module in modules {
+        if !known_modules.contains(module) {
+            bail!("module {} not found", module.red().bold())
+        }
+        targets.extend(cs.columns.iter_module(module).map(|(r, _)| r));
+    }
+    for name in columns {
+        let found = cs
+            .columns
+            .iter()
+            .filter(|(_, c)| {
+                c.handle.name == *name
+                    || Handle::new(&c.handle.module, &c.handle.name).to_string() == *name
+            })
+            .map(|(r, _)| r)
+            .collect::<Vec<_>>();
+        if found.is_empty() {
+            bail!("column {} not found", name.red().bold())
+        }
+        targets.extend(found);
+    }
+    if let Some(constraints) = constraints {
+        for name in constraints {
+            if !cs.constraints.iter().any(|c| c.name() == *name) {
+                bail!("constraint {} not found", name.red().bold())
+            }
+        }
+        targets.extend(
+            cs.constraints
+                .iter()
+                .filter(|c| constraints.contains(&c.name()))
+                .flat_map(Constraint::dependencies),
+        );
+    }
+
+    Ok(Some(
+        ComputationDag::from_computations(cs.computations.iter())
+            .ancestors(targets)
+            .iter()
+            .map(|r| cs.columns.id_of(r))
+            .collect(),
+    ))
+}
+
 pub fn compute_trace_str(
     trace: &[u8],
     cs: &mut ConstraintSet,
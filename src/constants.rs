@@ -1,15 +1,17 @@
-use crate::compiler::Magma;
+use crate::{compiler::Magma, field::Field};
 
-pub const FIELD_BITSIZE: usize = 254;
+/// The widest integers that can be stored in a column
+pub const MAX_BIT_SIZE: usize = 256;
 
-pub fn col_count_bits(x: usize) -> usize {
-    (x + FIELD_BITSIZE - 1) / FIELD_BITSIZE
+pub fn col_count_bits(x: usize, field: Field) -> usize {
+    let field_bitsize = field.bit_size();
+    (x + field_bitsize - 1) / field_bitsize
 }
 
-pub fn col_count_bytes(x: usize) -> usize {
-    col_count_bits(x * 8)
+pub fn col_count_bytes(x: usize, field: Field) -> usize {
+    col_count_bits(x * 8, field)
 }
 
-pub fn col_count_magma(m: Magma) -> usize {
-    col_count_bits(m.bit_size())
+pub fn col_count_magma(m: Magma, field: Field) -> usize {
+    col_count_bits(m.bit_size_in(field), field)
 }
@@ -1,11 +1,14 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use crate::{column::Computation, compiler::ColumnRef};
 
 #[derive(Default, Debug)]
 pub(crate) struct ComputationDag {
     nodes: HashSet<ColumnRef>,
-    edges: HashSet<(ColumnRef, ColumnRef)>,
+    /// node -> the nodes it depends on
+    incoming: HashMap<ColumnRef, HashSet<ColumnRef>>,
+    /// node -> the nodes depending on it
+    outgoing: HashMap<ColumnRef, HashSet<ColumnRef>>,
 }
 
 impl ComputationDag {
@@ -20,31 +23,30 @@ impl ComputationDag {
     pub fn depends(&mut self, n1: &ColumnRef, n2: &ColumnRef) {
         self.nodes.insert(n1.to_owned());
         self.nodes.insert(n2.to_owned());
-        self.edges.insert((n1.clone(), n2.clone()));
+        self.outgoing
+            .entry(n1.clone())
+            .or_default()
+            .insert(n2.clone());
+        self.incoming
+            .entry(n2.clone())
+            .or_default()
+            .insert(n1.clone());
     }
 
     fn sinks(&self) -> Vec<ColumnRef> {
         self.nodes
             .iter()
-            .filter(|n| self.outgoing(n).is_empty())
+            .filter(|n| self.outgoing(n).next().is_none())
             .cloned()
             .collect()
     }
 
-    fn incoming(&self, n: &ColumnRef) -> HashSet<ColumnRef> {
-        self.edges
-            .iter()
-            .filter(|(_, o)| o == n)
-            .map(|(from, _)| from.clone())
-            .collect()
+    fn incoming(&self, n: &ColumnRef) -> impl Iterator<Item = &ColumnRef> {
+        self.incoming.get(n).into_iter().flatten()
     }
 
-    fn outgoing(&self, n: &ColumnRef) -> HashSet<ColumnRef> {
-        self.edges
-            .iter()
-            .filter(|(o, _)| o == n)
-            .map(|(_, to)| to.clone())
-            .collect()
+    fn outgoing(&self, n: &ColumnRef) -> impl Iterator<Item = &ColumnRef> {
+        self.outgoing.get(n).into_iter().flatten()
     }
 
     pub fn insert_computation(&mut self, c: &Computation) {
@@ -83,6 +85,18 @@ impl ComputationDag {
                 }
             }
             Computation::ExoConstant { .. } => {}
+            Computation::Limbs { source, limbs, .. } => {
+                for limb in limbs.iter() {
+                    self.depends(source, limb);
+                }
+            }
+            Computation::Carries { sums, carries, .. } => {
+                for source in sums.iter().flat_map(|s| s.dependencies()) {
+                    for carry in carries.iter() {
+                        self.depends(&source, carry);
+                    }
+                }
+            }
             Computation::SortingConstraints {
                 ats,
                 eq,
@@ -104,6 +118,19 @@ impl ComputationDag {
         }
     }
 
+    /// Returns `roots` along with all the nodes they transitively depend on
+    pub fn ancestors<I: IntoIterator<Item = ColumnRef>>(&self, roots: I) -> HashSet<ColumnRef> {
+        let mut r = HashSet::new();
+        let mut todo = roots.into_iter().collect::<Vec<_>>();
+        while let Some(n) = todo.pop() {
+            if !r.contains(&n) {
+                todo.extend(self.incoming(&n).cloned());
+                r.insert(n);
+            }
+        }
+        r
+    }
+
     /// Returns a pseudo-topological sorting, a list of sets of independent columns
     pub fn job_slices(&self) -> Vec<HashSet<ColumnRef>> {
         let mut r = Vec::new();
@@ -115,9 +142,9 @@ impl ComputationDag {
             visited.extend(current.iter().cloned());
             current = current
                 .into_iter()
-                .flat_map(|n| self.incoming(&n))
+                .flat_map(|n| self.incoming(&n).cloned().collect::<Vec<_>>())
                 .filter(|n| !visited.contains(n))
-                .filter(|n| self.outgoing(n).iter().all(|o| visited.contains(o)))
+                .filter(|n| self.outgoing(n).all(|o| visited.contains(o)))
                 .collect();
             if current.is_empty() {
                 break;
@@ -128,3 +155,62 @@ impl ComputationDag {
         r
     }
 }
+
+/// The dependencies between computations, used to process each of them as
+/// soon as all the columns it reads have been computed
+#[derive(Default, Debug)]
+pub(crate) struct Schedule {
+    /// computation ID -> the number of computations it waits for
+    pending: Vec<usize>,
+    /// computation ID -> the computations waiting for it
+    dependents: Vec<Vec<usize>>,
+}
+
+impl Schedule {
+    pub fn from_computations<'a, I: Iterator<Item = &'a Computation>>(comps: I) -> Schedule {
+        let comps = comps.collect::<Vec<_>>();
+        let producers = comps
+            .iter()
+            .enumerate()
+            .flat_map(|(i, c)| c.targets().into_iter().map(move |t| (t, i)))
+            .collect::<HashMap<_, _>>();
+
+        let mut r = Schedule {
+            pending: vec![0; comps.len()],
+            dependents: vec![Vec::new(); comps.len()],
+        };
+        for (i, c) in comps.iter().enumerate() {
+            let requirements = c
+                .sources()
+                .iter()
+                .filter_map(|s| producers.get(s).copied())
+                .filter(|&j| j != i)
+                .collect::<HashSet<_>>();
+            r.pending[i] = requirements.len();
+            for j in requirements {
+                r.dependents[j].push(i);
+            }
+        }
+        r
+    }
+
+    /// The computations that do not depend on any other one
+    pub fn roots(&self) -> Vec<usize> {
+        (0..self.pending.len())
+            .filter(|&i| self.pending[i] == 0)
+            .collect()
+    }
+
+    /// Mark the computation `i` as processed, and return the computations that
+    /// were only waiting for it
+    pub fn done(&mut self, i: usize) -> Vec<usize> {
+        let mut r = Vec::new();
+        for &j in self.dependents[i].iter() {
+            self.pending[j] -= 1;
+            if self.pending[j] == 0 {
+                r.push(j);
+            }
+        }
+        r
+    }
+}
@@ -0,0 +1,626 @@
+//! Structural comparison of two constraint sets, e.g. two successive versions
+//! of the same constraint system.
+use itertools::Itertools;
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::{
+    column::{Column, Computation},
+    compiler::{ColumnRef, Constraint, ConstraintSet, Expression, Intrinsic, Node, MAIN_MODULE},
+    structs::Handle,
+};
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub(crate) struct Change<T> {
+    pub old: T,
+    pub new: T,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct ColumnChange {
+    pub column: String,
+    pub property: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct OperandChange {
+    pub operand: &'static str,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct ItemChange {
+    pub name: String,
+    pub changes: Vec<OperandChange>,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct ModuleChange {
+    pub module: String,
+    pub property: &'static str,
+    pub old: Option<isize>,
+    pub new: Option<isize>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub(crate) struct ItemsDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<ItemChange>,
+}
+impl ItemsDiff {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+pub(crate) struct ColumnsDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub renamed: Vec<Change<String>>,
+    pub changed: Vec<ColumnChange>,
+}
+impl ColumnsDiff {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.renamed.is_empty()
+            && self.changed.is_empty()
+    }
+}
+
+/// The differences between two constraint sets
+#[derive(Serialize, Debug, Default)]
+pub(crate) struct Diff {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<Change<String>>,
+    pub columns: ColumnsDiff,
+    pub constraints: ItemsDiff,
+    pub computations: ItemsDiff,
+    pub modules: Vec<ModuleChange>,
+}
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.field.is_none()
+            && self.columns.is_empty()
+            && self.constraints.is_empty()
+            && self.computations.is_empty()
+            && self.modules.is_empty()
+    }
+}
+
+/// The name identifying a column across constraint sets, regardless of its
+/// perspective
+fn column_name(h: &Handle) -> String {
+    Handle::new(&h.module, &h.name).to_string()
+}
+
+/// Sort the operands of the commutative operations of `e`, so that e.g. `(* A
+/// B)` and `(* B A)` compare equal
+fn canonicalize(e: &mut Node) {
+    match e.e_mut() {
+        Expression::Funcall { func, args } => {
+            args.iter_mut().for_each(canonicalize);
+            if matches!(
+                func,
+                Intrinsic::Add | Intrinsic::Mul | Intrinsic::VectorAdd | Intrinsic::VectorMul
+            ) {
+                args.sort_by_cached_key(|a| a.to_string());
+            }
+        }
+        Expression::List(xs) => xs.iter_mut().for_each(canonicalize),
+        _ => {}
+    }
+}
+
+/// Maps the columns of a constraint set to the names they are compared under
+struct Namer<'a> {
+    cs: &'a ConstraintSet,
+    renames: &'a HashMap<String, String>,
+}
+impl Namer<'_> {
+    fn name(&self, r: &ColumnRef) -> String {
+        let name = column_name(&self.cs.columns.column(r).unwrap().handle);
+        self.renames.get(&name).cloned().unwrap_or(name)
+    }
+
+    /// Replace all the column references of `e` by their comparison name
+    fn rename(&self, r: &mut ColumnRef) {
+        *r = ColumnRef::from_handle(Handle::new(MAIN_MODULE, self.name(r)));
+    }
+
+    fn node(&self, e: &Node) -> String {
+        let mut e = e.clone();
+        e.add_id_to_handles(&|r| self.rename(r));
+        canonicalize(&mut e);
+        e.to_string()
+    }
+
+    fn nodes(&self, es: &[Node]) -> String {
+        format!("[{}]", es.iter().map(|e| self.node(e)).join(", "))
+    }
+
+    fn columns(&self, rs: &[ColumnRef]) -> String {
+        format!("[{}]", rs.iter().map(|r| self.name(r)).join(", "))
+    }
+
+    /// Describe a constraint as a list of named operands
+    fn constraint(&self, c: &Constraint) -> Vec<(&'static str, String)> {
+        match c {
+            Constraint::Vanishes { domain, expr, .. } => {
+                let mut r = vec![("kind", "vanishing".to_string())];
+                if let Some(domain) = domain {
+                    r.push(("domain", domain.to_string()));
+                }
+                r.push(("expression", self.node(expr)));
+                r
+            }
+            Constraint::Lookup {
+                including,
+                included,
+                ..
+            } => vec![
+                ("kind", "lookup".to_string()),
+                ("including", self.nodes(including)),
+                ("included", self.nodes(included)),
+            ],
+            Constraint::Permutation { from, to, .. } => vec![
+                ("kind", "permutation".to_string()),
+                ("from", self.columns(from)),
+                ("to", self.columns(to)),
+            ],
+            Constraint::InRange { exp, max, .. } => vec![
+                ("kind", "range".to_string()),
+                ("expression", self.node(exp)),
+                ("max", max.to_string()),
+            ],
+            Constraint::Normalization {
+                reference,
+                inverted,
+                ..
+            } => vec![
+                ("kind", "normalization".to_string()),
+                ("reference", self.node(reference)),
+                ("inverted", self.name(inverted)),
+            ],
+        }
+    }
+
+    /// The name of a computation, made of its targets, and its operands
+    fn computation(&self, c: &Computation) -> (String, Vec<(&'static str, String)>) {
+        let (targets, operands) = match c {
+            Computation::Composite { target, exp } => (
+                vec![target.clone()],
+                vec![
+                    ("kind", "composite".to_string()),
+                    ("expression", self.node(exp)),
+                ],
+            ),
+            Computation::ExoOperation {
+                op,
+                sources,
+                target,
+            } => (
+                vec![target.clone()],
+                vec![
+                    ("kind", format!("exo-operation {}", op)),
+                    ("sources", self.nodes(sources)),
+                ],
+            ),
+            Computation::ExoConstant { value, target } => (
+                vec![target.clone()],
+                vec![
+                    ("kind", "exo-constant".to_string()),
+                    ("value", value.to_string()),
+                ],
+            ),
+            Computation::Interleaved { target, froms } => (
+                vec![target.clone()],
+                vec![
+                    ("kind", "interleaved".to_string()),
+                    ("from", self.columns(froms)),
+                ],
+            ),
+            Computation::Sorted { froms, tos, signs } => (
+                tos.clone(),
+                vec![
+                    ("kind", "sorted".to_string()),
+                    ("from", self.columns(froms)),
+                    ("signs", format!("{:?}", signs)),
+                ],
+            ),
+            Computation::CyclicFrom {
+                target,
+                froms,
+                modulo,
+            } => (
+                vec![target.clone()],
+                vec![
+                    ("kind", "cyclic".to_string()),
+                    ("from", self.columns(froms)),
+                    ("modulo", modulo.to_string()),
+                ],
+            ),
+            Computation::SortingConstraints {
+                ats,
+                eq,
+                delta,
+                delta_bytes,
+                signs,
+                froms,
+                sorted,
+            } => (
+                ats.iter()
+                    .chain([eq, delta])
+                    .chain(delta_bytes.iter())
+                    .cloned()
+                    .collect(),
+                vec![
+                    ("kind", "sorting constraints".to_string()),
+                    ("from", self.columns(froms)),
+                    ("sorted", self.columns(sorted)),
+                    ("signs", format!("{:?}", signs)),
+                ],
+            ),
+            Computation::Limbs {
+                source,
+                limbs,
+                bits,
+            } => (
+                limbs.clone(),
+                vec![
+                    ("kind", "limbs".to_string()),
+                    ("source", self.name(source)),
+                    ("bits", bits.to_string()),
+                ],
+            ),
+            Computation::Carries {
+                sums,
+                carries,
+                bits,
+                bound,
+            } => (
+                carries.clone(),
+                vec![
+                    ("kind", "carries".to_string()),
+                    ("sums", self.nodes(sums)),
+                    ("bits", bits.to_string()),
+                    ("bound", bound.to_string()),
+                ],
+            ),
+        };
+        (self.columns(&targets), operands)
+    }
+}
+
+/// Compare the items of two sets, each of them described by a list of named
+/// operands
+fn diff_items(
+    old: BTreeMap<String, Vec<(&'static str, String)>>,
+    mut new: BTreeMap<String, Vec<(&'static str, String)>>,
+) -> ItemsDiff {
+    let mut r = ItemsDiff::default();
+    for (name, old_operands) in old.into_iter() {
+        if let Some(new_operands) = new.remove(&name) {
+            let old_operands = old_operands.into_iter().collect::<BTreeMap<_, _>>();
+            let new_operands = new_operands.into_iter().collect::<BTreeMap<_, _>>();
+            let changes = old_operands
+                .keys()
+                .chain(new_operands.keys())
+                .unique()
+                .filter_map(|operand| {
+                    let (old, new) = (old_operands.get(operand), new_operands.get(operand));
+                    (old != new).then(|| OperandChange {
+                        operand,
+                        old: old.cloned(),
+                        new: new.cloned(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            if !changes.is_empty() {
+                r.changed.push(ItemChange { name, changes });
+            }
+        } else {
+            r.removed.push(name);
+        }
+    }
+    r.added = new.into_keys().collect();
+    r
+}
+
+/// Index the items of a set by their name, disambiguating homonyms by their
+/// rank
+fn by_name(
+    items: impl Iterator<Item = (String, Vec<(&'static str, String)>)>,
+) -> BTreeMap<String, Vec<(&'static str, String)>> {
+    let mut r = BTreeMap::new();
+    let mut seen = HashMap::<String, usize>::new();
+    for (name, operands) in items {
+        let count = seen.entry(name.clone()).or_default();
+        let name = if *count == 0 {
+            name
+        } else {
+            format!("{}#{}", name, count)
+        };
+        *count += 1;
+        r.insert(name, operands);
+    }
+    r
+}
+
+/// Pair the columns that disappeared from `old` with the ones that appeared in
+/// `new` and look alike enough -- same module, type, kind, and used by the
+/// same constraints -- to be renamings of one another
+fn find_renames(
+    old: &ConstraintSet,
+    new: &ConstraintSet,
+    removed: &[String],
+    added: &[String],
+) -> HashMap<String, String> {
+    fn signatures(
+        cs: &ConstraintSet,
+        candidates: &[String],
+    ) -> HashMap<(String, String, String, BTreeSet<String>), Vec<String>> {
+        let mut users = HashMap::<String, BTreeSet<String>>::new();
+        for c in cs.constraints.iter() {
+            for r in c.dependencies() {
+                users
+                    .entry(column_name(&cs.columns.column(&r).unwrap().handle))
+                    .or_default()
+                    .insert(c.name());
+            }
+        }
+
+        let mut r = HashMap::<_, Vec<String>>::new();
+        for (_, column) in cs.columns.iter() {
+            let name = column_name(&column.handle);
+            if candidates.contains(&name) {
+                if let Some(users) = users.remove(&name) {
+                    r.entry((
+                        column.handle.module.clone(),
+                        column.t.to_string(),
+                        format!("{:?}", column.kind),
+                        users,
+                    ))
+                    .or_default()
+                    .push(name);
+                }
+            }
+        }
+        r
+    }
+
+    let new_signatures = signatures(new, added);
+    signatures(old, removed)
+        .into_iter()
+        .filter_map(|(signature, old_names)| {
+            let new_names = new_signatures.get(&signature)?;
+            (old_names.len() == 1 && new_names.len() == 1)
+                .then(|| (old_names[0].clone(), new_names[0].clone()))
+        })
+        .collect()
+}
+
+/// Structurally compare two constraint sets
+pub(crate) fn diff(old: &ConstraintSet, new: &ConstraintSet) -> Diff {
+    let mut r = Diff::default();
+
+    if old.field != new.field {
+        r.field = Some(Change {
+            old: old.field.to_string(),
+            new: new.field.to_string(),
+        });
+    }
+
+    // Columns
+    fn columns(cs: &ConstraintSet) -> BTreeMap<String, &Column> {
+        cs.columns
+            .iter_cols()
+            .map(|c| (column_name(&c.handle), c))
+            .collect()
+    }
+    let (old_columns, new_columns) = (columns(old), columns(new));
+    let mut removed = old_columns
+        .keys()
+        .filter(|c| !new_columns.contains_key(*c))
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut added = new_columns
+        .keys()
+        .filter(|c| !old_columns.contains_key(*c))
+        .cloned()
+        .collect::<Vec<_>>();
+    let renames = find_renames(old, new, &removed, &added);
+    removed.retain(|c| !renames.contains_key(c));
+    added.retain(|c| !renames.values().any(|n| n == c));
+    r.columns.removed = removed;
+    r.columns.added = added;
+    r.columns.renamed = renames
+        .iter()
+        .map(|(old, new)| Change {
+            old: old.clone(),
+            new: new.clone(),
+        })
+        .sorted_by(|a, b| a.old.cmp(&b.old))
+        .collect();
+    for (name, old_column) in old_columns.iter() {
+        let new_name = renames.get(name).unwrap_or(name);
+        if let Some(new_column) = new_columns.get(new_name) {
+            let length = |cs: &ConstraintSet, c: &Column| {
+                cs.length_multiplier(&c.handle.clone().into()).to_string()
+            };
+            for (property, old_value, new_value) in [
+                ("type", old_column.t.to_string(), new_column.t.to_string()),
+                (
+                    "length multiplier",
+                    length(old, old_column),
+                    length(new, new_column),
+                ),
+            ] {
+                if old_value != new_value {
+                    r.columns.changed.push(ColumnChange {
+                        column: new_name.clone(),
+                        property,
+                        old: old_value,
+                        new: new_value,
+                    });
+                }
+            }
+        }
+    }
+
+    // Constraints & computations; the columns of the old set are named after
+    // their new name, so that renamings do not pollute the comparison
+    let no_renames = HashMap::new();
+    let old_namer = Namer {
+        cs: old,
+        renames: &renames,
+    };
+    let new_namer = Namer {
+        cs: new,
+        renames: &no_renames,
+    };
+    r.constraints = diff_items(
+        by_name(
+            old.constraints
+                .iter()
+                .map(|c| (c.name(), old_namer.constraint(c))),
+        ),
+        by_name(
+            new.constraints
+                .iter()
+                .map(|c| (c.name(), new_namer.constraint(c))),
+        ),
+    );
+    r.computations = diff_items(
+        by_name(old.computations.iter().map(|c| old_namer.computation(c))),
+        by_name(new.computations.iter().map(|c| new_namer.computation(c))),
+    );
+
+    // Modules
+    let modules = old
+        .columns
+        .modules()
+        .union(&new.columns.modules())
+        .cloned()
+        .sorted()
+        .collect::<Vec<_>>();
+    for module in modules.into_iter() {
+        for (property, old_value, new_value) in [
+            (
+                "minimal length",
+                old.columns.min_len.get(&module).map(|l| *l as isize),
+                new.columns.min_len.get(&module).map(|l| *l as isize),
+            ),
+            (
+                "spilling",
+                old.spilling_of(&module),
+                new.spilling_of(&module),
+            ),
+        ] {
+            if old_value != new_value {
+                r.modules.push(ModuleChange {
+                    module: module.clone(),
+                    property,
+                    old: old_value,
+                    new: new_value,
+                });
+            }
+        }
+    }
+
+    r
+}
+
+impl std::fmt::Display for Diff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn or_none(x: &Option<impl ToString>) -> String {
+            x.as_ref()
+                .map(|x| x.to_string())
+                .unwrap_or_else(|| "∅".to_string())
+        }
+
+        fn items(f: &mut std::fmt::Formatter<'_>, title: &str, d: &ItemsDiff) -> std::fmt::Result {
+            if d.is_empty() {
+                return std::fmt::Result::Ok(());
+            }
+            writeln!(f, "{}", title.bold())?;
+            for x in d.added.iter() {
+                writeln!(f, "  {} {}", "+".green().bold(), x)?;
+            }
+            for x in d.removed.iter() {
+                writeln!(f, "  {} {}", "-".red().bold(), x)?;
+            }
+            for x in d.changed.iter() {
+                writeln!(f, "  {} {}", "~".yellow().bold(), x.name)?;
+                for c in x.changes.iter() {
+                    writeln!(f, "      {}: {}", c.operand, or_none(&c.old).red())?;
+                    writeln!(
+                        f,
+                        "      {:width$}→ {}",
+                        "",
+                        or_none(&c.new).green(),
+                        width = c.operand.len()
+                    )?;
+                }
+            }
+            std::fmt::Result::Ok(())
+        }
+
+        if self.is_empty() {
+            return writeln!(f, "no differences");
+        }
+
+        if let Some(field) = self.field.as_ref() {
+            writeln!(f, "{} {} → {}", "field".bold(), field.old, field.new)?;
+        }
+
+        if !self.columns.is_empty() {
+            writeln!(f, "{}", "columns".bold())?;
+            for c in self.columns.added.iter() {
+                writeln!(f, "  {} {}", "+".green().bold(), c)?;
+            }
+            for c in self.columns.removed.iter() {
+                writeln!(f, "  {} {}", "-".red().bold(), c)?;
+            }
+            for c in self.columns.renamed.iter() {
+                writeln!(f, "  {} {} → {}", "~".yellow().bold(), c.old, c.new)?;
+            }
+            for c in self.columns.changed.iter() {
+                writeln!(
+                    f,
+                    "  {} {}: {} {} → {}",
+                    "~".yellow().bold(),
+                    c.column,
+                    c.property,
+                    c.old,
+                    c.new
+                )?;
+            }
+        }
+
+        items(f, "constraints", &self.constraints)?;
+        items(f, "computations", &self.computations)?;
+
+        if !self.modules.is_empty() {
+            writeln!(f, "{}", "modules".bold())?;
+            for m in self.modules.iter() {
+                writeln!(
+                    f,
+                    "  {} {}: {} {} → {}",
+                    "~".yellow().bold(),
+                    m.module,
+                    m.property,
+                    or_none(&m.old),
+                    or_none(&m.new)
+                )?;
+            }
+        }
+        std::fmt::Result::Ok(())
+    }
+}
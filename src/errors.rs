@@ -221,5 +221,456 @@ pub mod symbols {
 
         #[error("missing perspective name in {}", 0.yellow().bold())]
         MissingPerspective(String),
+
+        #[error("symbol {} is unavailable due to previous errors", .0.red())]
+        Poisoned(String),
+    }
+}
+
+/// Diagnostics link errors to the code that caused them, and render them
+/// either for humans -- in the fashion of rustc -- or as JSON for tooling.
+///
+/// Errors are located by wrapping them, as they bubble up, in [`Located`]
+/// (the AST node being processed) and [`InFile`] (the source file being
+/// processed) layers, that are then collected from the error chain.
+pub mod diagnostics {
+    use crate::compiler::parser::{parser::Rule, AstNode, LinCol};
+    use owo_colors::OwoColorize;
+    use serde::Serialize;
+    use std::fmt::Display;
+
+    type StdError = dyn std::error::Error + 'static;
+
+    /// A piece of code in a source file
+    #[derive(Debug, Clone, Serialize)]
+    pub struct Span {
+        pub file: String,
+        /// 1-based line of the start of the span
+        pub line: usize,
+        /// 1-based column of the start of the span, in characters
+        pub column: usize,
+        /// length of the span on its first line, in characters
+        pub len: usize,
+        /// the source line containing the start of the span
+        #[serde(skip)]
+        pub text: String,
+    }
+    impl Span {
+        /// Locate `src`, starting at `lc` in `content`
+        pub fn new(file: &str, content: &str, lc: LinCol, src: &str) -> Span {
+            let text = content
+                .lines()
+                .nth(lc.0.saturating_sub(1))
+                .unwrap_or_default()
+                .to_owned();
+            let available = text.chars().count().saturating_sub(lc.1.saturating_sub(1));
+            Span {
+                file: file.to_owned(),
+                line: lc.0,
+                column: lc.1,
+                len: src
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .chars()
+                    .count()
+                    .min(available),
+                text,
+            }
+        }
+    }
+
+    /// A secondary location attached to a diagnostic, e.g. a previous
+    /// definition
+    #[derive(Debug, Clone, Serialize)]
+    pub struct Label {
+        pub span: Span,
+        pub message: String,
+    }
+
+    #[derive(Debug, Clone, Copy, Serialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Severity {
+        Error,
+        Warning,
+    }
+
+    #[derive(Debug, Clone, Serialize)]
+    pub struct Diagnostic {
+        pub severity: Severity,
+        pub message: String,
+        /// where the error happened, if it could be located
+        pub span: Option<Span>,
+        pub labels: Vec<Label>,
+        pub notes: Vec<String>,
+    }
+
+    /// Wraps an error with the AST node that was being processed when it
+    /// happened.
+    #[derive(Debug)]
+    pub struct Located {
+        pub lc: LinCol,
+        pub src: String,
+        pub source: anyhow::Error,
+    }
+    impl Display for Located {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}", super::parser::make_src_error(&self.src, self.lc))
+        }
+    }
+    impl std::error::Error for Located {
+        fn source(&self) -> Option<&StdError> {
+            Some(&*self.source)
+        }
+    }
+
+    /// Wraps an error with the source file that was being processed when it
+    /// happened.
+    #[derive(Debug)]
+    pub struct InFile {
+        pub file: String,
+        pub content: String,
+        pub message: String,
+        pub source: anyhow::Error,
+    }
+    impl Display for InFile {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}", self.message)
+        }
+    }
+    impl std::error::Error for InFile {
+        fn source(&self) -> Option<&StdError> {
+            Some(&*self.source)
+        }
+    }
+
+    /// Wraps an error with a secondary location, e.g. where a conflicting
+    /// symbol has previously been defined.
+    #[derive(Debug)]
+    pub struct Labelled {
+        pub label: Label,
+        pub source: anyhow::Error,
+    }
+    impl Display for Labelled {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(
+                f,
+                "{} at {}:{}",
+                self.label.message, self.label.span.file, self.label.span.line
+            )
+        }
+    }
+    impl std::error::Error for Labelled {
+        fn source(&self) -> Option<&StdError> {
+            Some(&*self.source)
+        }
+    }
+
+    /// Several errors, reported together
+    #[derive(Debug)]
+    pub struct Errors(pub Vec<anyhow::Error>);
+    impl Display for Errors {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            for e in self.0.iter() {
+                writeln!(f, "{:?}", e)?;
+            }
+            write!(f, "{} errors found", self.0.len())
+        }
+    }
+    impl std::error::Error for Errors {}
+
+    /// Accumulates the errors encountered while compiling, so that they can
+    /// be reported all at once rather than one compilation cycle at a time.
+    ///
+    /// Errors stemming from the use of a [poisoned](super::symbols::Error::Poisoned)
+    /// symbol, i.e. one whose definition already failed, are consequences of
+    /// a previously collected error and are therefore skipped.
+    pub struct ErrorCollector {
+        errors: Vec<anyhow::Error>,
+        /// how many errors may be collected before giving up; 0 for no limit
+        limit: usize,
+        /// the file currently being processed, its content, and what is done
+        /// with it
+        file: Option<(String, String, String)>,
+    }
+    impl ErrorCollector {
+        pub fn new(limit: usize) -> Self {
+            ErrorCollector {
+                errors: Vec::new(),
+                limit,
+                file: None,
+            }
+        }
+
+        /// The errors collected from now on will be located in `file`, of
+        /// content `content`
+        pub fn enter(&mut self, file: &str, content: &str, message: String) {
+            self.file = Some((file.to_owned(), content.to_owned(), message));
+        }
+
+        /// Collect `err`, then fail with all the collected errors if the
+        /// limit has been reached
+        pub fn push(&mut self, err: anyhow::Error) -> anyhow::Result<()> {
+            if err.chain().any(|e| {
+                matches!(
+                    e.downcast_ref::<super::symbols::Error>(),
+                    Some(super::symbols::Error::Poisoned(_))
+                )
+            }) {
+                log::debug!("skipping consequential error: {:?}", err);
+                return Ok(());
+            }
+
+            let err = match self.file.as_ref() {
+                Some((file, content, message)) => InFile {
+                    file: file.clone(),
+                    content: content.clone(),
+                    message: message.clone(),
+                    source: err,
+                }
+                .into(),
+                None => err,
+            };
+            self.errors.push(err);
+            if self.limit > 0 && self.errors.len() >= self.limit {
+                self.finish()
+            } else {
+                Ok(())
+            }
+        }
+
+        /// Unwrap `r`, collecting its error if there is one
+        pub fn check<T>(&mut self, r: anyhow::Result<T>) -> anyhow::Result<Option<T>> {
+            match r {
+                Ok(x) => Ok(Some(x)),
+                Err(e) => self.push(e).map(|_| None),
+            }
+        }
+
+        /// Fail with all the errors collected so far, if any
+        pub fn finish(&mut self) -> anyhow::Result<()> {
+            let mut errors = std::mem::take(&mut self.errors);
+            match errors.len() {
+                0 => Ok(()),
+                1 => Err(errors.pop().unwrap()),
+                _ => Err(Errors(errors).into()),
+            }
+        }
+    }
+
+    /// Attach location information to the error of a [`Result`]
+    pub trait Locate<T> {
+        /// Attach the position of `node` to the error
+        fn at(self, node: &AstNode) -> anyhow::Result<T>;
+        /// Attach the source file `file`, of content `content`, to the error
+        fn in_file(self, file: &str, content: &str, message: String) -> anyhow::Result<T>;
+    }
+    impl<T, E: Into<anyhow::Error>> Locate<T> for Result<T, E> {
+        fn at(self, node: &AstNode) -> anyhow::Result<T> {
+            self.map_err(|e| {
+                Located {
+                    lc: node.lc,
+                    src: node.src.clone(),
+                    source: e.into(),
+                }
+                .into()
+            })
+        }
+
+        fn in_file(self, file: &str, content: &str, message: String) -> anyhow::Result<T> {
+            self.map_err(|e| {
+                InFile {
+                    file: file.to_owned(),
+                    content: content.to_owned(),
+                    message,
+                    source: e.into(),
+                }
+                .into()
+            })
+        }
+    }
+
+    impl Diagnostic {
+        pub fn warning(message: String, span: Option<Span>) -> Diagnostic {
+            Diagnostic {
+                severity: Severity::Warning,
+                message,
+                span,
+                labels: Vec::new(),
+                notes: Vec::new(),
+            }
+        }
+
+        /// Build the diagnostics describing `err`; there will be more than
+        /// one if `err` aggregates several [`Errors`].
+        pub fn from_error(err: &anyhow::Error) -> Vec<Diagnostic> {
+            if let Some(errs) = err.chain().find_map(|e| e.downcast_ref::<Errors>()) {
+                errs.0.iter().flat_map(Diagnostic::from_error).collect()
+            } else {
+                vec![Diagnostic::from_single_error(err)]
+            }
+        }
+
+        fn from_single_error(err: &anyhow::Error) -> Diagnostic {
+            let mut file: Option<&InFile> = None;
+            let mut span = None;
+            let mut labels = Vec::new();
+            let mut notes = Vec::new();
+            let mut message = err.root_cause().to_string();
+
+            let chain = err.chain().collect::<Vec<_>>();
+            for (i, e) in chain.iter().enumerate() {
+                if let Some(f) = e.downcast_ref::<InFile>() {
+                    file = Some(f);
+                } else if let Some(l) = e.downcast_ref::<Located>() {
+                    // The innermost location is the most precise one
+                    match file {
+                        Some(file) => {
+                            span = Some(Span::new(&file.file, &file.content, l.lc, &l.src))
+                        }
+                        None => notes.push(format!("at line {}", l.lc.0)),
+                    }
+                } else if let Some(l) = e.downcast_ref::<Labelled>() {
+                    labels.push(l.label.clone());
+                } else if let Some(pe) = e.downcast_ref::<pest::error::Error<Rule>>() {
+                    let (lc, len) = match pe.line_col {
+                        pest::error::LineColLocation::Pos(lc) => (lc, 1),
+                        pest::error::LineColLocation::Span(start, end) => (
+                            start,
+                            if start.0 == end.0 {
+                                end.1.saturating_sub(start.1).max(1)
+                            } else {
+                                1
+                            },
+                        ),
+                    };
+                    message = pe.variant.message().to_string();
+                    if let Some(file) = file {
+                        let mut s = Span::new(&file.file, &file.content, lc, "");
+                        s.len = len;
+                        span = Some(s);
+                    }
+                } else if i + 1 < chain.len() {
+                    notes.push(e.to_string());
+                }
+            }
+
+            Diagnostic {
+                severity: Severity::Error,
+                message,
+                span,
+                labels,
+                notes,
+            }
+        }
+
+        /// Render the diagnostic for tooling
+        pub fn to_json(&self) -> String {
+            let mut d = self.clone();
+            d.message = crate::utils::strip_ansi(&d.message);
+            d.notes = d
+                .notes
+                .iter()
+                .map(|n| crate::utils::strip_ansi(n))
+                .collect();
+            for l in d.labels.iter_mut() {
+                l.message = crate::utils::strip_ansi(&l.message);
+            }
+            serde_json::to_string(&d).unwrap()
+        }
+    }
+
+    /// Render the source line of `span`, underlined with `marker`
+    fn render_span(
+        f: &mut std::fmt::Formatter<'_>,
+        span: &Span,
+        marker: char,
+        message: &str,
+        primary: bool,
+        gutter: usize,
+    ) -> std::fmt::Result {
+        let bar = "|".blue().bold().to_string();
+        writeln!(
+            f,
+            "{:>gutter$}{} {}:{}:{}",
+            "",
+            if primary { "-->" } else { ":::" }.blue().bold(),
+            span.file,
+            span.line,
+            span.column,
+            gutter = gutter
+        )?;
+        writeln!(f, "{:>gutter$} {}", "", bar, gutter = gutter)?;
+        writeln!(
+            f,
+            "{:>gutter$} {} {}",
+            span.line.blue().bold(),
+            bar,
+            span.text,
+            gutter = gutter
+        )?;
+        let padding = span
+            .text
+            .chars()
+            .take(span.column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        let markers = std::iter::repeat(marker)
+            .take(span.len.max(1))
+            .collect::<String>();
+        writeln!(
+            f,
+            "{:>gutter$} {} {}{}{}",
+            "",
+            bar,
+            padding,
+            if primary {
+                markers.red().bold().to_string()
+            } else {
+                markers.blue().bold().to_string()
+            },
+            if message.is_empty() {
+                String::new()
+            } else {
+                format!(" {}", message)
+            },
+            gutter = gutter
+        )
+    }
+
+    impl Display for Diagnostic {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            let gutter = self
+                .span
+                .iter()
+                .chain(self.labels.iter().map(|l| &l.span))
+                .map(|s| s.line.to_string().len())
+                .max()
+                .unwrap_or(0)
+                + 1;
+
+            match self.severity {
+                Severity::Error => write!(f, "{}", "error".red().bold()),
+                Severity::Warning => write!(f, "{}", "warning".yellow().bold()),
+            }?;
+            writeln!(f, ": {}", self.message.bold())?;
+            if let Some(span) = self.span.as_ref() {
+                render_span(f, span, '^', "", true, gutter)?;
+            }
+            for label in self.labels.iter() {
+                render_span(f, &label.span, '-', &label.message, false, gutter)?;
+            }
+            for note in self.notes.iter() {
+                writeln!(
+                    f,
+                    "{:>gutter$} = {}: {}",
+                    "",
+                    "note".bold(),
+                    note,
+                    gutter = gutter
+                )?;
+            }
+            std::fmt::Result::Ok(())
+        }
     }
 }
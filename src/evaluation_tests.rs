@@ -77,5 +77,45 @@ fn check_json_trace(trace: &str, mut cs: ConstraintSet, report: bool) -> Result<
         &None, // Consider all columns
         &[],   // Consider all constraints
         check::DebugSettings::new().report(report),
+    )?
+    .into_result()
+}
+
+#[test]
+fn check_report() {
+    let mut cs = compile("(defcolumns A B) (defconstraint eq () (vanishes! (- A B)))").unwrap();
+    import::read_trace_str(
+        br#"{"<prelude>": {"A": [1, 2, 3, 4], "B": [1, 0, 3, 0]}}"#,
+        &mut cs,
+        false,
+    )
+    .unwrap();
+    compute::prepare(&mut cs, true).unwrap();
+    let report = check::check(&cs, &None, &[], check::DebugSettings::new()).unwrap();
+    assert_eq!(report.failures.len(), 1);
+    let failure = &report.failures[0];
+    assert_eq!(failure.handle.name, "eq");
+    assert_eq!(failure.rows.len(), 2);
+    assert_eq!(failure.first_row, failure.rows.first().cloned());
+    assert_eq!(failure.witnesses.len(), 1);
+}
+
+#[test]
+fn lookup_report() {
+    let mut cs = compile("(defcolumns A B X Y) (deflookup lk (A B) (X Y))").unwrap();
+    // (4, 1) and (1, 3) share the same 2·x + 3·y linear combination, but must
+    // still be told apart
+    import::read_trace_str(
+        br#"{"<prelude>": {"A": [1, 2], "B": [3, 2], "X": [1, 4], "Y": [3, 1]}}"#,
+        &mut cs,
+        false,
     )
+    .unwrap();
+    compute::prepare(&mut cs, true).unwrap();
+    let report = check::check(&cs, &None, &[], check::DebugSettings::new()).unwrap();
+    assert_eq!(report.failures.len(), 1);
+    let lookup = report.failures[0].lookup.as_ref().unwrap();
+    assert_eq!(lookup.distinct_missing, 1);
+    assert_eq!(lookup.missing[0].tuple, ["4", "1"]);
+    assert_eq!(lookup.missing[0].closest.len(), 0);
 }
@@ -0,0 +1,234 @@
+//! Writing computed traces in the binary format read by
+//! [`crate::import::parse_binary_trace`].
+//!
+//! A binary trace is laid out as:
+//!   - the number of registers, as a big-endian i32;
+//!   - for each register, its header, made of:
+//!     - the length of its `module.name` identifier, as a big-endian i16;
+//!     - its `module.name` identifier;
+//!     - the number of bytes per element, as an i8;
+//!     - the number of elements, as a big-endian i32;
+//!   - for each register, in the same order as the headers, its elements as
+//!     big-endian integers.
+//!
+//! All the headers are computed from the register lengths before any value is
+//! written, so that the values themselves are streamed to the output.
+use anyhow::*;
+use flate2::write::GzEncoder;
+use log::*;
+use logging_timer::time;
+use regex_lite::Regex;
+use std::{io::Write, ops::Range};
+
+use crate::{
+    column::{ColumnSet, Value, ValueBacking},
+    compiler::ConstraintSet,
+    pretty::Pretty,
+    structs::Handle,
+};
+
+/// The compression applied to a written trace
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+impl Compression {
+    /// Infer the compression from the extension of `filename`
+    pub fn from_filename(filename: &str) -> Self {
+        if filename.ends_with(".gz") {
+            Compression::Gzip
+        } else if filename.ends_with(".zst") {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// The subset of a trace to export
+#[derive(Default)]
+pub struct TraceSelection {
+    /// only export the modules matching this regex
+    pub modules: Option<Regex>,
+    /// only export the columns whose `module.name` match this regex
+    pub columns: Option<Regex>,
+    /// do not export these modules
+    pub exclude: Vec<String>,
+    /// only export these rows
+    pub rows: Option<Range<usize>>,
+}
+impl TraceSelection {
+    pub fn includes(&self, handle: &Handle) -> bool {
+        !self.exclude.contains(&handle.module)
+            && self
+                .modules
+                .as_ref()
+                .map(|r| r.is_match(&handle.module))
+                .unwrap_or(true)
+            && self
+                .columns
+                .as_ref()
+                .map(|r| r.is_match(&format!("{}.{}", handle.module, handle.name)))
+                .unwrap_or(true)
+    }
+
+    /// The selected rows of a register of `len` elements, its initial padding
+    /// excluded
+    pub fn rows(&self, len: usize) -> Range<usize> {
+        let len = len.saturating_sub(1);
+        self.rows
+            .as_ref()
+            .map(|r| r.start.min(len)..r.end.min(len))
+            .unwrap_or(0..len)
+    }
+
+    /// The values of the selected rows of `backing`
+    pub fn values<'a>(
+        &self,
+        backing: &'a ValueBacking,
+        columns: &'a ColumnSet,
+    ) -> impl Iterator<Item = Value> + 'a {
+        let rows = self.rows(backing.len());
+        // The first element of an imported register is its initial padding,
+        // which is added back on import
+        backing
+            .iter_without_spilling(columns)
+            .skip(1 + rows.start)
+            .take(rows.len())
+    }
+}
+
+/// Parse a row range written as `START:END`, `START:` or `:END`
+pub fn parse_rows(s: &str) -> Result<Range<usize>> {
+    let (start, end) = s
+        .split_once(':')
+        .ok_or_else(|| anyhow!("expected START:END, found `{}`", s))?;
+    let start = if start.is_empty() {
+        0
+    } else {
+        start
+            .parse()
+            .with_context(|| anyhow!("invalid row `{}`", start))?
+    };
+    let end = if end.is_empty() {
+        usize::MAX
+    } else {
+        end.parse()
+            .with_context(|| anyhow!("invalid row `{}`", end))?
+    };
+    if start > end {
+        bail!("empty row range `{}`", s)
+    }
+    Ok(start..end)
+}
+
+struct RegisterHeader<'a> {
+    id: String,
+    backing: &'a ValueBacking,
+    bytes_per_element: usize,
+    length: usize,
+}
+
+fn headers<'a>(
+    cs: &'a ConstraintSet,
+    selection: &TraceSelection,
+) -> Result<Vec<RegisterHeader<'a>>> {
+    cs.columns
+        .registers
+        .iter()
+        .filter_map(|r| Some((r.handle.as_ref()?, r.backing()?, r.magma)))
+        .filter(|(handle, ..)| selection.includes(handle))
+        .map(|(handle, backing, magma)| {
+            let id = format!("{}.{}", handle.module, handle.name);
+            if id.len() > i16::MAX as usize {
+                bail!("register name {} is too long", handle.pretty());
+            }
+            Ok(RegisterHeader {
+                id,
+                backing,
+                bytes_per_element: magma.byte_size_in(cs.field).clamp(1, 32),
+                length: selection.rows(backing.len()).len(),
+            })
+        })
+        .collect()
+}
+
+/// Encode `x` as a `bpe`-bytes long big-endian integer into `out`
+pub(crate) fn write_element(x: &Value, bpe: usize, out: &mut impl Write) -> Result<()> {
+    let mut element = [0u8; 32];
+    let bytes = x.to_bytes();
+    let bytes = &bytes[bytes.iter().take_while(|b| **b == 0).count()..];
+    if bytes.len() > bpe {
+        bail!("{} does not fit in {} bytes", x.pretty(), bpe);
+    }
+    element[bpe - bytes.len()..bpe].copy_from_slice(bytes);
+    out.write_all(&element[..bpe])?;
+    Ok(())
+}
+
+fn write_trace(cs: &ConstraintSet, selection: &TraceSelection, out: &mut impl Write) -> Result<()> {
+    let headers = headers(cs, selection)?;
+
+    out.write_all(&(headers.len() as i32).to_be_bytes())?;
+    for h in headers.iter() {
+        out.write_all(&(h.id.len() as i16).to_be_bytes())?;
+        out.write_all(h.id.as_bytes())?;
+        out.write_all(&(h.bytes_per_element as i8).to_be_bytes())?;
+        out.write_all(
+            &i32::try_from(h.length)
+                .with_context(|| anyhow!("{} is too long", h.id))?
+                .to_be_bytes(),
+        )?;
+    }
+
+    for h in headers.iter() {
+        debug!("Exporting {}", h.id);
+        let mut written = 0;
+        for x in selection.values(h.backing, &cs.columns) {
+            write_element(&x, h.bytes_per_element, out)
+                .with_context(|| anyhow!("while exporting {}", h.id))?;
+            written += 1;
+        }
+        if written != h.length {
+            bail!(
+                "{}: expected {} elements, found {}",
+                h.id,
+                h.length,
+                written
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Stream the selected registers of `cs` to `out` in the binary trace format,
+/// optionally compressed
+#[time("info", "Writing binary trace")]
+pub fn write_binary_trace(
+    cs: &ConstraintSet,
+    selection: &TraceSelection,
+    out: impl Write,
+    compression: Compression,
+) -> Result<()> {
+    match compression {
+        Compression::None => {
+            let mut out = out;
+            write_trace(cs, selection, &mut out)?;
+            out.flush()?;
+        }
+        Compression::Gzip => {
+            let mut out = GzEncoder::new(out, flate2::Compression::default());
+            write_trace(cs, selection, &mut out)?;
+            out.finish()?.flush()?;
+        }
+        Compression::Zstd => {
+            let mut out = zstd::Encoder::new(out, 0)?;
+            write_trace(cs, selection, &mut out)?;
+            out.finish()?.flush()?;
+        }
+    }
+    Ok(())
+}
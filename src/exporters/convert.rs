@@ -1,29 +1,112 @@
 use crate::{
-    column::ValueBacking,
+    column::{Column, ValueBacking},
     compiler::{ColumnRef, ConstraintSet, Kind},
+    export::{self, Compression, TraceSelection},
     pretty::{Base, Pretty},
-    structs::Handle,
 };
 use anyhow::*;
+use flate2::write::GzEncoder;
 use itertools::Itertools;
 use log::*;
 use owo_colors::OwoColorize;
 use rayon::prelude::*;
+use serde::Serialize;
 use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::Path;
 
-pub(crate) fn to_csv(cs: &ConstraintSet, exclude: &[String], filename: &str) -> Result<()> {
-    let base_filename = Path::new(filename);
+/// The formats a trace can be converted to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Format {
+    /// one CSV file per module
+    Csv,
+    /// the JSON format accepted as input, optionally gzipped
+    Json { gzip: bool },
+    /// the binary trace format
+    Lt(Compression),
+    /// a directory storing one raw big-endian file per column, described by a
+    /// JSON manifest
+    Columnar,
+}
+impl Format {
+    pub const NAMES: [&'static str; 7] = [
+        "csv", "json", "json.gz", "lt", "lt.gz", "lt.zst", "columnar",
+    ];
+
+    pub fn from_name(name: &str) -> Result<Format> {
+        Ok(match name {
+            "csv" => Format::Csv,
+            "json" => Format::Json { gzip: false },
+            "json.gz" => Format::Json { gzip: true },
+            "lt" => Format::Lt(Compression::None),
+            "lt.gz" => Format::Lt(Compression::Gzip),
+            "lt.zst" => Format::Lt(Compression::Zstd),
+            "columnar" => Format::Columnar,
+            _ => bail!(
+                "unknown trace format `{}`; expected one of {}",
+                name,
+                Format::NAMES.join(", ")
+            ),
+        })
+    }
+
+    /// Guess the format from the extension of `filename`
+    pub fn from_filename(filename: &str) -> Option<Format> {
+        Format::NAMES
+            .iter()
+            .filter(|name| filename.ends_with(&format!(".{}", name)))
+            .max_by_key(|name| name.len())
+            .and_then(|name| Format::from_name(name).ok())
+    }
+}
+
+/// Write the `selection` of the trace stored in `cs` to `filename` in the
+/// given `format`
+pub(crate) fn convert(
+    cs: &ConstraintSet,
+    selection: &TraceSelection,
+    format: Format,
+    filename: &str,
+) -> Result<()> {
+    match format {
+        Format::Csv => to_csv(cs, selection, filename),
+        Format::Json { gzip } => to_json(cs, selection, gzip, filename),
+        Format::Lt(compression) => {
+            let out = BufWriter::new(
+                File::create(filename)
+                    .with_context(|| anyhow!("opening {}", filename.bold().yellow()))?,
+            );
+            export::write_binary_trace(cs, selection, out, compression)
+        }
+        Format::Columnar => to_columnar(cs, selection, filename),
+    }
+}
 
+/// The selected columns of `cs` that have been filled, sorted by handle
+fn selected_columns<'a>(
+    cs: &'a ConstraintSet,
+    selection: &TraceSelection,
+) -> Vec<(&'a Column, &'a ValueBacking)> {
     cs.columns
-        .modules()
-        .par_iter()
-        .map(|module| {
-            if exclude.contains(&module) {
-                return Ok(());
-            }
+        .iter()
+        .filter(|(_, c)| selection.includes(&c.handle))
+        .filter_map(|(r, c)| Some((c, cs.columns.backing(&r)?)))
+        .sorted_by(|x, y| x.0.handle.cmp(&y.0.handle))
+        .collect()
+}
+
+fn to_csv(cs: &ConstraintSet, selection: &TraceSelection, filename: &str) -> Result<()> {
+    let base_filename = Path::new(filename);
+    let columns = selected_columns(cs, selection);
 
+    columns
+        .iter()
+        .group_by(|c| &c.0.handle.module)
+        .into_iter()
+        .map(|(module, columns)| (module, columns.collect::<Vec<_>>()))
+        .collect::<Vec<_>>()
+        .par_iter()
+        .map(|(module, columns)| {
             let filename = base_filename.with_file_name(format!(
                 "{}_{}",
                 base_filename.file_name().unwrap().to_str().unwrap(),
@@ -32,33 +115,36 @@ pub(crate) fn to_csv(cs: &ConstraintSet, exclude: &[String], filename: &str) ->
             info!("Writing {}", filename.display());
 
             let mut file = BufWriter::new(File::create(&filename)?);
+            file.write_all(
+                columns
+                    .iter()
+                    .map(|c| &c.0.handle.name)
+                    .join(",")
+                    .as_bytes(),
+            )?;
+            file.write_all(b"\n")?;
 
-            info!("Exporting {}", module);
-            let (column_refs, column_names): (Vec<ColumnRef>, Vec<Handle>) = cs
-                .columns
-                .iter_module(&module)
-                .map(|c| (c.0.clone(), cs.handle(&c.0).clone()))
-                .sorted_by(|x, y| x.1.cmp(&y.1))
-                .unzip();
-
-            file.write(column_names.iter().map(|h| &h.name).join(",").as_bytes())?;
-            file.write(&[b'\n'])?;
-            let max_i = cs.iter_len(&module);
-            dbg!(&module, cs.iter_len(&module));
-            for i in 0..max_i {
-                file.write(
-                    column_refs
-                        .iter()
-                        .map(|col| {
-                            cs.columns
-                                .get(&col, i.try_into().unwrap(), false)
-                                .unwrap_or_default()
-                                .pretty()
-                        })
+            // Columns of a module may have different lengths, e.g. when
+            // interleaved; shorter ones are left blank
+            let mut values = columns
+                .iter()
+                .map(|c| selection.values(c.1, &cs.columns))
+                .collect::<Vec<_>>();
+            loop {
+                let row = values
+                    .iter_mut()
+                    .map(|xs| xs.next().map(|x| x.pretty()))
+                    .collect::<Vec<_>>();
+                if row.iter().all(Option::is_none) {
+                    break;
+                }
+                file.write_all(
+                    row.into_iter()
+                        .map(Option::unwrap_or_default)
                         .join(",")
                         .as_bytes(),
                 )?;
-                file.write(&[b'\n'])?;
+                file.write_all(b"\n")?;
             }
 
             Ok(file.flush()?)
@@ -66,15 +152,34 @@ pub(crate) fn to_csv(cs: &ConstraintSet, exclude: &[String], filename: &str) ->
         .collect::<Result<_>>()
 }
 
-pub(crate) fn to_json(cs: &ConstraintSet, exclude: &[String], filename: &str) -> Result<()> {
-    let mut out = BufWriter::new(
+fn to_json(
+    cs: &ConstraintSet,
+    selection: &TraceSelection,
+    gzip: bool,
+    filename: &str,
+) -> Result<()> {
+    let file = BufWriter::new(
         File::create(filename).with_context(|| anyhow!("opening {}", filename.bold().yellow()))?,
     );
+    if gzip {
+        let mut out = GzEncoder::new(file, flate2::Compression::default());
+        write_json(cs, selection, &mut out)?;
+        out.finish()?.flush()?;
+    } else {
+        let mut out = file;
+        write_json(cs, selection, &mut out)?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+fn write_json(cs: &ConstraintSet, selection: &TraceSelection, out: &mut impl Write) -> Result<()> {
     out.write_all(b"{")?;
+    // Only commitments are written, so that the result can be read back
     let mut all_handles = cs
         .columns
         .iter()
-        .filter(|cr| cr.1.kind == Kind::Commitment)
+        .filter(|cr| cr.1.kind == Kind::Commitment && selection.includes(&cr.1.handle))
         .map(|cr| cr.1.handle.to_owned())
         .collect::<Vec<_>>();
     all_handles.sort_by(|a, b| a.module.cmp(&b.module));
@@ -82,10 +187,6 @@ pub(crate) fn to_json(cs: &ConstraintSet, exclude: &[String], filename: &str) ->
     let mut modules = modules.into_iter().peekable();
 
     while let Some((module, handles)) = modules.next() {
-        if exclude.contains(module) {
-            continue;
-        }
-
         out.write_all(format!("\"{}\": {{\"Trace\":{{", module).as_bytes())?;
         let mut handles = handles.into_iter().peekable();
         while let Some(handle) = handles.next() {
@@ -95,8 +196,8 @@ pub(crate) fn to_json(cs: &ConstraintSet, exclude: &[String], filename: &str) ->
                 .columns
                 .backing(&ColumnRef::from_handle(handle.clone()))
                 .unwrap_or(&empty_backing);
-            let values = backing
-                .iter_without_spilling(&cs.columns)
+            let values = selection
+                .values(backing, &cs.columns)
                 .map(|x| format!("\"{}\"", x.pretty_with_base(Base::Dec)))
                 .join(",");
             out.write_all(values.as_bytes())?;
@@ -116,3 +217,44 @@ pub(crate) fn to_json(cs: &ConstraintSet, exclude: &[String], filename: &str) ->
     out.write_all(b"}")?;
     Ok(())
 }
+
+#[derive(Serialize)]
+struct ColumnarEntry<'a> {
+    module: &'a str,
+    name: &'a str,
+    file: String,
+    bytes_per_element: usize,
+    length: usize,
+}
+
+fn to_columnar(cs: &ConstraintSet, selection: &TraceSelection, dirname: &str) -> Result<()> {
+    let dir = Path::new(dirname);
+    std::fs::create_dir_all(dir).with_context(|| anyhow!("creating {}", dir.display()))?;
+
+    let manifest = selected_columns(cs, selection)
+        .into_par_iter()
+        .enumerate()
+        .map(|(i, (column, backing))| {
+            let handle = &column.handle;
+            let entry = ColumnarEntry {
+                module: &handle.module,
+                name: &handle.name,
+                file: format!("{}.bin", i),
+                bytes_per_element: column.t.byte_size_in(cs.field).clamp(1, 32),
+                length: selection.rows(backing.len()).len(),
+            };
+            debug!("Exporting {}", handle.pretty());
+            let mut out = BufWriter::new(File::create(dir.join(&entry.file))?);
+            for x in selection.values(backing, &cs.columns) {
+                export::write_element(&x, entry.bytes_per_element, &mut out)
+                    .with_context(|| anyhow!("while exporting {}", handle.pretty()))?;
+            }
+            out.flush()?;
+            Ok(entry)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let manifest_file = dir.join("manifest.json");
+    serde_json::to_writer_pretty(BufWriter::new(File::create(&manifest_file)?), &manifest)
+        .with_context(|| anyhow!("writing {}", manifest_file.display()))
+}
@@ -1,7 +1,6 @@
-use crate::column::Computation;
+use crate::column::{ColumnID, Computation};
 use crate::compiler::codetyper::Tty;
-use crate::compiler::{Constraint, ConstraintSet, Expression, Intrinsic, Node};
-use crate::constants;
+use crate::compiler::{ColumnRef, Constraint, ConstraintSet, Expression, Intrinsic, Node};
 use crate::pretty::Pretty;
 use crate::structs::Handle;
 use anyhow::*;
@@ -11,6 +10,7 @@ use itertools::Itertools;
 use owo_colors::XtermColors;
 use owo_colors::{colored::Color, OwoColorize};
 use std::cmp::Ordering;
+use std::collections::HashSet;
 
 fn priority(a: Intrinsic, b: Intrinsic) -> Ordering {
     match (a, b) {
@@ -184,15 +184,26 @@ fn pretty_expr(n: &Node, prev: Option<Intrinsic>, tty: &mut Tty, show_types: boo
     }
 }
 
+/// Whether `r` is part of the columns to display
+fn in_scope(cs: &ConstraintSet, scope: Option<&HashSet<ColumnID>>, r: &ColumnRef) -> bool {
+    scope
+        .map(|s| s.contains(&cs.columns.id_of(r)))
+        .unwrap_or(true)
+}
+
 fn render_constraints(
     cs: &ConstraintSet,
     only: Option<&Vec<String>>,
     skip: &[String],
+    scope: Option<&HashSet<ColumnID>>,
     show_types: bool,
 ) {
     println!("\n{}", "=== Constraints ===".bold().yellow());
     for c in cs.constraints.iter() {
-        if !skip.contains(&c.name()) && only.map(|o| o.contains(&c.name())).unwrap_or(true) {
+        if !skip.contains(&c.name())
+            && only.map(|o| o.contains(&c.name())).unwrap_or(true)
+            && c.dependencies().iter().all(|r| in_scope(cs, scope, r))
+        {
             match c {
                 Constraint::Vanishes {
                     handle,
@@ -201,13 +212,14 @@ fn render_constraints(
                 } => {
                     let mut tty = Tty::new().with_guides();
                     println!(
-                        "\n{}{} :=",
+                        "\n{}{} {} :=",
                         handle.pretty(),
                         if let Some(domain) = domain {
                             domain.to_string()
                         } else {
                             String::new()
-                        }
+                        },
+                        format!("[degree {}]", expr.degree()).dimmed()
                     );
                     pretty_expr(expr, None, &mut tty, show_types);
                     println!("{}", tty.page_feed());
@@ -255,7 +267,7 @@ fn render_constraints(
                     ..
                 } => {
                     println!("\n{} :=", handle.pretty());
-                    if reference.bit_size() > constants::FIELD_BITSIZE {
+                    if reference.t().m().bit_size_in(cs.field) > cs.field.bit_size() {
                         println!("TODO XXX");
                     } else {
                         println!(
@@ -301,14 +313,19 @@ fn render_constants(cs: &ConstraintSet) {
     }
 }
 
-fn render_columns(cs: &ConstraintSet) {
+fn render_columns(cs: &ConstraintSet, scope: Option<&HashSet<ColumnID>>) {
     println!("\n{}", "=== Columns ===".bold().yellow());
 
     println!(
         "{:>4}{:>80}{:>6}{:>4}{:>50}",
         "ID", "Name", "Type", "×", "Reg."
     );
-    for (r, col) in cs.columns.iter().sorted_by_key(|c| c.1.register) {
+    for (r, col) in cs
+        .columns
+        .iter()
+        .filter(|(r, _)| in_scope(cs, scope, r))
+        .sorted_by_key(|c| c.1.register)
+    {
         println!(
             "{:>4}{:>80}{:>6}{:>4}{:>50}",
             r.as_id(),
@@ -333,9 +350,13 @@ fn render_columns(cs: &ConstraintSet) {
     }
 }
 
-fn render_computations(cs: &ConstraintSet) {
+fn render_computations(cs: &ConstraintSet, scope: Option<&HashSet<ColumnID>>) {
     println!("\n{}", "=== Computations ===".bold().yellow());
-    for comp in cs.computations.iter() {
+    for comp in cs
+        .computations
+        .iter()
+        .filter(|c| c.targets().iter().any(|t| in_scope(cs, scope, t)))
+    {
         match comp {
             Computation::Composite { target, exp } => {
                 println!("{} = {}", target.pretty(), exp.pretty())
@@ -383,6 +404,21 @@ fn render_computations(cs: &ConstraintSet) {
             Computation::ExoConstant { value, target } => {
                 println!("{} := {}", target.pretty(), value)
             }
+            Computation::Limbs {
+                source,
+                limbs,
+                bits,
+            } => println!(
+                "[{}] ≜ {}-bits limbs of {}",
+                limbs.iter().map(|c| cs.handle(c).pretty()).join(" "),
+                bits,
+                cs.handle(source).pretty()
+            ),
+            Computation::Carries { sums, carries, .. } => println!(
+                "[{}] ≜ carries of [{}]",
+                carries.iter().map(|c| cs.handle(c).pretty()).join(" "),
+                sums.iter().map(|s| s.pretty_with_handle(cs)).join(", ")
+            ),
         }
     }
 }
@@ -435,6 +471,7 @@ pub(crate) fn debug(
     settings: DebugSettings,
     only: Option<&Vec<String>>,
     skip: &[String],
+    scope: Option<&HashSet<ColumnID>>,
 ) -> Result<()> {
     if settings.modules {
         render_modules(cs);
@@ -443,13 +480,13 @@ pub(crate) fn debug(
         render_constants(cs);
     }
     if settings.constraints {
-        render_constraints(cs, only, skip, settings.types);
+        render_constraints(cs, only, skip, scope, settings.types);
     }
     if settings.columns {
-        render_columns(cs);
+        render_columns(cs, scope);
     }
     if settings.computations {
-        render_computations(cs);
+        render_computations(cs, scope);
     }
     if settings.perspectives {
         render_perspectives(cs);
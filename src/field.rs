@@ -0,0 +1,94 @@
+//! The prime fields a constraint set can be compiled for.
+//!
+//! BLS12-377 values are handled natively by arkworks; values in the other
+//! fields are stored as integers reduced modulo the field order.
+use anyhow::*;
+use num_bigint::BigInt;
+use num_traits::Num;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+static BLS12_377: OnceLock<BigInt> = OnceLock::new();
+static BN254: OnceLock<BigInt> = OnceLock::new();
+static GOLDILOCKS: OnceLock<BigInt> = OnceLock::new();
+static KOALABEAR: OnceLock<BigInt> = OnceLock::new();
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Field {
+    #[default]
+    Bls12_377,
+    Bn254,
+    /// 2^64 - 2^32 + 1
+    Goldilocks,
+    /// 2^31 - 2^24 + 1
+    KoalaBear,
+}
+impl Field {
+    pub const NAMES: [&'static str; 4] = ["bls12-377", "bn254", "goldilocks", "koalabear"];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Field::Bls12_377 => "bls12-377",
+            Field::Bn254 => "bn254",
+            Field::Goldilocks => "goldilocks",
+            Field::KoalaBear => "koalabear",
+        }
+    }
+
+    /// The order of the field
+    pub fn modulus(&self) -> &'static BigInt {
+        let (cell, hex) = match self {
+            Field::Bls12_377 => (
+                &BLS12_377,
+                "12ab655e9a2ca55660b44d1e5c37b00159aa76fed00000010a11800000000001",
+            ),
+            Field::Bn254 => (
+                &BN254,
+                "30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001",
+            ),
+            Field::Goldilocks => (&GOLDILOCKS, "ffffffff00000001"),
+            Field::KoalaBear => (&KOALABEAR, "7f000001"),
+        };
+        cell.get_or_init(|| BigInt::from_str_radix(hex, 16).unwrap())
+    }
+
+    /// The largest bit size of the values that can be stored in a single
+    /// field element
+    pub fn bit_size(&self) -> usize {
+        match self {
+            // the historical width of native BLS12-377 columns, that the layout
+            // of registers and exo-values depends on
+            Field::Bls12_377 => 254,
+            _ => self.modulus().bits() as usize - 1,
+        }
+    }
+
+    /// Whether values in this field are represented by arkworks field
+    /// elements rather than reduced integers
+    pub fn is_ark(&self) -> bool {
+        matches!(self, Field::Bls12_377)
+    }
+}
+impl std::str::FromStr for Field {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s.to_lowercase().as_str() {
+            "bls12-377" | "bls12_377" => Field::Bls12_377,
+            "bn254" => Field::Bn254,
+            "goldilocks" => Field::Goldilocks,
+            "koalabear" => Field::KoalaBear,
+            _ => bail!(
+                "unknown field `{}`; expected one of {}",
+                s,
+                Field::NAMES.join(", ")
+            ),
+        })
+    }
+}
+impl std::fmt::Display for Field {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
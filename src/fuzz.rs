@@ -0,0 +1,361 @@
+//! Random generation of traces for a constraint set, looking for traces that
+//! satisfy all of its constraints, then for the columns that may vary in them
+//! without breaking any constraint.
+use anyhow::*;
+use log::*;
+use owo_colors::OwoColorize;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde_json::json;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{
+    check::{self, DebugSettings},
+    compiler::{ConstraintSet, Kind},
+    compute,
+    structs::Handle,
+};
+
+pub struct FuzzSettings {
+    /// the number of rows of the generated modules
+    pub rows: usize,
+    /// how many traces to try before giving up
+    pub iterations: usize,
+    /// the seed of the random generator
+    pub seed: u64,
+    /// how many times to mutate a column of a passing trace before
+    /// considering it as constrained
+    pub attempts: usize,
+}
+
+/// A column filled by the fuzzer
+struct Input {
+    handle: Handle,
+    /// the maximal number of bits of the generated values
+    bits: usize,
+}
+
+/// The values of all the inputs of a constraint set
+#[derive(Clone)]
+pub struct Trace {
+    /// the number of rows of each module
+    lens: BTreeMap<String, usize>,
+    /// the values of each input
+    values: Vec<Vec<u64>>,
+}
+
+/// A change to a passing trace that still satisfies all the constraints
+pub struct Variation {
+    pub column: Handle,
+    pub row: usize,
+    pub old: u64,
+    pub new: u64,
+}
+
+/// The outcome of a fuzzing session
+pub struct FuzzReport {
+    /// how many traces have been checked
+    pub checked: usize,
+    /// a shrunk trace satisfying all the constraints, if one has been found
+    pub passing: Option<Trace>,
+    /// the constraints still failing on the best trace found otherwise
+    pub failing: Vec<Handle>,
+    /// per column, a variation showing that it is under-constrained
+    pub under_constrained: Vec<Variation>,
+    inputs: Vec<Handle>,
+}
+impl FuzzReport {
+    /// The passing trace, formatted as a JSON trace file
+    pub fn passing_trace(&self) -> Option<serde_json::Value> {
+        self.passing.as_ref().map(|t| t.to_json(self.inputs.iter()))
+    }
+}
+impl std::fmt::Display for FuzzReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(passing) = self.passing.as_ref() {
+            writeln!(
+                f,
+                "{} passing trace found after {} checks ({})",
+                "✓".green().bold(),
+                self.checked,
+                passing
+                    .lens
+                    .iter()
+                    .map(|(m, l)| format!("{}: {} rows", m, l))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+        } else {
+            writeln!(
+                f,
+                "{} no passing trace found after {} checks; still failing: {}",
+                "✗".red().bold(),
+                self.checked,
+                self.failing
+                    .iter()
+                    .map(|h| h.to_string().red().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+        }
+
+        if !self.under_constrained.is_empty() {
+            writeln!(
+                f,
+                "{} under-constrained columns",
+                self.under_constrained.len().to_string().yellow().bold()
+            )?;
+            for v in self.under_constrained.iter() {
+                writeln!(
+                    f,
+                    "  {}: row {} may be {} as well as {}",
+                    v.column.to_string().bold(),
+                    v.row,
+                    v.new.yellow(),
+                    v.old
+                )?;
+            }
+        }
+        std::fmt::Result::Ok(())
+    }
+}
+
+impl Trace {
+    fn to_json<'a>(&self, inputs: impl Iterator<Item = &'a Handle>) -> serde_json::Value {
+        let mut modules = BTreeMap::<&str, BTreeMap<&str, &[u64]>>::new();
+        for (h, values) in inputs.zip(self.values.iter()) {
+            modules
+                .entry(&h.module)
+                .or_default()
+                .insert(&h.name, values);
+        }
+        json!(modules)
+    }
+}
+
+struct Fuzzer<'a> {
+    cs: &'a mut ConstraintSet,
+    inputs: Vec<Input>,
+    rng: StdRng,
+    checked: usize,
+}
+impl<'a> Fuzzer<'a> {
+    fn new(cs: &'a mut ConstraintSet, seed: u64) -> Self {
+        // Only the commitments are filled; the other columns will be computed
+        // from them. Columns sharing a register are only filled once.
+        let field_bits = cs.field.bit_size();
+        let mut registers = HashSet::new();
+        let inputs = cs
+            .columns
+            .iter_cols()
+            .filter(|c| matches!(c.kind, Kind::Commitment))
+            .filter(|c| c.register.map(|r| registers.insert(r)).unwrap_or(true))
+            .map(|c| Input {
+                handle: c.handle.clone(),
+                bits: c.t.bit_size_in(cs.field).min(field_bits - 1).min(64),
+            })
+            .collect();
+
+        Fuzzer {
+            cs,
+            inputs,
+            rng: StdRng::seed_from_u64(seed),
+            checked: 0,
+        }
+    }
+
+    /// A random value fitting on `bits` bits, biased toward remarkable ones
+    fn value(&mut self, bits: usize) -> u64 {
+        let max = if bits >= 64 {
+            u64::MAX
+        } else {
+            (1 << bits) - 1
+        };
+        match self.rng.gen_range(0..10) {
+            0..=2 => 0,
+            3..=4 => 1.min(max),
+            5 => max,
+            _ => self.rng.gen_range(0..=max),
+        }
+    }
+
+    fn random_trace(&mut self, rows: usize) -> Trace {
+        let lens = self
+            .inputs
+            .iter()
+            .map(|i| (i.handle.module.clone(), rows))
+            .collect();
+        let values = (0..self.inputs.len())
+            .map(|i| (0..rows).map(|_| self.value(self.inputs[i].bits)).collect())
+            .collect();
+        Trace { lens, values }
+    }
+
+    /// Alter a random cell of `trace`, either with a new value, or with the
+    /// one of a neighbouring cell
+    fn mutate(&mut self, trace: &Trace) -> Trace {
+        let mut r = trace.clone();
+        let i = self.rng.gen_range(0..self.inputs.len());
+        if r.values[i].is_empty() {
+            return r;
+        }
+        let row = self.rng.gen_range(0..r.values[i].len());
+        let new = match self.rng.gen_range(0..3) {
+            // copy the value of another column of the same module
+            0 => {
+                let j = self.rng.gen_range(0..self.inputs.len());
+                if self.inputs[j].handle.module == self.inputs[i].handle.module {
+                    r.values[j][row]
+                } else {
+                    self.value(self.inputs[i].bits)
+                }
+            }
+            // copy the value of the previous row
+            1 if row > 0 => r.values[i][row - 1],
+            _ => self.value(self.inputs[i].bits),
+        };
+        r.values[i][row] = new;
+        r
+    }
+
+    /// How many rows of constraints do not hold on `trace`; or `None` if it
+    /// can not be computed
+    fn score(&mut self, trace: &Trace) -> Result<Option<(usize, Vec<Handle>)>> {
+        self.checked += 1;
+        let json = trace.to_json(self.inputs.iter().map(|i| &i.handle));
+        self.cs.columns.reset();
+        if let Err(e) = compute::compute_trace_str(&serde_json::to_vec(&json)?, self.cs, false) {
+            debug!("discarding trace: {:?}", e);
+            return Ok(None);
+        }
+        let report = check::check(
+            self.cs,
+            &None,
+            &[],
+            DebugSettings::new().continue_on_error(true).witnesses(0),
+        )?;
+        Ok(Some((
+            report.failures.iter().map(|f| f.rows.len().max(1)).sum(),
+            report.failures.into_iter().map(|f| f.handle).collect(),
+        )))
+    }
+
+    fn passes(&mut self, trace: &Trace) -> Result<bool> {
+        Ok(matches!(self.score(trace)?, Some((0, _))))
+    }
+
+    /// Remove rows, then zero cells of a passing trace, as long as it keeps
+    /// passing
+    fn shrink(&mut self, mut trace: Trace) -> Result<Trace> {
+        let modules = trace.lens.keys().cloned().collect::<Vec<_>>();
+        for module in modules {
+            while trace.lens[&module] > 1 {
+                let mut smaller = trace.clone();
+                *smaller.lens.get_mut(&module).unwrap() -= 1;
+                for (i, input) in self.inputs.iter().enumerate() {
+                    if input.handle.module == module {
+                        smaller.values[i].pop();
+                    }
+                }
+                if self.passes(&smaller)? {
+                    trace = smaller;
+                } else {
+                    break;
+                }
+            }
+        }
+
+        for i in 0..self.inputs.len() {
+            for row in 0..trace.values[i].len() {
+                if trace.values[i][row] != 0 {
+                    let mut zeroed = trace.clone();
+                    zeroed.values[i][row] = 0;
+                    if self.passes(&zeroed)? {
+                        trace = zeroed;
+                    }
+                }
+            }
+        }
+        Ok(trace)
+    }
+
+    /// Try to alter each column of a passing trace while keeping it passing
+    fn vary(&mut self, trace: &Trace, attempts: usize) -> Result<Vec<Variation>> {
+        let mut r = Vec::new();
+        for i in 0..self.inputs.len() {
+            if trace.values[i].is_empty() {
+                continue;
+            }
+            for _ in 0..attempts {
+                let row = self.rng.gen_range(0..trace.values[i].len());
+                let old = trace.values[i][row];
+                let new = self.value(self.inputs[i].bits);
+                if new == old {
+                    continue;
+                }
+                let mut varied = trace.clone();
+                varied.values[i][row] = new;
+                if self.passes(&varied)? {
+                    r.push(Variation {
+                        column: self.inputs[i].handle.clone(),
+                        row,
+                        old,
+                        new,
+                    });
+                    break;
+                }
+            }
+        }
+        Ok(r)
+    }
+}
+
+/// Generate and mutate random traces for `cs` until one satisfies all its
+/// constraints; then shrink it and look for the columns that may vary in it.
+pub fn fuzz(cs: &mut ConstraintSet, settings: &FuzzSettings) -> Result<FuzzReport> {
+    let mut fuzzer = Fuzzer::new(cs, settings.seed);
+    if fuzzer.inputs.is_empty() {
+        bail!("no column to fill in the constraint set")
+    }
+
+    let mut best = fuzzer.random_trace(settings.rows);
+    let mut best_score = fuzzer.score(&best)?;
+    while fuzzer.checked < settings.iterations && !matches!(best_score.as_ref(), Some((0, _))) {
+        // Restart from scratch from time to time to escape local minima
+        let candidate = if best_score.is_none() || fuzzer.rng.gen_bool(0.05) {
+            fuzzer.random_trace(settings.rows)
+        } else {
+            fuzzer.mutate(&best)
+        };
+        let score = fuzzer.score(&candidate)?;
+        let better = match (score.as_ref(), best_score.as_ref()) {
+            (Some(_), None) => true,
+            (Some((new, _)), Some((old, _))) => new <= old,
+            _ => false,
+        };
+        if better {
+            best = candidate;
+            best_score = score;
+        }
+    }
+
+    let inputs = fuzzer.inputs.iter().map(|i| i.handle.clone()).collect();
+    if let Some((0, _)) = best_score {
+        let passing = fuzzer.shrink(best)?;
+        let under_constrained = fuzzer.vary(&passing, settings.attempts)?;
+        Ok(FuzzReport {
+            checked: fuzzer.checked,
+            passing: Some(passing),
+            failing: Vec::new(),
+            under_constrained,
+            inputs,
+        })
+    } else {
+        Ok(FuzzReport {
+            checked: fuzzer.checked,
+            passing: None,
+            failing: best_score.map(|s| s.1).unwrap_or_default(),
+            under_constrained: Vec::new(),
+            inputs,
+        })
+    }
+}
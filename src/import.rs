@@ -1,4 +1,4 @@
-use super::compiler::{ColumnRef, Magma};
+use super::compiler::{ColumnRef, Kind, Magma};
 use crate::column::Value as CValue;
 use anyhow::*;
 use cached::Cached;
@@ -13,16 +13,16 @@ use rayon::prelude::*;
 use serde_json::Value;
 #[cfg(all(target_arch = "x86_64", target_feature = "avx"))]
 use simd_json::BorrowedValue as Value;
-#[cfg(all(target_arch = "x86_64", target_feature = "avx"))]
-use std::io::Read;
 use std::{
+    collections::HashSet,
     fs::File,
-    io::{BufReader, Seek},
+    io::{BufReader, Read, Seek},
 };
 
 use crate::{
     column::{Column, Register},
     compiler::ConstraintSet,
+    field::Field,
     pretty::Pretty,
     structs::Handle,
 };
@@ -160,15 +160,79 @@ impl<Data: AsRef<[u8]>> TraceReader<Data> {
     }
 }
 
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Whether `filename` designates a binary trace, possibly compressed
+pub fn is_binary_trace(filename: &str) -> bool {
+    filename
+        .trim_end_matches(".gz")
+        .trim_end_matches(".zst")
+        .ends_with("lt")
+}
+
 #[time("info", "Parsing binary traces")]
 pub fn parse_binary_trace(tracefile: &str, cs: &mut ConstraintSet, keep_raw: bool) -> Result<()> {
     let file = File::open(tracefile)
         .with_context(|| anyhow!("opening {}", tracefile.bright_white().bold()))?;
-    let mut trace_reader = TraceReader::from(unsafe {
+    let mmap = unsafe {
         memmap2::MmapOptions::new()
             .map(&file)
             .with_context(|| anyhow!("memory mapping {}", tracefile.bright_white().bold()))?
-    });
+    };
+
+    // Compressed traces have to be inflated in memory first
+    if mmap.starts_with(GZIP_MAGIC) {
+        let mut content = Vec::new();
+        flate2::read::GzDecoder::new(&mmap[..])
+            .read_to_end(&mut content)
+            .with_context(|| anyhow!("inflating {}", tracefile.bright_white().bold()))?;
+        fill_traces_from_binary(TraceReader::from(content), cs, keep_raw)
+    } else if mmap.starts_with(ZSTD_MAGIC) {
+        let content = zstd::decode_all(&mmap[..])
+            .with_context(|| anyhow!("inflating {}", tracefile.bright_white().bold()))?;
+        fill_traces_from_binary(TraceReader::from(content), cs, keep_raw)
+    } else {
+        fill_traces_from_binary(TraceReader::from(mmap), cs, keep_raw)
+    }
+}
+
+/// The handles of the registers only storing computed columns
+fn computed_registers(cs: &ConstraintSet) -> HashSet<Handle> {
+    let committed = cs
+        .columns
+        .iter_cols()
+        .filter(|c| matches!(c.kind, Kind::Commitment))
+        .filter_map(|c| c.register)
+        .collect::<HashSet<_>>();
+    cs.columns
+        .registers
+        .iter()
+        .enumerate()
+        .filter(|(id, _)| !committed.contains(id))
+        .filter_map(|(_, r)| r.handle.clone())
+        .collect()
+}
+
+/// Bring `x` into `field` when computing in it, failing if it is not an
+/// element of `field` though it belongs to a column narrow enough to fit in it
+fn in_field(x: BigInt, magma: Magma, field: Field) -> Result<CValue> {
+    if (*crate::IS_NATIVE.read().unwrap() || !field.is_ark())
+        && magma.bit_size_in(field) <= field.bit_size()
+        && &x >= field.modulus()
+    {
+        bail!("{} is not a {} element", x, field)
+    }
+    Ok(CValue::try_from(x)?.in_field(field))
+}
+
+fn fill_traces_from_binary<Data: AsRef<[u8]>>(
+    mut trace_reader: TraceReader<Data>,
+    cs: &mut ConstraintSet,
+    keep_raw: bool,
+) -> Result<()> {
+    let field = cs.field;
+    let computed_registers = computed_registers(cs);
     let trace_map = trace_reader.map()?;
     for trace_register in trace_map.headers.into_iter() {
         let column_ref: ColumnRef = trace_register.handle.clone().into();
@@ -190,9 +254,9 @@ pub fn parse_binary_trace(tracefile: &str, cs: &mut ConstraintSet, keep_raw: boo
                             )
                             .ok_or_else(|| anyhow!("error reading {}th element", i))
                             .and_then(|bs| {
-                                CValue::try_from(BigInt::from_bytes_be(Sign::Plus, bs))
+                                in_field(BigInt::from_bytes_be(Sign::Plus, bs), *magma, field)
                                     .with_context(|| anyhow!("while parsing {}th element", i))
-                                    .and_then(|x| magma.rm().validate(x))
+                                    .and_then(|x| magma.rm().validate(x, field))
                             })
                             .with_context(|| anyhow!("reading {}th element", i))
                     }
@@ -229,15 +293,19 @@ pub fn parse_binary_trace(tracefile: &str, cs: &mut ConstraintSet, keep_raw: boo
                 xs.reverse();
             }
 
-            let module_raw_size =
-                cs.effective_len_or_set(&trace_register.handle.module, xs.len() as isize);
-            if xs.len() as isize != module_raw_size {
-                bail!(
-                    "{} has an incorrect length: expected {}, found {}",
-                    trace_register.handle.to_string().blue(),
-                    module_raw_size.to_string().red().bold(),
-                    xs.len().to_string().yellow().bold(),
-                );
+            // Computed registers, e.g. from a trace written by `compute`, may
+            // have a different length than their module
+            if !computed_registers.contains(&trace_register.handle) {
+                let module_raw_size =
+                    cs.effective_len_or_set(&trace_register.handle.module, xs.len() as isize);
+                if xs.len() as isize != module_raw_size {
+                    bail!(
+                        "{} has an incorrect length: expected {}, found {}",
+                        trace_register.handle.to_string().blue(),
+                        module_raw_size.to_string().red().bold(),
+                        xs.len().to_string().yellow().bold(),
+                    );
+                }
             }
 
             cs.columns
@@ -319,7 +387,13 @@ pub fn read_trace_str(tracestr: &[u8], cs: &mut ConstraintSet, keep_raw: bool) -
 }
 
 #[cfg(not(all(target_arch = "x86_64", target_feature = "avx")))]
-fn parse_column(xs: &[Value], h: &Handle, t: Magma, keep_raw: bool) -> Result<Vec<CValue>> {
+fn parse_column(
+    xs: &[Value],
+    h: &Handle,
+    t: Magma,
+    field: Field,
+    keep_raw: bool,
+) -> Result<Vec<CValue>> {
     let mut cache_num = cached::SizedCache::with_size(200000); // ~1.60MB cache
     let mut cache_str = cached::SizedCache::with_size(200000); // ~1.60MB cache
     let mut r = if keep_raw {
@@ -332,13 +406,15 @@ fn parse_column(xs: &[Value], h: &Handle, t: Magma, keep_raw: bool) -> Result<Ve
         .map(|x| match x {
             Value::Number(n) => t.rm().validate(
                 cache_num
-                    .cache_get_or_set_with(n, || CValue::from(n.as_str()))
+                    .cache_get_or_set_with(n, || CValue::from_str_in(n.as_str(), field))
                     .to_owned(),
+                field,
             ),
             Value::String(s) => t.rm().validate(
                 cache_str
-                    .cache_get_or_set_with(s.clone(), || CValue::from(s.as_str()))
+                    .cache_get_or_set_with(s.clone(), || CValue::from_str_in(s.as_str(), field))
                     .to_owned(),
+                field,
             ),
             _ => bail!("expected numeric value, found `{}`", x),
         })
@@ -352,7 +428,13 @@ fn parse_column(xs: &[Value], h: &Handle, t: Magma, keep_raw: bool) -> Result<Ve
 }
 
 #[cfg(all(target_arch = "x86_64", target_feature = "avx"))]
-fn parse_column(xs: &[Value], h: &Handle, t: Magma, keep_raw: bool) -> Result<Vec<CValue>> {
+fn parse_column(
+    xs: &[Value],
+    h: &Handle,
+    t: Magma,
+    field: Field,
+    keep_raw: bool,
+) -> Result<Vec<CValue>> {
     let mut cache = cached::SizedCache::with_size(200000); // ~1.60MB cache
     let mut r = if keep_raw {
         Vec::new()
@@ -375,8 +457,9 @@ fn parse_column(xs: &[Value], h: &Handle, t: Magma, keep_raw: bool) -> Result<Ve
             };
             t.rm().validate(
                 cache
-                    .cache_get_or_set_with(s.clone(), || CValue::from(s.as_str()))
+                    .cache_get_or_set_with(s.clone(), || CValue::from_str_in(s.as_str(), field))
                     .to_owned(),
+                field,
             )
         })
         .collect::<Result<Vec<_>>>()?;
@@ -434,7 +517,7 @@ pub fn fill_traces_from_json(
                     let module_spilling = module_spilling
                         .ok_or_else(|| anyhow!("no spilling found for {}", handle.pretty()))?;
 
-                    let mut xs = parse_column(xs, handle.as_handle(), *t, keep_raw)
+                    let mut xs = parse_column(xs, handle.as_handle(), *t, cs.field, keep_raw)
                         .with_context(|| anyhow!("importing {}", handle.pretty()))?;
 
                     // If the parsed column is not long enought w.r.t. the
@@ -473,7 +556,7 @@ pub fn fill_traces_from_json(
                     let module_spilling = module_spilling
                         .ok_or_else(|| anyhow!("no spilling found for {}", handle.pretty()))?;
 
-                    let mut xs = parse_column(xs, handle.as_handle(), *magma, keep_raw)
+                    let mut xs = parse_column(xs, handle.as_handle(), *magma, cs.field, keep_raw)
                         .with_context(|| anyhow!("importing {}", handle.pretty()))?;
 
                     // If the parsed column is not long enought w.r.t. the
@@ -1,6 +1,7 @@
 use crate::{
     column::Value,
     compiler::ColumnRef,
+    field::Field,
     pretty::{self, Pretty},
 };
 use anyhow::*;
@@ -106,24 +107,14 @@ pub enum Function {
     Mul,
 }
 impl Function {
-    fn apply(&self, args: &[Value]) -> Value {
+    fn apply(&self, args: &[Value], field: Field) -> Value {
+        let mut x = args[0].clone();
         match self {
-            Function::Add => {
-                let mut x = args[0].clone();
-                x.add_assign(&args[1]);
-                x
-            }
-            Function::Sub => {
-                let mut x = args[0].clone();
-                x.sub_assign(&args[1]);
-                x
-            }
-            Function::Mul => {
-                let mut x = args[0].clone();
-                x.mul_assign(&args[1]);
-                x
-            }
+            Function::Add => x.add_assign(&args[1], field),
+            Function::Sub => x.sub_assign(&args[1], field),
+            Function::Mul => x.mul_assign(&args[1], field),
         }
+        x
     }
 }
 impl From<&str> for Function {
@@ -171,10 +162,11 @@ impl Node {
         &self,
         get: &F,
         max: isize,
+        field: Field,
     ) -> Vec<isize> {
         (0..max)
             .filter(|i| {
-                let r = self.eval(*i, &get);
+                let r = self.eval(*i, &get, field);
                 match r {
                     Some(Either::Right(b)) => b,
                     Some(Either::Left(_)) => panic!("not a boolean"),
@@ -194,28 +186,29 @@ impl Node {
         &self,
         i: isize,
         get: &F,
+        field: Field,
     ) -> Option<Either<Value, bool>> {
         match self {
             Node::Combinator(c, args) => {
                 let args = args
                     .iter()
-                    .map(|a| a.eval(i, get).map(|x| x.right().unwrap()))
+                    .map(|a| a.eval(i, get, field).map(|x| x.right().unwrap()))
                     .collect::<Option<Vec<_>>>();
                 args.map(|args| Either::Right(c.apply(&args)))
             }
             Node::Comparison(r, args) => {
                 let args = args
                     .iter()
-                    .map(|a| a.eval(i, get))
+                    .map(|a| a.eval(i, get, field))
                     .collect::<Option<Vec<_>>>();
                 args.map(|args| Either::Right(r.apply(&args)))
             }
             Node::Funcall(f, args) => {
                 let args = args
                     .iter()
-                    .map(|a| a.eval(i, get).map(|x| x.left().unwrap()))
+                    .map(|a| a.eval(i, get, field).map(|x| x.left().unwrap()))
                     .collect::<Option<Vec<_>>>();
-                args.map(|args| Either::Left(f.apply(&args)))
+                args.map(|args| Either::Left(f.apply(&args, field)))
             }
             Node::Column(_, column) => get(i, column).map(Either::Left),
             Node::Const(x) => Some(Either::Left(x.clone())),
@@ -299,7 +292,12 @@ fn take_two(stack: &mut Vec<Node>, fname: &str) -> Result<Vec<Node>> {
 }
 
 /// Returns a Node representing the root of the AST parsed from the string representation of a Forth program
-pub fn parse(s: &str, module: &str, columns: &HashMap<String, ColumnRef>) -> Result<Node> {
+pub fn parse(
+    s: &str,
+    module: &str,
+    columns: &HashMap<String, ColumnRef>,
+    field: Field,
+) -> Result<Node> {
     let tokens = s.split_whitespace();
     let mut stack = Vec::new();
 
@@ -341,7 +339,7 @@ pub fn parse(s: &str, module: &str, columns: &HashMap<String, ColumnRef>) -> Res
                     Function::Add => {
                         if let (Node::Const(c1), Node::Const(c2)) = (&args[0], &args[1]) {
                             let mut r = c1.clone();
-                            r.add_assign(c2);
+                            r.add_assign(c2, field);
                             Node::Const(r)
                         } else {
                             Node::Funcall(Function::Add, args)
@@ -350,7 +348,7 @@ pub fn parse(s: &str, module: &str, columns: &HashMap<String, ColumnRef>) -> Res
                     Function::Sub => {
                         if let (Node::Const(c1), Node::Const(c2)) = (&args[0], &args[1]) {
                             let mut r = c1.clone();
-                            r.sub_assign(c2);
+                            r.sub_assign(c2, field);
                             Node::Const(r)
                         } else {
                             Node::Funcall(Function::Sub, args)
@@ -359,7 +357,7 @@ pub fn parse(s: &str, module: &str, columns: &HashMap<String, ColumnRef>) -> Res
                     Function::Mul => {
                         if let (Node::Const(c1), Node::Const(c2)) = (&args[0], &args[1]) {
                             let mut r = c1.clone();
-                            r.mul_assign(c2);
+                            r.mul_assign(c2, field);
                             Node::Const(r)
                         } else {
                             Node::Funcall(Function::Mul, args)
@@ -1,5 +1,5 @@
 use crate::{
-    compiler::{ColumnRef, ConstraintSet},
+    compiler::{ColumnRef, ConstraintSet, EvalSettings},
     pretty::Pretty,
     structs::Handle,
 };
@@ -203,7 +203,7 @@ impl ModuleView {
                                             i,
                                             |handle, i, wrap| cs.columns.get_raw(handle, i, wrap),
                                             &mut None,
-                                            &Default::default(),
+                                            &EvalSettings::new().field(cs.field),
                                         )
                                         .map(|x| x.is_zero())
                                         .unwrap_or(false)
@@ -415,6 +415,7 @@ impl<'a> Inspector<'a> {
                                 &self.current_module().name,
                                 &self.current_module().last_scan,
                                 &column_cache,
+                                self.cs.field,
                             )
                             .run(
                                 &mut t,
@@ -1,6 +1,7 @@
 use crate::{
     column::Value,
     compiler::ColumnRef,
+    field::Field,
     inspect::{
         forth::{self, Node},
         StdTerminal,
@@ -17,13 +18,20 @@ use tui_textarea::{CursorMove, Input, Key, TextArea};
 pub struct ScanInput<'a> {
     module: String,
     columns: &'a HashMap<String, ColumnRef>,
+    field: Field,
     input: TextArea<'a>,
 }
 impl<'a> ScanInput<'a> {
-    pub fn new(module: &str, content: &str, columns: &'a HashMap<String, ColumnRef>) -> Self {
+    pub fn new(
+        module: &str,
+        content: &str,
+        columns: &'a HashMap<String, ColumnRef>,
+        field: Field,
+    ) -> Self {
         let mut r = ScanInput {
             module: module.to_owned(),
             columns,
+            field,
             input: TextArea::from([content]),
         };
         r.input.move_cursor(CursorMove::End);
@@ -31,7 +39,12 @@ impl<'a> ScanInput<'a> {
     }
 
     fn validate(&mut self) -> anyhow::Result<Node> {
-        let r = forth::parse(&self.input.lines()[0], &self.module, self.columns);
+        let r = forth::parse(
+            &self.input.lines()[0],
+            &self.module,
+            self.columns,
+            self.field,
+        );
 
         match &r {
             Err(err) => {
@@ -76,7 +89,7 @@ impl<'a> ScanInput<'a> {
                     return self
                         .validate()
                         .ok()
-                        .map(|node| node.scan(get, max))
+                        .map(|node| node.scan(get, max, self.field))
                         .map(|i| (self.input.into_lines()[0].to_owned(), i));
                 }
                 Input { key: Key::Esc, .. } => {
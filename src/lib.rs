@@ -11,13 +11,13 @@ use std::{
     ffi::{c_uint, CStr, CString},
     sync::RwLock,
 };
-use transformer::{AutoConstraint, ExpansionLevel};
 
 use crate::{
     column::{Computation, Value, ValueBacking},
     compiler::EvalSettings,
 };
 
+mod api;
 mod check;
 mod column;
 mod compiler;
@@ -25,6 +25,7 @@ mod compute;
 mod constants;
 mod dag;
 mod errors;
+mod field;
 mod import;
 mod pretty;
 mod structs;
@@ -33,7 +34,12 @@ mod utils;
 
 pub(crate) static IS_NATIVE: RwLock<bool> = RwLock::new(true);
 
-type Corset = ConstraintSet;
+pub use api::{Column, Corset, TraceSource};
+pub use check::{
+    CheckReport, ClosestTuple, Evaluation, Failure, LookupFailure, MissingTuple, Witness,
+};
+pub use field::Field;
+pub use structs::Handle;
 
 #[derive(Copy, Clone)]
 #[repr(i32)]
@@ -120,11 +126,12 @@ pub struct Trace {
     ids: Vec<String>,
 }
 impl Trace {
-    fn from_constraints(c: &Corset) -> Self {
+    fn from_constraints(c: &ConstraintSet) -> Self {
         let mut r = Trace {
             ..Default::default()
         };
 
+        let field = c.field;
         let rs = c
             .columns
             .all()
@@ -150,7 +157,7 @@ impl Trace {
                                             0,
                                             |_, _, _| Some(Value::zero()),
                                             &mut None,
-                                            &EvalSettings::default(),
+                                            &EvalSettings::new().field(field),
                                         )
                                         .unwrap_or_else(Value::zero),
                                     Computation::Interleaved { .. } => Value::zero(),
@@ -159,6 +166,8 @@ impl Trace {
                                     Computation::SortingConstraints { .. } => Value::zero(),
                                     Computation::ExoOperation { .. } => Value::zero(), // TODO: FIXME:
                                     Computation::ExoConstant { value, .. } => value.clone(),
+                                    Computation::Limbs { .. } => Value::zero(),
+                                    Computation::Carries { .. } => Value::zero(),
                                 })
                                 .unwrap_or_else(Value::zero)
                         })
@@ -199,23 +208,13 @@ impl Trace {
     }
 }
 
-fn make_corset(mut constraints: ConstraintSet) -> Result<Corset> {
-    transformer::expand_to(
-        &mut constraints,
-        ExpansionLevel::all().into(),
-        AutoConstraint::all(),
-    )?;
-    transformer::concretize(&mut constraints);
-    Ok(constraints)
+fn make_corset(constraints: ConstraintSet) -> Result<Corset> {
+    Corset::from_constraint_set(constraints)
 }
 
 fn _corset_from_file(zkevmfile: &str) -> Result<Corset> {
     info!("Loading `{}`", &zkevmfile);
-    let constraints = ron::from_str(
-        &std::fs::read_to_string(zkevmfile)
-            .with_context(|| anyhow!("while reading `{}`", zkevmfile))?,
-    )
-    .with_context(|| anyhow!("while parsing `{}`", zkevmfile))?;
+    let constraints = ConstraintSet::from_file(zkevmfile)?;
     make_corset(constraints)
 }
 
@@ -231,9 +230,9 @@ fn _compute_trace_from_file(
     tracefile: &str,
     fail_on_missing: bool,
 ) -> Result<Trace> {
-    compute::compute_trace(tracefile, constraints, fail_on_missing)
+    compute::compute_trace(tracefile, &mut constraints.cs, fail_on_missing)
         .with_context(|| format!("while computing from file `{}`", tracefile))?;
-    Ok(Trace::from_constraints(constraints))
+    Ok(Trace::from_constraints(&constraints.cs))
 }
 
 fn _compute_trace_from_str(
@@ -241,9 +240,9 @@ fn _compute_trace_from_str(
     tracestr: &str,
     fail_on_missing: bool,
 ) -> Result<Trace> {
-    compute::compute_trace_str(tracestr.as_bytes(), constraints, fail_on_missing)
+    compute::compute_trace_str(tracestr.as_bytes(), &mut constraints.cs, fail_on_missing)
         .with_context(|| format!("while computing from string `{}`", tracestr))?;
-    Ok(Trace::from_constraints(constraints))
+    Ok(Trace::from_constraints(&constraints.cs))
 }
 
 #[no_mangle]
@@ -293,6 +292,7 @@ fn _trace_check(corset: &mut ConstraintSet, tracefile: &str, fail_on_missing: bo
             .report(false)
             .full_trace(false),
     )
+    .and_then(|report| report.into_result())
     .with_context(|| format!("while checking `{}`", tracefile))?;
     info!("{}: SUCCESS", tracefile);
 
@@ -323,7 +323,7 @@ pub extern "C" fn trace_check(
     let corset = Corset::mut_from_ptr(corset);
     let tracefile = cstr_to_string(tracefile);
 
-    match _trace_check(corset, tracefile, fail_on_missing) {
+    match _trace_check(&mut corset.cs, tracefile, fail_on_missing) {
         Result::Ok(_) => true,
         Err(e) => {
             eprintln!("{e:?}");
@@ -0,0 +1,1017 @@
+//! A Language Server Protocol implementation for Corset, speaking JSON-RPC
+//! over stdio.
+//!
+//! On every modification of an open buffer, the buffer is re-parsed to
+//! report syntax errors and refresh its definitions; once the edits settle
+//! down, the whole workspace is re-compiled from the cached parse results.
+//! These are then used to provide diagnostics, hovering, go-to-definition and
+//! completion.
+use anyhow::*;
+use itertools::Itertools;
+use log::*;
+use lsp_server::{Connection, ErrorCode, ExtractError, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+        Notification as _, PublishDiagnostics,
+    },
+    request::{Completion, GotoDefinition, HoverRequest, Request as _},
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionResponse, CompletionTextEdit,
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, GotoDefinitionResponse, Hover,
+    HoverContents, HoverProviderCapability, Location, MarkupContent, MarkupKind, OneOf, Position,
+    PublishDiagnosticsParams, Range, ServerCapabilities, TextDocumentContentChangeEvent,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit, Url,
+};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::compiler::{
+    self,
+    generator::FunctionClass,
+    parser::{Ast, AstNode, LinCol, Token},
+    tables::Scope,
+    CompileSettings, Expression, Kind, MAIN_MODULE,
+};
+use crate::errors::diagnostics::{self, ErrorCollector, Locate};
+use crate::field::Field;
+use crate::structs::PERSPECTIVE_SEPARATOR;
+use crate::utils::strip_ansi;
+
+/// The name given to the standard library in the analyzed sources
+const STDLIB: &str = "stdlib";
+/// How long to wait after the last edit before re-compiling the workspace
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum DefKind {
+    Column,
+    Constant,
+    Function,
+    Alias,
+}
+impl DefKind {
+    fn completion_kind(&self) -> CompletionItemKind {
+        match self {
+            DefKind::Column => CompletionItemKind::FIELD,
+            DefKind::Constant => CompletionItemKind::CONSTANT,
+            DefKind::Function => CompletionItemKind::FUNCTION,
+            DefKind::Alias => CompletionItemKind::REFERENCE,
+        }
+    }
+}
+
+/// A symbol definition, as found in the AST of a source file
+pub(crate) struct Definition {
+    pub(crate) module: String,
+    pub(crate) name: String,
+    pub(crate) kind: DefKind,
+    /// the source file containing the definition; `None` for the stdlib
+    pub(crate) uri: Option<Url>,
+    /// where the definition starts in its source file
+    pub(crate) lc: LinCol,
+    /// a short description of the definition, used as a fallback when the
+    /// symbol tables are unavailable
+    pub(crate) detail: String,
+}
+
+/// The state of the language server: the files making up the workspace, and
+/// the results of the last analysis run on them.
+pub(crate) struct Workspace {
+    no_stdlib: bool,
+    debug: bool,
+    /// the field the workspace is compiled for
+    field: Field,
+    /// the content of the source files, either read from the disk or
+    /// synchronized with the editor buffers
+    pub(crate) files: HashMap<Url, String>,
+    /// the files currently opened in the editor
+    open: HashSet<Url>,
+    /// the last successful parse of each source, indexed by its name
+    asts: HashMap<String, Ast>,
+    /// the syntax errors of the files that currently fail to parse
+    syntax: HashMap<Url, Vec<Diagnostic>>,
+    /// the diagnostics found by the last compilation of the workspace
+    semantic: HashMap<Url, Vec<Diagnostic>>,
+    /// the symbols defined in the workspace
+    definitions: Vec<Definition>,
+    /// for each file, the lines at which a module is entered
+    modules: HashMap<Url, Vec<(usize, String)>>,
+    /// the symbol tables of the last workspace that could be parsed
+    scope: Option<Scope>,
+    /// the files for which diagnostics have been published last
+    reported: HashSet<Url>,
+}
+
+impl Workspace {
+    pub(crate) fn new(
+        sources: &[String],
+        no_stdlib: bool,
+        debug: bool,
+        field: Field,
+    ) -> Result<Workspace> {
+        let mut files = HashMap::new();
+        for source in sources.iter() {
+            collect_sources(Path::new(source), &mut files)?;
+        }
+        let mut workspace = Workspace {
+            no_stdlib,
+            debug,
+            field,
+            files,
+            open: Default::default(),
+            asts: Default::default(),
+            syntax: Default::default(),
+            semantic: Default::default(),
+            definitions: Default::default(),
+            modules: Default::default(),
+            scope: None,
+            reported: Default::default(),
+        };
+        if !no_stdlib {
+            workspace.reparse(STDLIB, None, include_str!("stdlib.lisp"));
+        }
+        for uri in workspace.files.keys().cloned().collect::<Vec<_>>() {
+            workspace.refresh(&uri);
+        }
+        Ok(workspace)
+    }
+
+    /// The sources to compile, with their URI as name, and preceded by the
+    /// stdlib if it is enabled.
+    fn sources(&self) -> Vec<(String, String)> {
+        let mut sources = self
+            .files
+            .iter()
+            .map(|(uri, content)| (uri.to_string(), content.to_owned()))
+            .sorted()
+            .collect::<Vec<_>>();
+        if !self.no_stdlib {
+            sources.insert(
+                0,
+                (STDLIB.to_string(), include_str!("stdlib.lisp").to_owned()),
+            );
+        }
+        sources
+    }
+
+    /// Re-parse the source `name`, and replace its definitions with the
+    /// ones it now contains. If it fails to parse, its syntax errors are
+    /// recorded and its previous definitions are kept.
+    fn reparse(&mut self, name: &str, uri: Option<&Url>, content: &str) {
+        match compiler::parser::parser::parse(content).in_file(
+            name,
+            content,
+            format!("parsing `{}`", name),
+        ) {
+            Result::Ok(ast) => {
+                self.definitions.retain(|d| d.uri.as_ref() != uri);
+                self.index(uri, &ast);
+                self.asts.insert(name.to_owned(), ast);
+                if let Some(uri) = uri {
+                    self.syntax.remove(uri);
+                }
+            }
+            Err(e) => {
+                if let Some(uri) = uri {
+                    let mut diagnostics = self.to_diagnostics(&e);
+                    // an error may only be located in the failing file
+                    diagnostics.retain(|(u, _)| u == uri);
+                    self.syntax.insert(
+                        uri.clone(),
+                        diagnostics.into_iter().map(|(_, d)| d).collect(),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Re-parse the file `uri` after it changed, or forget it if it is not
+    /// part of the workspace anymore
+    fn refresh(&mut self, uri: &Url) {
+        // the locations of the previous compilation errors are now stale
+        self.semantic.remove(uri);
+        if let Some(content) = self.files.get(uri).cloned() {
+            self.reparse(uri.as_str(), Some(uri), &content);
+        } else {
+            self.asts.remove(uri.as_str());
+            self.syntax.remove(uri);
+            self.definitions.retain(|d| d.uri.as_ref() != Some(uri));
+            self.modules.remove(uri);
+        }
+    }
+
+    /// Compile the whole workspace from the cached parse results to find
+    /// semantic errors, and refresh the symbol tables. This is skipped as
+    /// long as some files fail to parse.
+    pub(crate) fn analyze(&mut self) {
+        self.semantic.clear();
+        if !self.syntax.is_empty() {
+            return;
+        }
+
+        let (sources, asts): (Vec<_>, Vec<_>) = self
+            .sources()
+            .into_iter()
+            .filter_map(|(name, content)| {
+                let ast = self.asts.get(&name)?.clone();
+                Some(((name.clone(), content), (name, ast)))
+            })
+            .unzip();
+        // Report all the errors, and keep the symbol tables even if some
+        // definitions are faulty
+        let settings = CompileSettings {
+            debug: self.debug,
+            max_errors: 0,
+            field: self.field,
+        };
+        let mut errors = ErrorCollector::new(0);
+        let r =
+            compiler::parser::define(&sources, &asts, &settings, &mut errors).and_then(|scope| {
+                self.scope = Some(scope.clone());
+                compiler::generate(&sources, scope, asts, &settings, &mut errors)
+            });
+        if let Err(e) = r {
+            for (uri, d) in self.to_diagnostics(&e) {
+                self.semantic.entry(uri).or_default().push(d);
+            }
+        }
+    }
+
+    /// The diagnostics to publish for each file, including empty ones for
+    /// the files whose previous diagnostics must be cleared
+    pub(crate) fn diagnostics(&mut self) -> HashMap<Url, Vec<Diagnostic>> {
+        let mut diagnostics: HashMap<Url, Vec<Diagnostic>> = self
+            .files
+            .keys()
+            .chain(self.reported.iter())
+            .map(|uri| (uri.clone(), Vec::new()))
+            .collect();
+        for (uri, ds) in self.syntax.iter().chain(self.semantic.iter()) {
+            diagnostics
+                .entry(uri.clone())
+                .or_default()
+                .extend(ds.iter().cloned());
+        }
+
+        self.reported = diagnostics
+            .iter()
+            .filter(|(_, ds)| !ds.is_empty())
+            .map(|(uri, _)| uri.clone())
+            .collect();
+        diagnostics
+    }
+
+    /// Register all the symbols defined at the top level of `ast`
+    pub(crate) fn index(&mut self, uri: Option<&Url>, ast: &Ast) {
+        let mut module = MAIN_MODULE.to_owned();
+        let mut modules = Vec::new();
+        for e in ast.exprs.iter() {
+            let mut define = |name: &str, kind: DefKind, lc: LinCol, detail: String| {
+                self.definitions.push(Definition {
+                    module: module.clone(),
+                    name: name.to_owned(),
+                    kind,
+                    uri: uri.cloned(),
+                    lc,
+                    detail,
+                })
+            };
+            match &e.class {
+                Token::DefColumns(columns) => {
+                    for c in columns.iter() {
+                        index_column(c, &mut define);
+                    }
+                }
+                Token::DefPerspective { name, columns, .. } => {
+                    for c in columns.iter() {
+                        index_column(c, &mut |n, kind, lc, detail| {
+                            define(n, kind, lc, format!("{} (in perspective {})", detail, name))
+                        });
+                    }
+                }
+                Token::DefConsts(consts) => {
+                    for (name, value) in consts.iter() {
+                        define(
+                            name,
+                            DefKind::Constant,
+                            e.lc,
+                            format!("{} := {}", name, value),
+                        );
+                    }
+                }
+                Token::Defun {
+                    name,
+                    args,
+                    in_types,
+                    out_type,
+                    ..
+                }
+                | Token::Defpurefun {
+                    name,
+                    args,
+                    in_types,
+                    out_type,
+                    ..
+                } => define(
+                    name,
+                    DefKind::Function,
+                    e.lc,
+                    format!(
+                        "({} {}){}",
+                        name,
+                        args.iter()
+                            .zip(in_types.iter())
+                            .map(|(a, t)| format!("{}:{}", a, t))
+                            .join(" "),
+                        out_type.map(|t| format!(" → {}", t)).unwrap_or_default()
+                    ),
+                ),
+                Token::DefAliases(aliases) => {
+                    for a in aliases.iter() {
+                        if let Token::DefAlias(from, to) = &a.class {
+                            define(from, DefKind::Alias, a.lc, format!("{} ≡ {}", from, to));
+                        }
+                    }
+                }
+                Token::DefunAlias(from, to) => {
+                    define(from, DefKind::Alias, e.lc, format!("{} ≡ {}", from, to))
+                }
+                Token::DefInterleaving { target, .. } => define(
+                    &target.name,
+                    DefKind::Column,
+                    e.lc,
+                    format!("{} (interleaved)", target.name),
+                ),
+                Token::DefPermutation { to, .. } => {
+                    for c in to.iter() {
+                        define(
+                            &c.name,
+                            DefKind::Column,
+                            e.lc,
+                            format!("{} (permuted)", c.name),
+                        );
+                    }
+                }
+                Token::DefModule(name) => {
+                    module = name.to_owned();
+                    modules.push((e.lc.0, name.to_owned()));
+                }
+                _ => {}
+            }
+        }
+        if let Some(uri) = uri {
+            self.modules.insert(uri.clone(), modules);
+        }
+    }
+
+    /// Convert a compilation error into diagnostics, attached to the files
+    /// they have been located in.
+    fn to_diagnostics(&self, e: &Error) -> Vec<(Url, Diagnostic)> {
+        let mut r = Vec::new();
+        for d in diagnostics::Diagnostic::from_error(e) {
+            let located = d.span.as_ref().and_then(|span| {
+                let uri = Url::parse(&span.file).ok()?;
+                let range = self.span_range(span)?;
+                Some((uri, range))
+            });
+            let Some((uri, range)) = located else {
+                warn!("{}", d);
+                continue;
+            };
+            let related = d
+                .labels
+                .iter()
+                .filter_map(|l| {
+                    Some(DiagnosticRelatedInformation {
+                        location: Location::new(
+                            Url::parse(&l.span.file).ok()?,
+                            self.span_range(&l.span)?,
+                        ),
+                        message: strip_ansi(&l.message),
+                    })
+                })
+                .collect::<Vec<_>>();
+            r.push((
+                uri,
+                Diagnostic {
+                    range,
+                    severity: Some(match d.severity {
+                        diagnostics::Severity::Error => DiagnosticSeverity::ERROR,
+                        diagnostics::Severity::Warning => DiagnosticSeverity::WARNING,
+                    }),
+                    source: Some("corset".into()),
+                    message: std::iter::once(strip_ansi(&d.message))
+                        .chain(d.notes.iter().map(|n| strip_ansi(n)))
+                        .join("\n"),
+                    related_information: if related.is_empty() {
+                        None
+                    } else {
+                        Some(related)
+                    },
+                    ..Default::default()
+                },
+            ));
+        }
+        r
+    }
+
+    /// The LSP range covered by a compiler span
+    fn span_range(&self, span: &diagnostics::Span) -> Option<Range> {
+        let content = self.files.get(&Url::parse(&span.file).ok()?)?;
+        Some(Range::new(
+            to_position(content, (span.line, span.column)),
+            to_position(content, (span.line, span.column + span.len.max(1))),
+        ))
+    }
+
+    /// The module in which the given line of the given file lives
+    pub(crate) fn module_at(&self, uri: &Url, line: usize) -> String {
+        self.modules
+            .get(uri)
+            .and_then(|ms| ms.iter().rev().find(|(l, _)| *l <= line + 1))
+            .map(|(_, m)| m.to_owned())
+            .unwrap_or_else(|| MAIN_MODULE.to_owned())
+    }
+
+    /// Split a symbol as found in the source into its module and its name,
+    /// defaulting to `module` if it is not fully qualified.
+    pub(crate) fn qualify(&self, module: &str, symbol: &str) -> (String, String) {
+        let symbol = symbol
+            .rsplit(PERSPECTIVE_SEPARATOR)
+            .next()
+            .unwrap_or(symbol);
+        if let Some((m, name)) = symbol.split_once('.') {
+            (m.to_owned(), name.to_owned())
+        } else {
+            (module.to_owned(), symbol.to_owned())
+        }
+    }
+
+    /// Find the definition of `name` as seen from `module`, falling back on
+    /// the prelude.
+    pub(crate) fn find_definition(&self, module: &str, name: &str) -> Option<&Definition> {
+        [module, MAIN_MODULE].iter().find_map(|m| {
+            self.definitions
+                .iter()
+                .find(|d| d.module == *m && d.name == name)
+        })
+    }
+
+    /// The symbol table of the given module, if it exists
+    fn module_scope(&self, module: &str) -> Option<Scope> {
+        let root = self.scope.as_ref()?;
+        if module == MAIN_MODULE {
+            Some(root.clone())
+        } else {
+            root.modules().into_iter().find(|s| s.name() == module)
+        }
+    }
+
+    fn definition(&self, uri: &Url, pos: Position) -> Option<GotoDefinitionResponse> {
+        let content = self.files.get(uri)?;
+        let (symbol, _) = word_at(content, pos)?;
+        let (module, name) = self.qualify(&self.module_at(uri, pos.line as usize), &symbol);
+        let def = self.find_definition(&module, &name)?;
+        let target = def.uri.as_ref()?;
+        let position = to_position(self.files.get(target)?, def.lc);
+        Some(GotoDefinitionResponse::Scalar(Location::new(
+            target.clone(),
+            Range::new(position, position),
+        )))
+    }
+
+    fn hover(&self, uri: &Url, pos: Position) -> Option<Hover> {
+        let content = self.files.get(uri)?;
+        let (symbol, range) = word_at(content, pos)?;
+        let (module, name) = self.qualify(&self.module_at(uri, pos.line as usize), &symbol);
+
+        let from_scope = [module.as_str(), MAIN_MODULE].iter().find_map(|m| {
+            let mut scope = self.module_scope(m)?;
+            if let Result::Ok(node) = scope.resolve_symbol(&name) {
+                Some(match node.e() {
+                    Expression::Column {
+                        handle,
+                        kind,
+                        must_prove,
+                        ..
+                    } => format!(
+                        "```\n{}\n```\n{} column — type `{}`, magma `{}`{}",
+                        handle,
+                        match kind {
+                            Kind::Commitment => "commitment",
+                            Kind::Computed => "computed",
+                            Kind::Expression(_) => "defined",
+                        },
+                        node.t(),
+                        node.t().m(),
+                        if *must_prove { " (proven)" } else { "" }
+                    ),
+                    Expression::ExoColumn { handle, .. } => format!(
+                        "```\n{}\n```\nexo-column — type `{}`, magma `{}`",
+                        handle,
+                        node.t(),
+                        node.t().m()
+                    ),
+                    Expression::ArrayColumn { handle, domain, .. } => format!(
+                        "```\n{}\n```\narray column over {} — type `{}`, magma `{}`",
+                        handle,
+                        domain,
+                        node.t(),
+                        node.t().m()
+                    ),
+                    Expression::Const(x) => format!(
+                        "```\n{} := {}\n```\nconstant — type `{}`",
+                        name,
+                        x,
+                        node.t()
+                    ),
+                    _ => format!("```\n{}\n```\ntype `{}`", name, node.t()),
+                })
+            } else if let Result::Ok(f) = scope.resolve_function(&name) {
+                Some(match f.class {
+                    FunctionClass::UserDefined(d) => format!(
+                        "```\n{}\n```\n{}",
+                        f.handle,
+                        d.specializations
+                            .iter()
+                            .map(|s| format!("- `{}`", s))
+                            .join("\n")
+                    ),
+                    FunctionClass::Alias(to) => format!("```\n{}\n```\nalias of `{}`", name, to),
+                    _ => format!("```\n{}\n```\nbuiltin", name),
+                })
+            } else {
+                None
+            }
+        });
+        let text = from_scope.or_else(|| {
+            self.find_definition(&module, &name)
+                .map(|d| format!("```\n{}\n```", d.detail))
+        })?;
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: text,
+            }),
+            range: Some(range),
+        })
+    }
+
+    fn completion(&self, uri: &Url, pos: Position) -> Option<CompletionResponse> {
+        let content = self.files.get(uri)?;
+        let (prefix, range) = prefix_at(content, pos);
+        let current = self.module_at(uri, pos.line as usize);
+
+        // Build the candidates as (label, kind) pairs
+        let mut candidates: Vec<(String, CompletionItemKind)> = Vec::new();
+        let push_module = |module: &str, qualify: bool, candidates: &mut Vec<_>| {
+            let prefix = if qualify {
+                format!("{}.", module)
+            } else {
+                String::new()
+            };
+            let kind_of = |name: &str, default| {
+                self.definitions
+                    .iter()
+                    .find(|d| d.module == module && d.name == name)
+                    .map(|d| d.kind.completion_kind())
+                    .unwrap_or(default)
+            };
+            if let Some(scope) = self.module_scope(module) {
+                for s in scope.symbol_names() {
+                    let kind = kind_of(&s, CompletionItemKind::VARIABLE);
+                    candidates.push((format!("{}{}", prefix, s), kind));
+                }
+                for f in scope.function_names() {
+                    candidates.push((format!("{}{}", prefix, f), CompletionItemKind::FUNCTION));
+                }
+            }
+            for d in self.definitions.iter().filter(|d| d.module == module) {
+                candidates.push((format!("{}{}", prefix, d.name), d.kind.completion_kind()));
+            }
+        };
+
+        if let Some((module, _)) = prefix.split_once('.') {
+            push_module(module, true, &mut candidates);
+        } else {
+            push_module(&current, false, &mut candidates);
+            if current != MAIN_MODULE {
+                push_module(MAIN_MODULE, false, &mut candidates);
+            }
+            let modules = self
+                .scope
+                .iter()
+                .flat_map(|s| s.modules())
+                .map(|s| s.name())
+                .chain(self.definitions.iter().map(|d| d.module.clone()))
+                .filter(|m| m != MAIN_MODULE)
+                .collect::<HashSet<_>>();
+            for m in modules {
+                candidates.push((m, CompletionItemKind::MODULE));
+            }
+        }
+
+        let mut seen = HashSet::new();
+        Some(CompletionResponse::Array(
+            candidates
+                .into_iter()
+                .filter(|(label, _)| !label.starts_with('#') && label.starts_with(&prefix))
+                .filter(|(label, _)| seen.insert(label.clone()))
+                .map(|(label, kind)| CompletionItem {
+                    text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(
+                        range,
+                        label.clone(),
+                    ))),
+                    label,
+                    kind: Some(kind),
+                    ..Default::default()
+                })
+                .collect(),
+        ))
+    }
+
+    pub(crate) fn open(&mut self, uri: Url, text: String) {
+        self.open.insert(uri.clone());
+        self.files.insert(uri.clone(), text);
+        self.refresh(&uri);
+    }
+
+    pub(crate) fn change(&mut self, uri: &Url, changes: Vec<TextDocumentContentChangeEvent>) {
+        if let Some(content) = self.files.get_mut(uri) {
+            for change in changes {
+                if let Some(range) = change.range {
+                    let start = to_offset(content, range.start);
+                    let end = to_offset(content, range.end);
+                    if start > end {
+                        warn!("ignoring change of {} over reversed range {:?}", uri, range);
+                        continue;
+                    }
+                    content.replace_range(start..end, &change.text);
+                } else {
+                    *content = change.text;
+                }
+            }
+            self.refresh(uri);
+        }
+    }
+
+    fn close(&mut self, uri: &Url) {
+        self.open.remove(uri);
+        // Files part of the workspace on disk are reloaded, the others
+        // are forgotten
+        if let Result::Ok(path) = uri.to_file_path() {
+            if path.is_file() {
+                match std::fs::read_to_string(&path) {
+                    Result::Ok(content) => {
+                        self.files.insert(uri.clone(), content);
+                        self.refresh(uri);
+                        return;
+                    }
+                    Err(e) => warn!("unable to reload {}: {}", path.display(), e),
+                }
+            }
+        }
+        self.files.remove(uri);
+        self.refresh(uri);
+    }
+}
+
+/// Register the column(s) declared by a `defcolumns` entry
+fn index_column(c: &AstNode, define: &mut dyn FnMut(&str, DefKind, LinCol, String)) {
+    match &c.class {
+        Token::DefColumn { name, t, kind, .. } => define(
+            name,
+            DefKind::Column,
+            c.lc,
+            format!(
+                "{} :{}{}",
+                name,
+                t,
+                if matches!(kind, Kind::Expression(_)) {
+                    " (computed)"
+                } else {
+                    ""
+                }
+            ),
+        ),
+        Token::DefArrayColumn {
+            name, t, domain, ..
+        } => define(
+            name,
+            DefKind::Column,
+            c.lc,
+            format!("{} :{} {}", name, t, domain),
+        ),
+        _ => {}
+    }
+}
+
+/// Insert all the Corset files found under `path` in `files`
+fn collect_sources(path: &Path, files: &mut HashMap<Url, String>) -> Result<()> {
+    if path.is_dir() {
+        for entry in path
+            .read_dir()
+            .with_context(|| anyhow!("while reading {}", path.display()))?
+        {
+            collect_sources(&entry?.path(), files)?;
+        }
+    } else if path.is_file()
+        && path
+            .extension()
+            .map(|ext| ext == "lisp" || ext == "corset")
+            .unwrap_or(false)
+    {
+        let path = path.canonicalize()?;
+        let uri =
+            Url::from_file_path(&path).map_err(|_| anyhow!("invalid path {}", path.display()))?;
+        files.insert(
+            uri,
+            std::fs::read_to_string(&path)
+                .with_context(|| anyhow!("reading {}", path.display()))?,
+        );
+    }
+    Ok(())
+}
+
+/// Convert a 1-based line/column position, as used by the parser, into an
+/// LSP position.
+fn to_position(content: &str, lc: LinCol) -> Position {
+    let line = content
+        .lines()
+        .nth(lc.0.saturating_sub(1))
+        .unwrap_or_default();
+    let character = line
+        .chars()
+        .take(lc.1.saturating_sub(1))
+        .map(char::len_utf16)
+        .sum::<usize>();
+    Position::new(lc.0.saturating_sub(1) as u32, character as u32)
+}
+
+/// Convert an LSP position into a byte offset in `content`
+pub(crate) fn to_offset(content: &str, pos: Position) -> usize {
+    let mut offset = 0;
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        if i == pos.line as usize {
+            let mut utf16 = 0;
+            for (j, c) in line.char_indices() {
+                if utf16 >= pos.character as usize || c == '\n' {
+                    return offset + j;
+                }
+                utf16 += c.len_utf16();
+            }
+            return offset + line.len();
+        }
+        offset += line.len();
+    }
+    content.len()
+}
+
+/// Convert a byte offset in `content` into an LSP position
+fn to_lsp_position(content: &str, offset: usize) -> Position {
+    let before = &content[..offset];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    Position::new(
+        line as u32,
+        before[line_start..]
+            .chars()
+            .map(char::len_utf16)
+            .sum::<usize>() as u32,
+    )
+}
+
+/// Whether `c` may be part of a symbol, following the Corset grammar
+fn is_symbol_char(c: char) -> bool {
+    !c.is_whitespace() && !"()[]{};:\"".contains(c)
+}
+
+/// The symbol under the cursor, and its range
+pub(crate) fn word_at(content: &str, pos: Position) -> Option<(String, Range)> {
+    let offset = to_offset(content, pos);
+    let start = content[..offset]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_symbol_char(*c))
+        .last()
+        .map(|(i, _)| i)
+        .unwrap_or(offset);
+    let end = content[offset..]
+        .char_indices()
+        .find(|(_, c)| !is_symbol_char(*c))
+        .map(|(i, _)| offset + i)
+        .unwrap_or(content.len());
+    if start == end {
+        None
+    } else {
+        Some((
+            content[start..end].to_owned(),
+            Range::new(
+                to_lsp_position(content, start),
+                to_lsp_position(content, end),
+            ),
+        ))
+    }
+}
+
+/// The partial symbol preceding the cursor, and its range
+pub(crate) fn prefix_at(content: &str, pos: Position) -> (String, Range) {
+    let offset = to_offset(content, pos);
+    let start = content[..offset]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_symbol_char(*c))
+        .last()
+        .map(|(i, _)| i)
+        .unwrap_or(offset);
+    (
+        content[start..offset].to_owned(),
+        Range::new(to_lsp_position(content, start), pos),
+    )
+}
+
+/// Answer `req` with the result of `f` on its parameters, or with an error if
+/// they can not be decoded, so that a malformed request does not bring the
+/// server down
+fn handle_request<R, T>(
+    connection: &Connection,
+    req: Request,
+    f: impl FnOnce(R::Params) -> T,
+) -> Result<()>
+where
+    R: lsp_types::request::Request,
+    R::Params: serde::de::DeserializeOwned,
+    T: serde::Serialize,
+{
+    let id = req.id.clone();
+    let response = match req.extract::<R::Params>(R::METHOD) {
+        Result::Ok((id, params)) => Response::new_ok(id, f(params)),
+        Err(ExtractError::JsonError { method, error }) => {
+            warn!("invalid {} request: {}", method, error);
+            Response::new_err(
+                id,
+                ErrorCode::InvalidParams as i32,
+                format!("invalid parameters: {}", error),
+            )
+        }
+        Err(ExtractError::MethodMismatch(req)) => Response::new_err(
+            id,
+            ErrorCode::MethodNotFound as i32,
+            format!("unexpected method {}", req.method),
+        ),
+    };
+    connection.sender.send(Message::Response(response))?;
+    Ok(())
+}
+
+/// The parameters of `n`, if they can be decoded; notifications can not be
+/// answered, so that malformed ones are only logged
+fn cast_notification<N>(n: Notification) -> Option<N::Params>
+where
+    N: lsp_types::notification::Notification,
+    N::Params: serde::de::DeserializeOwned,
+{
+    match n.extract(N::METHOD) {
+        Result::Ok(params) => Some(params),
+        Err(ExtractError::JsonError { method, error }) => {
+            warn!("invalid {} notification: {}", method, error);
+            None
+        }
+        Err(ExtractError::MethodMismatch(n)) => {
+            warn!("unexpected notification {}", n.method);
+            None
+        }
+    }
+}
+
+fn publish(connection: &Connection, diagnostics: HashMap<Url, Vec<Diagnostic>>) -> Result<()> {
+    for (uri, diagnostics) in diagnostics.into_iter() {
+        connection
+            .sender
+            .send(Message::Notification(Notification::new(
+                PublishDiagnostics::METHOD.to_owned(),
+                PublishDiagnosticsParams::new(uri, diagnostics, None),
+            )))?;
+    }
+    Ok(())
+}
+
+fn main_loop(connection: &Connection, mut workspace: Workspace) -> Result<()> {
+    workspace.analyze();
+    publish(connection, workspace.diagnostics())?;
+
+    // when the last edit not followed by a compilation of the workspace
+    // happened
+    let mut pending: Option<Instant> = None;
+    loop {
+        let msg = if let Some(edited) = pending {
+            match connection
+                .receiver
+                .recv_timeout(DEBOUNCE.saturating_sub(edited.elapsed()))
+            {
+                Result::Ok(msg) => msg,
+                Err(e) if e.is_timeout() => {
+                    pending = None;
+                    workspace.analyze();
+                    publish(connection, workspace.diagnostics())?;
+                    continue;
+                }
+                Err(_) => break,
+            }
+        } else {
+            match connection.receiver.recv() {
+                Result::Ok(msg) => msg,
+                Err(_) => break,
+            }
+        };
+
+        match msg {
+            Message::Request(req) => {
+                if connection.handle_shutdown(&req)? {
+                    return Ok(());
+                }
+                match req.method.as_str() {
+                    GotoDefinition::METHOD => {
+                        handle_request::<GotoDefinition, _>(connection, req, |params| {
+                            let p = params.text_document_position_params;
+                            workspace.definition(&p.text_document.uri, p.position)
+                        })?
+                    }
+                    HoverRequest::METHOD => {
+                        handle_request::<HoverRequest, _>(connection, req, |params| {
+                            let p = params.text_document_position_params;
+                            workspace.hover(&p.text_document.uri, p.position)
+                        })?
+                    }
+                    Completion::METHOD => {
+                        handle_request::<Completion, _>(connection, req, |params| {
+                            let p = params.text_document_position;
+                            workspace.completion(&p.text_document.uri, p.position)
+                        })?
+                    }
+                    _ => debug!("ignoring request {}", req.method),
+                }
+            }
+            Message::Notification(n) => {
+                let edited = match n.method.as_str() {
+                    DidOpenTextDocument::METHOD => cast_notification::<DidOpenTextDocument>(n)
+                        .map(|params| {
+                            workspace.open(params.text_document.uri, params.text_document.text)
+                        })
+                        .is_some(),
+                    DidChangeTextDocument::METHOD => cast_notification::<DidChangeTextDocument>(n)
+                        .map(|params| {
+                            workspace.change(&params.text_document.uri, params.content_changes)
+                        })
+                        .is_some(),
+                    DidCloseTextDocument::METHOD => cast_notification::<DidCloseTextDocument>(n)
+                        .map(|params| workspace.close(&params.text_document.uri))
+                        .is_some(),
+                    DidSaveTextDocument::METHOD => false,
+                    _ => {
+                        debug!("ignoring notification {}", n.method);
+                        false
+                    }
+                };
+                if edited {
+                    // syntax errors are reported right away, the compilation
+                    // waits for the edits to settle down
+                    publish(connection, workspace.diagnostics())?;
+                    pending = Some(Instant::now());
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Run a language server over stdio, analyzing the Corset files found in
+/// `sources` as well as the ones opened by the client.
+pub(crate) fn run(sources: &[String], no_stdlib: bool, debug: bool, field: Field) -> Result<()> {
+    let workspace = Workspace::new(sources, no_stdlib, debug, field)?;
+
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = serde_json::to_value(ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(
+            TextDocumentSyncKind::INCREMENTAL,
+        )),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec![".".into()]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })?;
+    connection.initialize(capabilities)?;
+    info!("Corset language server started");
+
+    main_loop(&connection, workspace)?;
+    // The I/O threads only stop once the connection is closed
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
@@ -2,9 +2,11 @@
 #[macro_use]
 extern crate pest_derive;
 use anyhow::*;
+use cache::CompilationCache;
 use compiler::parser::Ast;
 use compiler::ConstraintSet;
 use either::Either;
+use field::Field;
 use log::*;
 use logging_timer::time;
 use owo_colors::OwoColorize;
@@ -12,31 +14,40 @@ use serde::Serialize;
 use serde_json::Value;
 use std::sync::RwLock;
 use std::{
-    io::{Read, Write},
+    io::{IsTerminal, Read, Write},
     path::Path,
 };
 use transformer::{AutoConstraint, ExpansionLevel};
 
 use clap::{Parser, Subcommand};
 
+mod audit;
+mod cache;
 mod check;
 mod column;
 mod compiler;
 mod compute;
 mod constants;
 mod dag;
+mod diff;
 mod errors;
 #[cfg(test)]
 mod evaluation_tests;
+mod export;
 mod exporters;
+mod field;
 mod formatter;
+mod fuzz;
 mod import;
 #[cfg(feature = "inspector")]
 mod inspect;
+#[cfg(feature = "lsp")]
+mod lsp;
 mod pretty;
 mod structs;
 #[cfg(test)]
 mod tests;
+mod trace_diff;
 mod transformer;
 mod utils;
 
@@ -60,6 +71,34 @@ pub struct Args {
     #[arg(long="auto-constraints", value_parser=["sorts", "nhood"], value_delimiter=',', global=true)]
     auto_constraints: Vec<String>,
 
+    #[arg(
+        long = "max-degree",
+        help = "introduce intermediate columns until all constraints are at most of this degree",
+        global = true
+    )]
+    max_degree: Option<usize>,
+
+    #[arg(
+        long = "cse",
+        help = "compute sub-expressions shared by several constraints only once",
+        global = true
+    )]
+    cse: bool,
+
+    #[arg(
+        long = "simplify",
+        help = "algebraically simplify the constraints once expanded",
+        global = true
+    )]
+    simplify: bool,
+
+    #[arg(
+        long = "prune",
+        help = "remove the columns and computations that no constraint depends on",
+        global = true
+    )]
+    prune: bool,
+
     #[arg(long = "debug", help = "Compile code in debug mode", global = true)]
     debug: bool,
 
@@ -72,6 +111,13 @@ pub struct Args {
     )]
     threads: usize,
 
+    #[arg(
+        long = "memory-budget",
+        help = "beyond this many MB, drop the computed columns not needed anymore; ignored by the commands outputting the computed trace",
+        global = true
+    )]
+    memory_budget: Option<usize>,
+
     #[arg(
         long = "native",
         short = 'N',
@@ -83,6 +129,46 @@ pub struct Args {
     #[arg(long = "no-stdlib")]
     no_stdlib: bool,
 
+    #[arg(
+        long = "field",
+        help = "the field to compile the constraints for; ignored for compiled constraint sets",
+        value_parser = Field::NAMES,
+        default_value = "bls12-377",
+        global = true
+    )]
+    field: String,
+
+    #[arg(
+        long = "max-errors",
+        help = "stop compiling after this many errors; 0 to report all of them",
+        default_value_t = 1,
+        global = true
+    )]
+    max_errors: usize,
+
+    #[arg(
+        long = "cache",
+        help = "reuse previously compiled constraint sets when the sources did not change",
+        global = true
+    )]
+    cache: bool,
+
+    #[arg(
+        long = "cache-dir",
+        help = "where to store compiled constraint sets; implies --cache",
+        global = true
+    )]
+    cache_dir: Option<String>,
+
+    #[arg(
+        long = "error-format",
+        help = "how to render compilation errors",
+        value_parser = ["human", "json"],
+        default_value = "human",
+        global = true
+    )]
+    error_format: String,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -160,11 +246,37 @@ enum Commands {
         )]
         exclude: Option<Vec<String>>,
 
-        #[arg(short = 'o', long = "out", help = "where to write the computed trace")]
+        #[arg(long = "modules", help = "only export the modules matching this regex")]
+        modules: Option<String>,
+
+        #[arg(
+            long = "columns",
+            help = "only export the columns whose `module.name` match this regex"
+        )]
+        columns: Option<String>,
+
+        #[arg(
+            long = "rows",
+            help = "only export these rows, as START:END, START: or :END"
+        )]
+        rows: Option<String>,
+
+        #[arg(
+            long = "no-compute",
+            help = "convert the trace as is, without computing the missing columns"
+        )]
+        no_compute: bool,
+
+        #[arg(short = 'o', long = "out", help = "where to write the converted trace")]
         outfile: Option<String>,
 
-        #[arg(short='F', long="format", help="output format", value_parser=["csv", "json", "lt"], default_value="sqlite")]
-        format: String,
+        #[arg(
+            short = 'F',
+            long = "format",
+            help = "output format; guessed from the output file extension if not set",
+            value_parser = exporters::convert::Format::NAMES
+        )]
+        format: Option<String>,
     },
     /// Given a set of constraints and a trace file, fill the computed columns
     Compute {
@@ -179,13 +291,85 @@ enum Commands {
         #[arg(
             short = 'o',
             long = "out",
-            help = "where to write the computed trace",
+            help = "where to write the computed trace; binary if ending in .lt, .lt.gz or .lt.zst, JSON otherwise",
             required = true
         )]
         outfile: Option<String>,
 
         #[arg(long, help = "exit on failing columns")]
         fail_on_missing: bool,
+
+        #[arg(
+            long = "modules",
+            help = "only compute the columns of these modules, along with their dependencies",
+            value_delimiter = ','
+        )]
+        modules: Vec<String>,
+
+        #[arg(
+            long = "columns",
+            help = "only compute these columns, along with their dependencies",
+            value_delimiter = ','
+        )]
+        columns: Vec<String>,
+    },
+    /// Given a set of constraints, compute two traces and compare them column by column
+    TraceDiff {
+        #[arg(
+            short = 'T',
+            long = "traces",
+            required = true,
+            num_args = 2,
+            value_names = ["OLD", "NEW"],
+            help = "the two traces to compare"
+        )]
+        tracefiles: Vec<String>,
+
+        #[arg(
+            long = "ignore-padding",
+            help = "skip the padding rows, and align the columns on their last row"
+        )]
+        ignore_padding: bool,
+
+        #[arg(
+            short = 'r',
+            long = "rows",
+            default_value_t = 10,
+            help = "how many differing rows to display per column"
+        )]
+        rows: usize,
+    },
+    /// Given a set of constraints, look for random traces satisfying them, and for the columns
+    /// that may vary in these traces
+    Fuzz {
+        #[arg(
+            short = 'r',
+            long = "rows",
+            default_value_t = 8,
+            help = "the number of rows of the generated modules"
+        )]
+        rows: usize,
+
+        #[arg(
+            short = 'i',
+            long = "iterations",
+            default_value_t = 1000,
+            help = "how many traces to check before giving up"
+        )]
+        iterations: usize,
+
+        #[arg(long = "seed", help = "the seed of the random generator")]
+        seed: Option<u64>,
+
+        #[arg(
+            long = "attempts",
+            default_value_t = 32,
+            help = "how many times to alter a column of the passing trace before considering it constrained"
+        )]
+        attempts: usize,
+
+        #[arg(short = 'o', long = "out", help = "where to write the passing trace")]
+        outfile: Option<String>,
     },
     /// Given a set of constraints and a filled trace, check the validity of the constraints
     Check {
@@ -214,12 +398,89 @@ enum Commands {
         #[arg(long = "skip", help = "skip these constraints", value_delimiter = ',')]
         skip: Vec<String>,
 
+        #[arg(
+            long = "modules",
+            help = "only compute the columns of these modules, along with their dependencies",
+            value_delimiter = ','
+        )]
+        modules: Vec<String>,
+
+        #[arg(
+            long = "columns",
+            help = "only compute these columns, along with their dependencies",
+            value_delimiter = ','
+        )]
+        columns: Vec<String>,
+
         #[arg(
             long = "no-abort",
-            help = "continue checking a constraint after it met an error"
+            help = "report on every failing row of a constraint rather than only the first one"
         )]
         continue_on_error: bool,
 
+        #[arg(
+            long = "json-report",
+            help = "write a JSON report of the failing constraints to this file"
+        )]
+        json_report: Option<String>,
+
+        #[arg(
+            long = "witnesses",
+            help = "how many failing rows of each constraint to detail in the JSON report",
+            default_value_t = 1
+        )]
+        witnesses: usize,
+
+        #[arg(
+            long = "rows",
+            help = "only check the vanishing constraints on this START..END window of rows",
+            conflicts_with_all = ["sample", "boundary"]
+        )]
+        rows: Option<String>,
+
+        #[arg(
+            long = "sample",
+            help = "only check the vanishing constraints on this many random rows",
+            conflicts_with = "boundary"
+        )]
+        sample: Option<usize>,
+
+        #[arg(
+            long = "seed",
+            help = "the seed used to sample the checked rows",
+            default_value_t = 0,
+            requires = "sample"
+        )]
+        seed: u64,
+
+        #[arg(
+            long = "boundary",
+            help = "only check the vanishing constraints on the first and last rows of their module"
+        )]
+        boundary: bool,
+
+        #[arg(
+            long = "profile",
+            help = "report the time spent, the rows evaluated and the failures per module and constraint"
+        )]
+        profile: bool,
+
+        #[arg(
+            long = "profile-sort",
+            help = "how to sort the profiling report",
+            value_parser = check::ProfileOrder::NAMES,
+            default_value = "time",
+            requires = "profile"
+        )]
+        profile_sort: String,
+
+        #[arg(
+            long = "profile-json",
+            help = "write the profiling report as JSON to this file",
+            requires = "profile"
+        )]
+        profile_json: Option<String>,
+
         #[arg(short = 'r', long = "report", help = "detail the failing constraint")]
         report: bool,
 
@@ -282,17 +543,23 @@ enum Commands {
         #[arg(
             short = 'm',
             long = "modules",
-            help = "show modules and their properties"
+            help = "show modules and their properties; if given as --modules=A,B, only show the columns of these modules, along with their dependencies",
+            num_args = 0..=1,
+            require_equals = true,
+            value_delimiter = ','
         )]
-        show_modules: bool,
+        modules: Option<Vec<String>>,
         #[arg(short = 'n', long = "constants", help = "show constants")]
         show_constants: bool,
         #[arg(
             short = 'C',
             long = "columns",
-            help = "show columns and their properties"
+            help = "show columns and their properties; if given as --columns=A,B, only show these columns, along with their dependencies",
+            num_args = 0..=1,
+            require_equals = true,
+            value_delimiter = ','
         )]
-        show_columns: bool,
+        columns: Option<Vec<String>>,
         #[arg(
             short = 'c',
             long = "constraints",
@@ -333,6 +600,7 @@ enum Commands {
             requires = "show_constraints"
         )]
         skip: Vec<String>,
+
         #[arg(
             long = "toml",
             help = "generate information (where applicable) in TOML format",
@@ -377,6 +645,19 @@ enum Commands {
         #[arg(long = "skip", help = "skip these constraints", value_delimiter = ',')]
         skip: Vec<String>,
     },
+    /// Run a language server over stdio for the given Corset sources
+    #[cfg(feature = "lsp")]
+    Lsp,
+    /// Compare two constraint sets, either compiled or as Corset sources
+    Diff {
+        #[arg(long, help = "output the differences as JSON")]
+        json: bool,
+    },
+    /// Report the commitment columns that may be insufficiently constrained
+    Audit {
+        #[arg(long, help = "output the warnings as JSON")]
+        json: bool,
+    },
     /// Given a set of Corset files, compile them into a single file for faster later use
     Compile {
         #[arg(
@@ -392,44 +673,66 @@ enum Commands {
 
         #[arg(
             long,
-            help = "generate output as JSON instead of in the Rusty Object Notation (RON)"
+            help = "generate output as JSON instead of in the compact binary format"
         )]
         json: bool,
+
+        #[arg(
+            long,
+            help = "generate output in the Rusty Object Notation (RON) instead of in the compact binary format"
+        )]
+        ron: bool,
     },
 }
 
 type SourceMapping = Vec<(String, String)>;
 struct ConstraintSetBuilder {
     debug: bool,
+    max_errors: usize,
+    field: Field,
     no_stdlib: bool,
     source: Either<SourceMapping, ConstraintSet>,
     expand_to: ExpansionLevel,
     auto_constraints: Vec<AutoConstraint>,
+    max_degree: Option<usize>,
+    cse: bool,
+    simplify: bool,
+    prune: bool,
+    cache: Option<CompilationCache>,
 }
 impl ConstraintSetBuilder {
     fn from_sources(no_stdlib: bool, debug: bool) -> ConstraintSetBuilder {
         ConstraintSetBuilder {
             debug,
+            max_errors: 1,
+            field: Field::default(),
             no_stdlib,
             source: Either::Left(Vec::new()),
             expand_to: Default::default(),
             auto_constraints: Default::default(),
+            max_degree: None,
+            cse: false,
+            simplify: false,
+            prune: false,
+            cache: None,
         }
     }
 
     fn from_bin(filename: &str) -> Result<ConstraintSetBuilder> {
+        let cs = ConstraintSet::from_file(filename)?;
         Ok(ConstraintSetBuilder {
             debug: false,
+            max_errors: 1,
+            field: cs.field,
             no_stdlib: false,
-            source: Either::Right(
-                ron::from_str(
-                    &std::fs::read_to_string(filename)
-                        .with_context(|| anyhow!("while reading `{}`", filename))?,
-                )
-                .with_context(|| anyhow!("while parsing `{}`", filename))?,
-            ),
+            source: Either::Right(cs),
             expand_to: Default::default(),
             auto_constraints: Default::default(),
+            max_degree: None,
+            cse: false,
+            simplify: false,
+            prune: false,
+            cache: None,
         })
     }
 
@@ -441,6 +744,34 @@ impl ConstraintSetBuilder {
         self.auto_constraints = auto.to_vec();
     }
 
+    fn max_degree(&mut self, max_degree: Option<usize>) {
+        self.max_degree = max_degree;
+    }
+
+    fn cse(&mut self, cse: bool) {
+        self.cse = cse;
+    }
+
+    fn simplify(&mut self, simplify: bool) {
+        self.simplify = simplify;
+    }
+
+    fn prune(&mut self, prune: bool) {
+        self.prune = prune;
+    }
+
+    fn max_errors(&mut self, max_errors: usize) {
+        self.max_errors = max_errors;
+    }
+
+    fn field(&mut self, field: Field) {
+        self.field = field;
+    }
+
+    fn cache(&mut self, cache: CompilationCache) {
+        self.cache = Some(cache);
+    }
+
     fn find_section(root: &Path, section: &str) -> Result<Option<SourceMapping>> {
         let section_file = root.join(format!("{}.lisp", section));
         let section_str = section_file.to_str().unwrap();
@@ -586,27 +917,157 @@ impl ConstraintSetBuilder {
 
     #[time("info", "Compiling into constraint set")]
     fn into_constraint_set(self) -> Result<ConstraintSet> {
+        let cached = match (self.source.as_ref(), self.cache.as_ref()) {
+            (Either::Left(sources), Some(cache)) => Some((
+                cache,
+                CompilationCache::key(
+                    &self.prepare_sources(sources),
+                    self.debug,
+                    self.field,
+                    self.expand_to,
+                    &self.auto_constraints,
+                    self.max_degree,
+                    self.cse,
+                    self.simplify,
+                    self.prune,
+                ),
+            )),
+            _ => None,
+        };
+        if let Some(cs) = cached.as_ref().and_then(|(cache, key)| cache.load(key)) {
+            return Ok(cs);
+        }
+
         let mut cs = match self.source {
             Either::Left(ref sources) => compiler::make(
                 &self.prepare_sources(sources),
-                &compiler::CompileSettings { debug: self.debug },
+                &compiler::CompileSettings {
+                    debug: self.debug,
+                    max_errors: self.max_errors,
+                    field: self.field,
+                },
             )
             .map(|r| r.1),
             Either::Right(cs) => Ok(cs),
         }?;
 
-        transformer::expand_to(&mut cs, self.expand_to, &self.auto_constraints)?;
+        let pruned = transformer::expand_to(
+            &mut cs,
+            self.expand_to,
+            &self.auto_constraints,
+            self.max_degree,
+            self.cse,
+            self.simplify,
+            self.prune,
+        )?;
+        if let Some(report) = pruned.filter(|r| !r.is_empty()) {
+            eprint!("{}", report);
+        }
         transformer::concretize(&mut cs);
+        if let Some((cache, key)) = cached {
+            if let Err(e) = cache.store(&key, &cs) {
+                warn!("unable to cache the constraint set: {:?}", e);
+            }
+        }
         Ok(cs)
     }
 }
 
 #[cfg(feature = "cli")]
 fn main() -> Result<()> {
-    use crate::{inspect::InspectorSettings, transformer::concretize};
+    use errors::diagnostics::Diagnostic;
 
     let args = Args::parse();
+    let error_format = args.error_format.clone();
+    if let Err(err) = run(args) {
+        let diagnostics = Diagnostic::from_error(&err);
+        if error_format == "json" {
+            for d in diagnostics.iter() {
+                eprintln!("{}", d.to_json());
+            }
+        } else if diagnostics.iter().any(|d| d.span.is_some()) {
+            for d in diagnostics.iter() {
+                eprintln!("{}", d);
+            }
+            if diagnostics.len() > 1 {
+                eprintln!(
+                    "{}: aborting due to {} errors",
+                    "error".red().bold(),
+                    diagnostics.len()
+                );
+            }
+        } else {
+            return Err(err);
+        }
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Whether `source` is a compiled constraint set rather than Corset sources
+#[cfg(feature = "cli")]
+fn is_compiled(source: &str) -> bool {
+    Path::new(source)
+        .extension()
+        .map(|e| e == "bin")
+        .unwrap_or(false)
+}
+
+/// Commands outputting the whole computed trace can not drop any of its
+/// columns; warn that the memory budget is ignored if one was set
+#[cfg(feature = "cli")]
+fn ignore_memory_budget(memory_budget: Option<usize>, command: &str) {
+    if memory_budget.is_some() {
+        warn!(
+            "--memory-budget is ignored by `{}`, which needs all the computed columns",
+            command
+        );
+    }
+}
+
+/// Prepare the compilation of the constraint set defined by `sources`, either
+/// a compiled constraint set or Corset files and directories, following the
+/// global settings of `args`
+#[cfg(feature = "cli")]
+fn make_builder(args: &Args, sources: &[String]) -> Result<ConstraintSetBuilder> {
+    let mut builder = if sources.len() == 1 && is_compiled(&sources[0]) {
+        info!("Loading `{}`", &sources[0]);
+        ConstraintSetBuilder::from_bin(&sources[0])?
+    } else {
+        info!("Parsing Corset source files...");
+        let mut r = ConstraintSetBuilder::from_sources(args.no_stdlib, args.debug);
+        r.field(args.field.parse()?);
+        for f in sources.iter() {
+            r.add_source(f)?;
+        }
+        r
+    };
+
+    builder.expand_to(args.expand.into());
+    builder.auto_constraints(&AutoConstraint::parse(&args.auto_constraints));
+    builder.max_degree(args.max_degree);
+    builder.cse(args.cse);
+    builder.simplify(args.simplify);
+    builder.prune(args.prune);
+    builder.max_errors(args.max_errors);
+    if let Some(dir) = args.cache_dir.as_ref() {
+        builder.cache(CompilationCache::new(dir));
+    } else if args.cache {
+        match CompilationCache::in_user_dir() {
+            Some(cache) => builder.cache(cache),
+            None => warn!("no cache directory found, use --cache-dir to set one"),
+        }
+    }
+    Ok(builder)
+}
+
+#[cfg(feature = "cli")]
+fn run(args: Args) -> Result<()> {
+    use crate::{inspect::InspectorSettings, transformer::concretize};
+
     *crate::IS_NATIVE.write().unwrap() = args.native_arithmetic;
+    // how many bytes of computed columns may be held in memory at once
+    let memory_budget = args.memory_budget.map(|mb| mb << 20);
     buche::new()
         .verbosity(args.verbose.log_level_filter())
         .quiet(args.verbose.is_silent())
@@ -618,46 +1079,46 @@ fn main() -> Result<()> {
         .build_global()
         .unwrap();
 
-    let mut builder = if matches!(args.command, Commands::Format { .. }) {
+    #[cfg(feature = "lsp")]
+    if matches!(args.command, Commands::Lsp) {
+        return lsp::run(
+            &args.source,
+            args.no_stdlib,
+            args.debug,
+            args.field.parse()?,
+        );
+    }
+
+    if matches!(args.command, Commands::Format { .. }) {
         if args.source.len() != 1 {
             bail!(
                 "can only format one file at a time; found {}",
                 args.source.len()
             )
-        } else if args.source.len() == 1
-            && Path::new(&args.source[0])
-                .extension()
-                .map(|e| e == "bin")
-                .unwrap_or(false)
-        {
+        } else if is_compiled(&args.source[0]) {
             bail!("expected Corset source file, found compiled constraint set")
-        } else {
-            let mut r = ConstraintSetBuilder::from_sources(args.no_stdlib, args.debug);
-            for f in args.source.iter() {
-                r.add_source(f)?;
-            }
-            r
-        }
-    } else if args.source.len() == 1
-        && Path::new(&args.source[0])
-            .extension()
-            .map(|e| e == "bin")
-            .unwrap_or(false)
-    {
-        info!("Loading `{}`", &args.source[0]);
-        ConstraintSetBuilder::from_bin(&args.source[0])?
-    } else {
-        info!("Parsing Corset source files...");
-        let mut r = ConstraintSetBuilder::from_sources(args.no_stdlib, args.debug);
-        for f in args.source.iter() {
-            r.add_source(f)?;
         }
-        r
-    };
+    }
 
-    builder.expand_to(args.expand.into());
-    builder.auto_constraints(&AutoConstraint::parse(&args.auto_constraints));
+    if let Commands::Diff { json } = args.command {
+        if args.source.len() != 2 {
+            bail!(
+                "expected two constraint sets to compare; found {}",
+                args.source.len()
+            )
+        }
+        let old = make_builder(&args, &args.source[..1])?.into_constraint_set()?;
+        let new = make_builder(&args, &args.source[1..])?.into_constraint_set()?;
+        let diff = diff::diff(&old, &new);
+        if json {
+            println!("{}", serde_json::to_string_pretty(&diff)?);
+        } else {
+            print!("{}", diff);
+        }
+        return Ok(());
+    }
 
+    let mut builder = make_builder(&args, &args.source)?;
     match args.command {
         #[cfg(feature = "exporters")]
         Commands::Go { package, filename } => {
@@ -711,49 +1172,95 @@ fn main() -> Result<()> {
             outfile,
             format,
             exclude,
+            modules,
+            columns,
+            rows,
+            no_compute,
         } => {
+            use exporters::convert::Format;
+
+            let format = match (format, outfile.as_ref()) {
+                (Some(format), _) => Format::from_name(&format)?,
+                (None, Some(outfile)) => Format::from_filename(outfile).ok_or_else(|| {
+                    anyhow!("unable to guess the format of `{}`; use --format", outfile)
+                })?,
+                (None, None) => bail!("either --out or --format must be set"),
+            };
+            let outfile = outfile.unwrap_or_else(|| {
+                match format {
+                    Format::Csv => "trace.csv",
+                    Format::Json { gzip: false } => "trace.json",
+                    Format::Json { gzip: true } => "trace.json.gz",
+                    Format::Lt(export::Compression::None) => "trace.lt",
+                    Format::Lt(export::Compression::Gzip) => "trace.lt.gz",
+                    Format::Lt(export::Compression::Zstd) => "trace.lt.zst",
+                    Format::Columnar => "trace",
+                }
+                .to_string()
+            });
+            let selection = export::TraceSelection {
+                modules: modules
+                    .map(|r| regex_lite::Regex::new(&r))
+                    .transpose()
+                    .with_context(|| "invalid --modules regex")?,
+                columns: columns
+                    .map(|r| regex_lite::Regex::new(&r))
+                    .transpose()
+                    .with_context(|| "invalid --columns regex")?,
+                exclude: exclude.unwrap_or_default(),
+                rows: rows.map(|r| export::parse_rows(&r)).transpose()?,
+            };
+
             let mut cs = builder.into_constraint_set()?;
-            compute::compute_trace(&tracefile, &mut cs, false)
-                .with_context(|| format!("while expanding `{}`", tracefile))?;
+            if no_compute {
+                compute::import_trace(&tracefile, &mut cs)
+            } else {
+                ignore_memory_budget(memory_budget, "convert");
+                compute::compute_trace(&tracefile, &mut cs, false)
+            }
+            .with_context(|| format!("while expanding `{}`", tracefile))?;
 
-            match format.as_str() {
-                "csv" => exporters::convert::to_csv(
-                    &cs,
-                    &exclude.unwrap_or_default(),
-                    outfile.as_ref().map(String::as_str).unwrap_or("trace.csv"),
-                ),
-                "json" => exporters::convert::to_json(
-                    &cs,
-                    &exclude.unwrap_or_default(),
-                    outfile.as_ref().map(String::as_str).unwrap_or("trace.json"),
-                ),
-                // "lt" => exporters::convert::to_lt(
-                //     &cs,
-                //     &exclude.unwrap_or_default(),
-                //     outfile.as_ref().map(String::as_str).unwrap_or("trace.csv"),
-                // ),
-                _ => unreachable!(),
-            }?;
+            exporters::convert::convert(&cs, &selection, format, &outfile)
+                .with_context(|| format!("while writing to `{}`", outfile))?;
         }
         Commands::Compute {
             tracefile,
             outfile,
             fail_on_missing,
+            modules,
+            columns,
         } => {
             builder.expand_to(ExpansionLevel::top());
             builder.auto_constraints(AutoConstraint::all());
             let mut cs = builder.into_constraint_set()?;
 
-            compute::compute_trace(&tracefile, &mut cs, fail_on_missing)
-                .with_context(|| format!("while computing from `{}`", tracefile))?;
+            let scope = compute::dependency_closure(&cs, &modules, &columns, None)?;
+            ignore_memory_budget(memory_budget, "compute");
+            compute::compute_trace_within(
+                &tracefile,
+                &mut cs,
+                fail_on_missing,
+                scope.as_ref(),
+                None,
+            )
+            .with_context(|| format!("while computing from `{}`", tracefile))?;
 
             let outfile = outfile.as_ref().unwrap();
             let mut f = std::fs::File::create(outfile)
                 .with_context(|| format!("while creating `{}`", &outfile))?;
 
             let mut out = std::io::BufWriter::with_capacity(10_000_000, &mut f);
-            cs.write(&mut out)
-                .with_context(|| format!("while writing to `{}`", &outfile))?;
+            if import::is_binary_trace(outfile) {
+                export::write_binary_trace(
+                    &cs,
+                    &export::TraceSelection::default(),
+                    &mut out,
+                    export::Compression::from_filename(outfile),
+                )
+            } else {
+                cs.write(&mut out)
+            }
+            .with_context(|| format!("while writing to `{}`", &outfile))?;
             out.flush()?;
         }
         #[cfg(feature = "postgres")]
@@ -836,13 +1343,80 @@ fn main() -> Result<()> {
                 std::thread::sleep(std::time::Duration::from_secs(1));
             }
         }
+        Commands::TraceDiff {
+            tracefiles,
+            ignore_padding,
+            rows,
+        } => {
+            builder.expand_to(ExpansionLevel::top());
+            builder.auto_constraints(AutoConstraint::all());
+            let mut cs = builder.into_constraint_set()?;
+
+            if !trace_diff::trace_diff(
+                &mut cs,
+                &tracefiles[0],
+                &tracefiles[1],
+                &trace_diff::TraceDiffSettings {
+                    ignore_padding,
+                    rows,
+                },
+            )? {
+                println!("no differences");
+            }
+        }
+        Commands::Fuzz {
+            rows,
+            iterations,
+            seed,
+            attempts,
+            outfile,
+        } => {
+            let mut cs = builder.into_constraint_set()?;
+            let seed = seed.unwrap_or_else(|| {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default()
+            });
+            info!("fuzzing with seed {}", seed);
+
+            let report = fuzz::fuzz(
+                &mut cs,
+                &fuzz::FuzzSettings {
+                    rows,
+                    iterations,
+                    seed,
+                    attempts,
+                },
+            )?;
+            print!("{}", report);
+            if let (Some(outfile), Some(trace)) = (outfile, report.passing_trace()) {
+                std::fs::File::create(&outfile)
+                    .with_context(|| format!("while creating `{}`", outfile))
+                    .and_then(|f| {
+                        serde_json::to_writer(f, &trace)
+                            .with_context(|| format!("while writing `{}`", outfile))
+                    })?;
+            }
+        }
         Commands::Check {
             tracefile,
             full_trace,
             report,
             only,
             skip,
+            modules,
+            columns,
             continue_on_error,
+            json_report,
+            witnesses,
+            rows,
+            sample,
+            seed,
+            boundary,
+            profile,
+            profile_sort,
+            profile_json,
             unclutter,
             dim,
             with_src,
@@ -857,9 +1431,40 @@ fn main() -> Result<()> {
 
             let mut cs = builder.into_constraint_set()?;
 
-            compute::compute_trace(&tracefile, &mut cs, false)
-                .with_context(|| format!("while expanding `{}`", tracefile))?;
-            check::check(
+            let scope = compute::dependency_closure(&cs, &modules, &columns, only.as_ref())?;
+            // Unless explicitly required, only check the constraints that can
+            // be evaluated over the computed columns
+            let only = match (scope.as_ref(), only) {
+                (Some(scope), None) => Some(
+                    cs.constraints
+                        .iter()
+                        .filter(|c| {
+                            c.dependencies()
+                                .iter()
+                                .all(|r| scope.contains(&cs.columns.id_of(r)))
+                        })
+                        .map(|c| c.name())
+                        .collect(),
+                ),
+                (_, only) => only,
+            };
+
+            let mode = match (rows, sample, boundary) {
+                (Some(rows), ..) => check::CheckMode::window(&rows)?,
+                (_, Some(rows), _) => check::CheckMode::Sample { rows, seed },
+                (.., true) => check::CheckMode::Boundary,
+                _ => check::CheckMode::Full,
+            };
+
+            compute::compute_trace_within(
+                &tracefile,
+                &mut cs,
+                false,
+                scope.as_ref(),
+                memory_budget,
+            )
+            .with_context(|| format!("while expanding `{}`", tracefile))?;
+            let mut check_report = check::check(
                 &cs,
                 &only,
                 &skip,
@@ -872,10 +1477,41 @@ fn main() -> Result<()> {
                     .full_trace(full_trace)
                     .context_span(trace_span)
                     .and_context_span_before(trace_span_before)
-                    .and_context_span_after(trace_span_after),
+                    .and_context_span_after(trace_span_after)
+                    .witnesses(witnesses)
+                    .mode(mode)
+                    .progress(!report && std::io::stderr().is_terminal()),
             )
             .with_context(|| format!("while checking {}", tracefile.bright_white().bold()))?;
-            info!("{}: SUCCESS", tracefile)
+            if profile {
+                let mut profile = std::mem::take(&mut check_report.profile);
+                profile.sort(check::ProfileOrder::from_name(&profile_sort)?);
+                print!("{}", profile);
+                if let Some(profile_json) = profile_json {
+                    std::fs::File::create(&profile_json)
+                        .with_context(|| format!("while creating `{}`", profile_json))
+                        .and_then(|f| {
+                            serde_json::to_writer_pretty(f, &profile)
+                                .with_context(|| format!("while writing `{}`", profile_json))
+                        })?;
+                }
+            }
+            if let Some(json_report) = json_report {
+                std::fs::File::create(&json_report)
+                    .with_context(|| format!("while creating `{}`", json_report))
+                    .and_then(|f| {
+                        serde_json::to_writer_pretty(f, &check_report)
+                            .with_context(|| format!("while writing `{}`", json_report))
+                    })?;
+            }
+            check_report
+                .into_result()
+                .with_context(|| format!("while checking {}", tracefile.bright_white().bold()))?;
+            if mode == check::CheckMode::Full {
+                info!("{}: SUCCESS", tracefile)
+            } else {
+                info!("{}: SUCCESS on {}", tracefile, mode)
+            }
         }
         #[cfg(feature = "inspector")]
         Commands::Inspect {
@@ -889,6 +1525,7 @@ fn main() -> Result<()> {
             }
             let mut cs = builder.into_constraint_set()?;
 
+            ignore_memory_budget(memory_budget, "inspect");
             compute::compute_trace(&tracefile, &mut cs, false)
                 .with_context(|| format!("while expanding `{}`", tracefile))?;
 
@@ -903,9 +1540,9 @@ fn main() -> Result<()> {
             info!("{}: SUCCESS", tracefile)
         }
         Commands::Debug {
-            show_modules,
+            modules,
             show_constants,
-            show_columns,
+            columns,
             show_constraints,
             show_computations,
             show_perspectives,
@@ -916,14 +1553,20 @@ fn main() -> Result<()> {
             toml,
         } => {
             let cs = builder.into_constraint_set()?;
+            let scope = compute::dependency_closure(
+                &cs,
+                modules.as_deref().unwrap_or_default(),
+                columns.as_deref().unwrap_or_default(),
+                only.as_ref(),
+            )?;
 
             exporters::debugger::debug(
                 &cs,
                 exporters::debugger::DebugSettings {
-                    modules: show_modules,
+                    modules: modules.is_some(),
                     constants: show_constants,
                     constraints: show_constraints,
-                    columns: show_columns,
+                    columns: columns.is_some(),
                     types: show_types,
                     perspectives: show_perspectives,
                     computations: show_computations,
@@ -932,6 +1575,7 @@ fn main() -> Result<()> {
                 },
                 only.as_ref(),
                 &skip,
+                scope.as_ref(),
             )?;
         }
         Commands::Format { inplace } => {
@@ -946,30 +1590,46 @@ fn main() -> Result<()> {
                 }
             }
         }
+        #[cfg(feature = "lsp")]
+        Commands::Lsp => unreachable!(),
+        Commands::Diff { .. } => unreachable!(),
+        Commands::Audit { json } => {
+            let cs = builder.into_constraint_set()?;
+            let report = audit::audit(&cs);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else if report.is_empty() {
+                println!("no soundness warnings");
+            } else {
+                print!("{}", report);
+            }
+        }
         Commands::Compile {
             outfile,
             pretty,
             json,
+            ron,
         } => {
             let constraints = builder.into_constraint_set()?;
             std::fs::File::create(&outfile)
                 .with_context(|| format!("while creating `{}`", &outfile))?
-                .write_all(
-                    if json && cfg!(feature = "json-bin") {
-                        if pretty {
-                            serde_json::to_string_pretty(&constraints)?
-                        } else {
-                            serde_json::to_string(&constraints)?
-                        }
-                    } else if json {
-                        panic!("Exporting as JSON requires the `json-bin` feature.");
-                    } else if pretty {
-                        ron::ser::to_string_pretty(&constraints, ron::ser::PrettyConfig::default())?
+                .write_all(&if json && cfg!(feature = "json-bin") {
+                    if pretty {
+                        serde_json::to_string_pretty(&constraints)?
                     } else {
-                        ron::ser::to_string(&constraints)?
+                        serde_json::to_string(&constraints)?
                     }
-                    .as_bytes(),
-                )
+                    .into_bytes()
+                } else if json {
+                    panic!("Exporting as JSON requires the `json-bin` feature.");
+                } else if pretty {
+                    ron::ser::to_string_pretty(&constraints, ron::ser::PrettyConfig::default())?
+                        .into_bytes()
+                } else if ron {
+                    ron::ser::to_string(&constraints)?.into_bytes()
+                } else {
+                    constraints.to_bin()?
+                })
                 .with_context(|| format!("while writing to `{}`", &outfile))?;
         }
     }
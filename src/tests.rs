@@ -1,4 +1,4 @@
-use crate::{transformer::ExpansionLevel, ConstraintSetBuilder};
+use crate::{field::Field, transformer::ExpansionLevel, ConstraintSetBuilder};
 use anyhow::*;
 
 fn make(name: &str, source: &str) -> Result<()> {
@@ -246,3 +246,996 @@ fn complex_for() {
 //     //     "(module foobar) (defcolumns A B (C :bool) (D :i32)) (defconstraint pipo () (if (eq! A D) C D))",
 //     // );
 // }
+
+#[test]
+fn collect_errors() {
+    use crate::errors::diagnostics::Diagnostic;
+
+    let source = "(defcolumns A (B :comp (+ A Q))) (defconstraint c1 () (vanishes! (+ A Z))) (defconstraint c2 () (vanishes! B))";
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(source).unwrap();
+    r.max_errors(0);
+    let err = r.into_constraint_set().unwrap_err();
+    // Q & Z are reported, but not the use of the poisoned B
+    assert_eq!(Diagnostic::from_error(&err).len(), 2);
+}
+
+#[test]
+fn compilation_cache() -> Result<()> {
+    use crate::cache::CompilationCache;
+
+    let sources = vec![("a.lisp".to_string(), "(defcolumns A B)".to_string())];
+    let key = CompilationCache::key(
+        &sources,
+        false,
+        Field::Bls12_377,
+        ExpansionLevel::top(),
+        &[],
+        None,
+        false,
+        false,
+        false,
+    );
+    let changed = vec![("a.lisp".to_string(), "(defcolumns A C)".to_string())];
+    assert_ne!(
+        key,
+        CompilationCache::key(
+            &changed,
+            false,
+            Field::Bls12_377,
+            ExpansionLevel::top(),
+            &[],
+            None,
+            false,
+            false,
+            false
+        )
+    );
+    assert_ne!(
+        key,
+        CompilationCache::key(
+            &sources,
+            false,
+            Field::Bls12_377,
+            ExpansionLevel::None,
+            &[],
+            None,
+            false,
+            false,
+            false
+        )
+    );
+    assert_ne!(
+        key,
+        CompilationCache::key(
+            &sources,
+            false,
+            Field::Goldilocks,
+            ExpansionLevel::top(),
+            &[],
+            None,
+            false,
+            false,
+            false
+        )
+    );
+
+    let dir = std::env::temp_dir().join(format!("corset-cache-{}", std::process::id()));
+    let cache = CompilationCache::new(&dir);
+    let mut r = ConstraintSetBuilder::from_sources(true, false);
+    r.add_source(&sources[0].1)?;
+    cache.store(&key, &r.into_constraint_set()?)?;
+    let cs = cache.load(&key);
+    std::fs::remove_dir_all(&dir)?;
+    assert!(cs.is_some_and(|cs| cs.columns.all().len() == 2));
+    Ok(())
+}
+
+#[test]
+fn binary_constraint_set() -> Result<()> {
+    use crate::compiler::ConstraintSet;
+
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source("(defcolumns A B) (defconstraint c () (vanishes! (- A B)))")?;
+    r.expand_to(ExpansionLevel::top());
+    let cs = r.into_constraint_set()?;
+
+    let mut bin = cs.to_bin()?;
+    let decoded = ConstraintSet::from_bin(&bin)?;
+    assert_eq!(decoded.columns.all().len(), cs.columns.all().len());
+    assert_eq!(decoded.constraints.len(), cs.constraints.len());
+
+    // Sets in other formats must be rejected
+    bin[8] = bin[8].wrapping_add(1);
+    assert!(ConstraintSet::from_bin(&bin).is_err());
+    assert!(ConstraintSet::from_bin(b"(columns: ...)").is_err());
+    Ok(())
+}
+
+#[test]
+fn binary_trace_roundtrip() -> Result<()> {
+    use crate::{
+        compiler::{ColumnRef, ConstraintSet},
+        compute,
+        export::{self, Compression},
+        transformer::AutoConstraint,
+    };
+
+    let source = "(defcolumns A (B :byte)) (defconstraint c () (vanishes! (- A B 3)))";
+    let make = || -> Result<ConstraintSet> {
+        let mut r = ConstraintSetBuilder::from_sources(false, false);
+        r.add_source(source)?;
+        r.expand_to(ExpansionLevel::top());
+        r.auto_constraints(AutoConstraint::all());
+        r.into_constraint_set()
+    };
+
+    let dir = std::env::temp_dir().join(format!("corset-trace-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let json = dir.join("trace.json");
+    std::fs::write(
+        &json,
+        r#"{"<prelude>": {"A": [3, 4, 258], "B": [0, 1, 255]}}"#,
+    )?;
+    let mut cs = make()?;
+    compute::compute_trace(json.to_str().unwrap(), &mut cs, true)?;
+
+    for (ext, compression) in [
+        ("lt", Compression::None),
+        ("lt.gz", Compression::Gzip),
+        ("lt.zst", Compression::Zstd),
+    ] {
+        let path = dir.join(format!("trace.{}", ext));
+        let path = path.to_str().unwrap();
+        assert_eq!(Compression::from_filename(path), compression);
+        export::write_binary_trace(
+            &cs,
+            &export::TraceSelection::default(),
+            std::fs::File::create(path)?,
+            compression,
+        )?;
+
+        let mut reread = make()?;
+        compute::compute_trace(path, &mut reread, true)?;
+        // Column IDs are not stable across compilations
+        for (c, column) in cs.columns.iter() {
+            let h: ColumnRef = column.handle.clone().into();
+            let len = cs.columns.len(&c).unwrap() as isize;
+            assert_eq!(reread.columns.len(&h), Some(len as usize));
+            for i in 0..len {
+                assert_eq!(
+                    cs.columns.get(&c, i, false),
+                    reread.columns.get(&h, i, false)
+                );
+            }
+        }
+    }
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn convert_trace() -> Result<()> {
+    use crate::{
+        compiler::ColumnRef,
+        compute,
+        export::{self, TraceSelection},
+        exporters::convert::{self, Format},
+        structs::Handle,
+    };
+
+    assert_eq!(export::parse_rows("2:")?, 2..usize::MAX);
+    assert_eq!(export::parse_rows(":3")?, 0..3);
+    assert!(export::parse_rows("3").is_err());
+    assert_eq!(
+        Format::from_filename("trace.lt.zst"),
+        Some(Format::Lt(export::Compression::Zstd))
+    );
+    assert_eq!(
+        Format::from_filename("trace.json.gz"),
+        Some(Format::Json { gzip: true })
+    );
+
+    let make = || {
+        let mut r = ConstraintSetBuilder::from_sources(false, false);
+        r.add_source("(defcolumns A B)")?;
+        r.into_constraint_set()
+    };
+    let dir = std::env::temp_dir().join(format!("corset-convert-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let json = dir.join("trace.json");
+    std::fs::write(&json, r#"{"<prelude>": {"A": [1, 2, 3], "B": [4, 5, 6]}}"#)?;
+    let mut cs = make()?;
+    compute::import_trace(json.to_str().unwrap(), &mut cs)?;
+
+    let selection = TraceSelection {
+        columns: Some(regex_lite::Regex::new(r"\.A$")?),
+        rows: Some(1..usize::MAX),
+        ..Default::default()
+    };
+    for ext in ["json.gz", "lt"] {
+        let out = dir.join(format!("out.{}", ext));
+        let out = out.to_str().unwrap();
+        convert::convert(&cs, &selection, Format::from_filename(out).unwrap(), out)?;
+
+        let mut reread = make()?;
+        compute::import_trace(out, &mut reread)?;
+        let a: ColumnRef = Handle::new("<prelude>", "A").into();
+        let b: ColumnRef = Handle::new("<prelude>", "B").into();
+        assert_eq!(
+            (0..2)
+                .map(|i| reread.columns.get(&a, i + 1, false).unwrap().to_string())
+                .collect::<Vec<_>>(),
+            ["2", "3"]
+        );
+        assert!(!reread.columns.is_computed(&b));
+    }
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn degree_reduction() -> Result<()> {
+    use crate::compiler::Constraint;
+
+    let source = "(defcolumns A B C D E)
+(defconstraint quintic () (vanishes! (- E (^ A 5))))
+(defconstraint cond () (if-zero A (vanishes! (* B C D)) (vanishes! (- B 1))))";
+    let degrees = |max_degree| -> Result<Vec<usize>> {
+        let mut r = ConstraintSetBuilder::from_sources(false, false);
+        r.add_source(source)?;
+        r.expand_to(ExpansionLevel::top());
+        r.max_degree(max_degree);
+        Ok(r.into_constraint_set()?
+            .constraints
+            .iter()
+            .filter_map(|c| match c {
+                Constraint::Vanishes { expr, .. } => Some(expr.degree()),
+                _ => None,
+            })
+            .collect())
+    };
+
+    assert_eq!(degrees(None)?.into_iter().max(), Some(5));
+    for max_degree in 2..=4 {
+        assert!(degrees(Some(max_degree))?
+            .into_iter()
+            .all(|d| d <= max_degree));
+    }
+
+    // before expansion, a condition weighs as much as its normalization
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source("(defcolumns A B)\n(defconstraint c () (if-zero A (vanishes! B)))")?;
+    let cs = r.into_constraint_set()?;
+    let c = cs.constraints.iter().find(|c| c.handle().name == "c");
+    if let Some(Constraint::Vanishes { expr, .. }) = c {
+        assert_eq!(expr.degree(), 3);
+    } else {
+        panic!("constraint c not found")
+    }
+    Ok(())
+}
+
+#[test]
+fn common_subexpressions() -> Result<()> {
+    let source = "(defcolumns A B C)
+(defconstraint one () (vanishes! (* (- 1 (~ A)) (+ B C))))
+(defconstraint two () (vanishes! (* (- 1 (~ A)) (+ B C) B)))
+(deflookup l1 (A) ((+ B C)))";
+    let columns = |cse| -> Result<Vec<String>> {
+        let mut r = ConstraintSetBuilder::from_sources(false, false);
+        r.add_source(source)?;
+        r.expand_to(ExpansionLevel::top());
+        r.cse(cse);
+        Ok(r.into_constraint_set()?
+            .columns
+            .iter()
+            .map(|(_, c)| c.handle.name.clone())
+            .filter(|name| name.contains("#EXPAND"))
+            .collect())
+    };
+
+    assert_eq!(columns(false)?.len(), 1);
+    let shared = columns(true)?;
+    assert_eq!(shared.len(), 2);
+    assert!(shared.iter().any(|c| c.contains("(+ B C)")));
+    Ok(())
+}
+
+#[test]
+fn simplification() -> Result<()> {
+    use crate::compiler::Constraint;
+
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(
+        "(defcolumns A B C)
+(defconstraint cancel () (vanishes! (- (+ A B) (+ A C))))
+(defconstraint distribute () (vanishes! (* 3 (+ A (* 2 (- B 0)) (* 0 C)))))
+(defconstraint ones () (vanishes! (* 1 (* A (* 1 B)))))
+(defconstraint constant () (if-zero (- A A) (vanishes! (- B 1)) (vanishes! C)))",
+    )?;
+    r.expand_to(ExpansionLevel::top());
+    r.simplify(true);
+    let cs = r.into_constraint_set()?;
+    for c in cs.constraints.iter() {
+        if let Constraint::Vanishes { handle, expr, .. } = c {
+            let expected = match handle.name.as_str() {
+                "cancel" => "(- B C)",
+                "distribute" => "(+ (* 3 A) (* 6 B))",
+                "ones" => "(* A B)",
+                "constant" => "{(- B 1)}",
+                _ => continue,
+            };
+            assert_eq!(expr.to_string(), expected, "{}", handle.name);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn pruning() -> Result<()> {
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(
+        "(defcolumns A B C UNUSED)
+(definterleaved IL (A B))
+(defconstraint c1 () (vanishes! (- A B C)))
+(module m)
+(defcolumns X Y Z)
+(definterleaved IL2 (X Y))
+(deflookup lk (A) (IL2))",
+    )?;
+    r.expand_to(ExpansionLevel::top());
+    r.prune(true);
+    let cs = r.into_constraint_set()?;
+
+    let mut columns = cs
+        .columns
+        .iter()
+        .map(|(_, c)| c.handle.to_string())
+        .collect::<Vec<_>>();
+    columns.sort();
+    assert_eq!(columns, ["A", "B", "C", "m.IL2", "m.X", "m.Y"]);
+    for (i, c) in cs.columns.iter() {
+        assert_eq!(cs.columns.id_of(&c.handle.clone().into()), i.as_id());
+        assert!(c.register.unwrap() < cs.columns.registers.len());
+    }
+    assert_eq!(cs.computations.iter().count(), 1);
+    Ok(())
+}
+
+#[test]
+fn constraint_set_diff() -> Result<()> {
+    let compile = |source: &str| {
+        let mut r = ConstraintSetBuilder::from_sources(false, false);
+        r.add_source(source)?;
+        r.expand_to(ExpansionLevel::top());
+        r.into_constraint_set()
+    };
+    let old = compile(
+        "(defcolumns A (B :byte) OLD GONE)
+(defconstraint c1 () (vanishes! (- A B)))
+(defconstraint c2 () (vanishes! (* OLD A)))
+(defconstraint c3 () (vanishes! GONE))",
+    )?;
+    let new = compile(
+        "(defcolumns A (B :i16) NEW)
+(defconstraint c1 () (vanishes! (- A B 1)))
+(defconstraint c2 () (vanishes! (* A NEW)))",
+    )?;
+
+    assert!(crate::diff::diff(&old, &old).is_empty());
+    let d = crate::diff::diff(&old, &new);
+    assert_eq!(d.columns.removed, ["GONE"]);
+    assert!(d.columns.added.is_empty());
+    assert_eq!(
+        d.columns.renamed,
+        [crate::diff::Change {
+            old: "OLD".to_string(),
+            new: "NEW".to_string()
+        }]
+    );
+    assert_eq!(d.columns.changed.len(), 1);
+    assert_eq!(d.constraints.removed, ["c3"]);
+    // c2 only differs by the renaming and the order of its factors
+    assert_eq!(d.constraints.changed.len(), 1);
+    assert_eq!(d.constraints.changed[0].name, "c1");
+    Ok(())
+}
+
+#[test]
+fn trace_diff() -> Result<()> {
+    use crate::{
+        compute::compute_trace_str,
+        trace_diff::{diff_columns, TraceDiffSettings},
+    };
+
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source("(defcolumns A B)\n(defconstraint c () (vanishes! (- A B)))")?;
+    r.expand_to(ExpansionLevel::top());
+    let mut cs = r.into_constraint_set()?;
+
+    let old = br#"{"<prelude>": {"A": [1, 2], "B": [1, 2]}}"#.as_slice();
+    let new = br#"{"<prelude>": {"A": [1, 3], "B": [1, 2]}}"#.as_slice();
+    let padded = br#"{"<prelude>": {"A": [0, 0, 1, 2], "B": [0, 0, 1, 2]}}"#.as_slice();
+    let mut diff = |old: &[u8], new: &[u8], ignore_padding| {
+        diff_columns(
+            &mut cs,
+            |cs| compute_trace_str(old, cs, false),
+            |cs| compute_trace_str(new, cs, false),
+            &TraceDiffSettings {
+                ignore_padding,
+                rows: 10,
+            },
+        )
+        .map(|modules| modules.into_values().flatten().collect::<Vec<_>>())
+    };
+
+    assert!(diff(old, old, false)?.is_empty());
+    let changed = diff(old, new, false)?;
+    assert_eq!(changed.len(), 1);
+    assert_eq!((changed[0].name.as_str(), changed[0].count), ("A", 1));
+    assert!(!diff(old, padded, false)?.is_empty());
+    // only the lengths differ once padding is ignored
+    let padded = diff(old, padded, true)?;
+    assert!(!padded.is_empty());
+    assert!(padded.iter().all(|d| d.count == 0 && d.lens.0 != d.lens.1));
+    Ok(())
+}
+
+#[test]
+fn fuzzing() -> Result<()> {
+    use crate::fuzz::{fuzz, FuzzSettings};
+
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(
+        "(defcolumns A B FREE (BIT :binary))
+(defconstraint eq () (vanishes! (- A B)))
+(defconstraint bit () (vanishes! (* BIT (- A 3))))",
+    )?;
+    let mut cs = r.into_constraint_set()?;
+
+    let report = fuzz(
+        &mut cs,
+        &FuzzSettings {
+            rows: 4,
+            iterations: 1000,
+            seed: 0,
+            attempts: 32,
+        },
+    )?;
+    assert!(report.passing.is_some());
+    assert_eq!(
+        report
+            .under_constrained
+            .iter()
+            .map(|v| v.column.name.as_str())
+            .collect::<Vec<_>>(),
+        ["FREE"]
+    );
+    Ok(())
+}
+
+#[test]
+fn soundness_audit() -> Result<()> {
+    use crate::audit::{audit, Issue};
+
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(
+        "(defcolumns A B FREE (BYTE :byte) (PROVEN :byte@prove) SEL (BIT :binary) X)
+(defcolumns (IN_PROVEN :byte) (IN_IN_PROVEN :byte) (IN_BYTE :byte) (IN_NATIVE :byte))
+(defconstraint eq () (vanishes! (- A B)))
+(defconstraint guarded () (vanishes! (* SEL (- X 3))))
+(defconstraint bit () (vanishes! (* BIT (- 1 BIT))))
+(defconstraint sel () (vanishes! (- SEL BYTE PROVEN)))
+(deflookup l1 (PROVEN) (IN_PROVEN))
+(deflookup l2 (IN_PROVEN) (IN_IN_PROVEN))
+(deflookup l3 (BYTE) (IN_BYTE))
+(deflookup l4 (A) (IN_NATIVE))",
+    )?;
+    let cs = r.into_constraint_set()?;
+
+    let report = audit(&cs);
+    let warnings = &report.modules["<prelude>"];
+    assert_eq!(
+        warnings
+            .iter()
+            .map(|w| (w.column.as_str(), &w.issue))
+            .collect::<Vec<_>>(),
+        [
+            ("BYTE", &Issue::Unproven { bits: 8 }),
+            ("FREE", &Issue::Unconstrained),
+            ("IN_BYTE", &Issue::Unproven { bits: 8 }),
+            ("IN_NATIVE", &Issue::Unproven { bits: 8 }),
+            (
+                "X",
+                &Issue::Guarded {
+                    selectors: vec!["SEL".to_string()]
+                }
+            ),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn memory_budget() -> Result<()> {
+    use crate::{compiler::ColumnRef, compute, import, structs::Handle};
+
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(
+        "(defcolumns A B)
+(definterleaved UNUSED (A B))
+(definterleaved USED (A B))
+(defconstraint c () (vanishes! (- USED 1)))",
+    )?;
+    let mut cs = r.into_constraint_set()?;
+    import::read_trace_str(
+        br#"{"<prelude>": {"A": [1, 2, 3], "B": [4, 5, 6]}}"#,
+        &mut cs,
+        false,
+    )?;
+    compute::prepare_within(&mut cs, true, None, Some(0))?;
+
+    let column = |name: &str| ColumnRef::from(Handle::new("<prelude>", name));
+    assert!(!cs.columns.is_computed(&column("UNUSED")));
+    assert!(cs.columns.is_computed(&column("USED")));
+    assert!(cs.columns.is_computed(&column("A")));
+    Ok(())
+}
+
+#[test]
+fn dependency_closure() -> Result<()> {
+    use crate::{compiler::ColumnRef, compute, import, structs::Handle};
+
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(
+        "(module m1)
+(defcolumns A B)
+(definterleaved AB (A B))
+(defconstraint c1 () (vanishes! (- AB 1)))
+
+(module m2)
+(defcolumns C D)
+(definterleaved CD (C D))
+(defconstraint c2 () (vanishes! (- CD 2)))",
+    )?;
+    let mut cs = r.into_constraint_set()?;
+    import::read_trace_str(
+        br#"{"m1": {"A": [1, 1], "B": [1, 1]}, "m2": {"C": [2, 2], "D": [2, 2]}}"#,
+        &mut cs,
+        false,
+    )?;
+
+    let scope = compute::dependency_closure(&cs, &["m1".to_string()], &[], None)?;
+    compute::prepare_within(&mut cs, true, scope.as_ref(), None)?;
+    let column = |module: &str, name: &str| ColumnRef::from(Handle::new(module, name));
+    assert!(cs.columns.is_computed(&column("m1", "AB")));
+    assert!(!cs.columns.is_computed(&column("m2", "CD")));
+
+    let scope = compute::dependency_closure(&cs, &[], &[], Some(&vec!["m2.c2".to_string()]))?;
+    assert!(scope
+        .unwrap()
+        .contains(&cs.columns.id_of(&column("m2", "D"))));
+
+    assert!(compute::dependency_closure(&cs, &["m3".to_string()], &[], None).is_err());
+    assert!(compute::dependency_closure(&cs, &[], &[], Some(&vec!["m2.c3".to_string()])).is_err());
+    Ok(())
+}
+
+#[test]
+fn check_profile() -> Result<()> {
+    use crate::{
+        check::{check, DebugSettings, ProfileOrder},
+        compute,
+    };
+
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(
+        "(module m1)
+(defcolumns A B)
+(defconstraint c1 () (vanishes! (- A B)))
+
+(module m2)
+(defcolumns C)
+(defconstraint c2 () (vanishes! (* C (- C 1))))
+(defconstraint c3 () (vanishes! (- C 2)))",
+    )?;
+    let mut cs = r.into_constraint_set()?;
+    compute::compute_trace_str(
+        br#"{"m1": {"A": [1, 2, 3], "B": [1, 2, 3]}, "m2": {"C": [1, 0]}}"#,
+        &mut cs,
+        true,
+    )?;
+
+    let report = check(
+        &cs,
+        &None,
+        &[],
+        DebugSettings::new().continue_on_error(true),
+    )?;
+    assert_eq!(
+        report
+            .failures
+            .iter()
+            .map(|f| f.handle.name.as_str())
+            .collect::<Vec<_>>(),
+        ["c3"]
+    );
+
+    let mut profile = report.profile;
+    assert_eq!(profile.constraints.len(), 3);
+    profile.sort(ProfileOrder::Failures);
+    assert_eq!(profile.modules[0].module, "m2");
+    assert_eq!(profile.modules[0].constraints, 2);
+    assert_eq!(profile.constraints[0].handle.name, "c3");
+    assert!(profile.constraints[0].failures > 0);
+    assert!(profile.modules.iter().all(|m| m.rows > 0));
+    Ok(())
+}
+
+#[test]
+fn check_modes() -> Result<()> {
+    use crate::{
+        check::{check, CheckMode, DebugSettings},
+        compute,
+    };
+
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(
+        "(defcolumns A B)
+(defconstraint c () (vanishes! (- A B)))
+(defconstraint d () (vanishes! (- (next A) (+ A 1))))",
+    )?;
+    let mut cs = r.into_constraint_set()?;
+    compute::compute_trace_str(
+        br#"{"<prelude>": {"A": [1, 2, 3, 4, 5, 6, 7, 8], "B": [1, 2, 3, 4, 9, 6, 7, 8]}}"#,
+        &mut cs,
+        true,
+    )?;
+
+    let failing = |mode: CheckMode| -> Result<Vec<isize>> {
+        let report = check(&cs, &None, &[], DebugSettings::new().mode(mode))?;
+        assert_eq!(report.mode, mode);
+        Ok(report.failures.into_iter().flat_map(|f| f.rows).collect())
+    };
+    // B differs from A on the 5th row, i.e. the 6th one once padded
+    assert_eq!(failing(CheckMode::Full)?, [5]);
+    assert!(failing(CheckMode::window("0..5")?)?.is_empty());
+    assert_eq!(failing(CheckMode::window("4..8")?)?, [5]);
+    assert!(failing(CheckMode::Boundary)?.is_empty());
+    assert!(failing(CheckMode::Sample { rows: 4, seed: 0 })?.len() <= 1);
+    assert_eq!(failing(CheckMode::Sample { rows: 100, seed: 0 })?, [5]);
+    assert!(CheckMode::window("8..2").is_err());
+    Ok(())
+}
+
+#[test]
+fn compiled_evaluation() -> Result<()> {
+    use crate::{
+        compiler::{
+            bytecode::{Access, Evaluator, Program},
+            Constraint, EvalSettings,
+        },
+        compute,
+    };
+
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source(
+        "(defcolumns A B (C :comp (* A (+ B 2))))
+(defconstraint products () (vanishes! (- (* A B) (prev C))))
+(defconstraint conditions () (if-zero A (vanishes! B) (vanishes! (- (next A) (+ A 1)))))
+(defconstraint inverses () (vanishes! (* (- A (prev A) 1) (~ (+ B 7)) (- C 3))))
+(defconstraint lists () (begin (vanishes! (- A 2)) (vanishes! (if-not-zero B (- B 1)))))",
+    )?;
+    r.expand_to(ExpansionLevel::top());
+    let mut cs = r.into_constraint_set()?;
+    compute::compute_trace_str(
+        br#"{"<prelude>": {"A": [0, 1, 2, 0, 4, 5], "B": [1, 0, 3, 1, 0, 2]}}"#,
+        &mut cs,
+        true,
+    )?;
+
+    // the computed column is materialized from its compiled expression; the
+    // imported trace starts with a padding row
+    let c = cs
+        .columns
+        .all()
+        .into_iter()
+        .find(|h| cs.handle(h).name == "C")
+        .unwrap();
+    let values = (0..7)
+        .map(|i| cs.columns.get(&c, i, false).unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(values, ["0", "0", "2", "10", "0", "8", "20"]);
+
+    let rows = (-3..10).collect::<Vec<_>>();
+    for c in cs.constraints.iter() {
+        if let Constraint::Vanishes { expr, .. } = c {
+            let program = Program::compile(expr)?;
+            for wrap in [false, true] {
+                let mut evaluator =
+                    Evaluator::new(&program, &cs.columns, Access::Raw { wrap }, cs.field);
+                let compiled = evaluator.eval(&rows).to_vec();
+                for (i, compiled) in rows.iter().zip(compiled) {
+                    let walked = expr.eval(
+                        *i,
+                        |handle, i, wrap| cs.columns.get_raw(handle, i, wrap),
+                        &mut None,
+                        &EvalSettings::new().wrap(wrap).field(cs.field),
+                    );
+                    assert_eq!(compiled, walked, "{} at row {}", c.name(), i);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn field_arithmetic() -> Result<()> {
+    use crate::{
+        check::{check, DebugSettings},
+        compute,
+    };
+
+    assert_eq!(Field::Goldilocks.bit_size(), 63);
+    assert_eq!(Field::KoalaBear.bit_size(), 30);
+
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.field(Field::Goldilocks);
+    r.add_source(
+        "(defcolumns A B (W :i64))
+(defconstraint square () (vanishes! (- B (* A A))))",
+    )?;
+    let mut cs = r.into_constraint_set()?;
+    assert_eq!(cs.field, Field::Goldilocks);
+    // 2^64 wraps around to 2^32 - 1, even when not computing natively; and
+    // 64-bit values beyond the modulus are kept in wide columns
+    compute::compute_trace_str(
+        br#"{"<prelude>": {"A": [4294967296], "B": [4294967295], "W": [18446744073709551615]}}"#,
+        &mut cs,
+        true,
+    )?;
+    assert!(check(&cs, &None, &[], DebugSettings::new())?
+        .failures
+        .is_empty());
+    let w = cs
+        .columns
+        .all()
+        .into_iter()
+        .find(|h| cs.handle(h).name == "W")
+        .unwrap();
+    assert_eq!(
+        cs.columns.get(&w, 1, false).unwrap().to_string(),
+        "18446744073709551615"
+    );
+    Ok(())
+}
+
+#[test]
+fn field_widths() -> Result<()> {
+    // the width of the registers follows the field of each constraint set,
+    // whatever was compiled before
+    for (field, width) in [(Field::KoalaBear, 3), (Field::Bls12_377, 1)] {
+        let mut r = ConstraintSetBuilder::from_sources(false, false);
+        r.field(field);
+        r.add_source("(defcolumns (X :i64))")?;
+        let cs = r.into_constraint_set()?;
+        let x = cs.columns.all().pop().unwrap();
+        assert_eq!(cs.columns.register_of(&x).width(), width);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "lsp")]
+#[test]
+fn lsp_positions() {
+    use crate::lsp::{prefix_at, to_offset, word_at};
+    use lsp_types::{Position, Range};
+
+    let content = "ab\ncd";
+    assert_eq!(to_offset(content, Position::new(1, 1)), 4);
+    // past the end of a line or of the content
+    assert_eq!(to_offset(content, Position::new(0, 10)), 2);
+    assert_eq!(to_offset(content, Position::new(5, 0)), content.len());
+    // LSP columns count UTF-16 code units
+    assert_eq!(to_offset("é𝔸x", Position::new(0, 3)), "é𝔸".len());
+
+    let content = "(defcolumns A BC)";
+    assert_eq!(
+        word_at(content, Position::new(0, 15)),
+        Some((
+            "BC".to_string(),
+            Range::new(Position::new(0, 14), Position::new(0, 16))
+        ))
+    );
+    // a cursor right after a symbol still points to it
+    assert_eq!(
+        word_at(content, Position::new(0, 11)).map(|w| w.0),
+        Some("defcolumns".to_string())
+    );
+    assert_eq!(word_at("a  b", Position::new(0, 2)), None);
+
+    assert_eq!(
+        prefix_at("(m.fo bar)", Position::new(0, 5)),
+        (
+            "m.fo".to_string(),
+            Range::new(Position::new(0, 1), Position::new(0, 5))
+        )
+    );
+    assert_eq!(prefix_at("( )", Position::new(0, 1)).0, "");
+}
+
+#[cfg(feature = "lsp")]
+#[test]
+fn lsp_index() -> Result<()> {
+    use crate::compiler::MAIN_MODULE;
+    use crate::lsp::{DefKind, Workspace};
+    use lsp_types::Url;
+
+    let uri = Url::parse("file:///index.lisp")?;
+    let mut ws = Workspace::new(&[], true, false, Field::default())?;
+    let ast = crate::compiler::parser::parser::parse(
+        "(defcolumns A (B :byte))
+(defconst C 3)
+(module m)
+(defcolumns X)
+(defun (f x) x)",
+    )?;
+    ws.index(Some(&uri), &ast);
+
+    let a = ws.find_definition(MAIN_MODULE, "A").unwrap();
+    assert_eq!((a.kind, a.lc.0), (DefKind::Column, 1));
+    assert_eq!(
+        ws.find_definition(MAIN_MODULE, "C").unwrap().detail,
+        "C := 3"
+    );
+    let f = ws.find_definition("m", "f").unwrap();
+    assert_eq!((f.kind, f.module.as_str()), (DefKind::Function, "m"));
+    // definitions of the prelude are visible from every module
+    assert!(ws.find_definition("m", "A").is_some());
+    assert!(ws.find_definition(MAIN_MODULE, "X").is_none());
+
+    assert_eq!(ws.module_at(&uri, 1), MAIN_MODULE);
+    assert_eq!(ws.module_at(&uri, 3), "m");
+
+    assert_eq!(ws.qualify("m", "X"), ("m".to_string(), "X".to_string()));
+    assert_eq!(ws.qualify("m", "n.Y"), ("n".to_string(), "Y".to_string()));
+    assert_eq!(
+        ws.qualify(MAIN_MODULE, "n.persp/Z"),
+        (MAIN_MODULE.to_string(), "Z".to_string())
+    );
+    Ok(())
+}
+
+#[cfg(feature = "lsp")]
+#[test]
+fn lsp_changes() -> Result<()> {
+    use crate::lsp::Workspace;
+    use lsp_types::{Position, Range, TextDocumentContentChangeEvent, Url};
+
+    let uri = Url::parse("file:///changes.lisp")?;
+    let mut ws = Workspace::new(&[], true, false, Field::default())?;
+    ws.open(uri.clone(), "(defcolumns A B)".to_string());
+    let change = |start, end, text: &str| TextDocumentContentChangeEvent {
+        range: Some(Range::new(Position::new(0, start), Position::new(0, end))),
+        range_length: None,
+        text: text.to_string(),
+    };
+    ws.change(&uri, vec![change(12, 13, "X"), change(15, 12, "Y")]);
+    // reversed ranges are ignored rather than applied
+    assert_eq!(ws.files[&uri], "(defcolumns X B)");
+    Ok(())
+}
+
+#[cfg(feature = "lsp")]
+#[test]
+fn lsp_analysis() -> Result<()> {
+    use crate::compiler::MAIN_MODULE;
+    use crate::lsp::Workspace;
+    use lsp_types::{TextDocumentContentChangeEvent, Url};
+
+    let uri = Url::parse("file:///analysis.lisp")?;
+    let mut ws = Workspace::new(&[], true, false, Field::default())?;
+    let replace = |text: &str| {
+        vec![TextDocumentContentChangeEvent {
+            range: None,
+            range_length: None,
+            text: text.to_string(),
+        }]
+    };
+
+    ws.open(
+        uri.clone(),
+        "(defcolumns A)\n(defconstraint c () (vanishes! (+ A Z)))".to_string(),
+    );
+    assert!(ws.find_definition(MAIN_MODULE, "A").is_some());
+    // semantic errors are only found once the workspace is compiled
+    assert!(ws.diagnostics()[&uri].is_empty());
+    ws.analyze();
+    let diagnostics = ws.diagnostics();
+    assert_eq!(diagnostics[&uri].len(), 1);
+    assert_eq!(diagnostics[&uri][0].range.start.line, 1);
+
+    // syntax errors are reported right away, and the definitions of the last
+    // successful parse are kept
+    ws.change(&uri, replace("(defcolumns B"));
+    assert_eq!(ws.diagnostics()[&uri].len(), 1);
+    assert!(ws.find_definition(MAIN_MODULE, "A").is_some());
+    assert!(ws.find_definition(MAIN_MODULE, "B").is_none());
+
+    ws.change(&uri, replace("(defcolumns B)"));
+    assert!(ws.find_definition(MAIN_MODULE, "A").is_none());
+    assert!(ws.find_definition(MAIN_MODULE, "B").is_some());
+    ws.analyze();
+    assert!(ws.diagnostics()[&uri].is_empty());
+    Ok(())
+}
+
+#[test]
+fn diagnostics() -> Result<()> {
+    use crate::errors::diagnostics::{Diagnostic, Label, Severity, Span};
+    use crate::utils::strip_ansi;
+
+    let content = "(defcolumns A)\n  (foo bar)\n";
+    let span = Span::new("f.lisp", content, (2, 3), "(foo bar)\n(baz)");
+    assert_eq!(
+        (span.line, span.column, span.len, span.text.as_str()),
+        (2, 3, 9, "  (foo bar)")
+    );
+    // spans are clamped to their first line
+    assert_eq!(Span::new("f.lisp", content, (2, 8), "bar) (baz)").len, 4);
+
+    let mut r = ConstraintSetBuilder::from_sources(false, false);
+    r.add_source("(defcolumns A)\n(defconstraint c () (vanishes! (+ A Z)))")?;
+    let err = r.into_constraint_set().unwrap_err();
+    let diagnostics = Diagnostic::from_error(&err);
+    assert_eq!(diagnostics.len(), 1);
+    let d = &diagnostics[0];
+    assert!(matches!(d.severity, Severity::Error));
+    let span = d.span.as_ref().unwrap();
+    assert_eq!(span.line, 2);
+
+    assert_eq!(
+        strip_ansi(&d.to_string()),
+        "error: symbol Z not found in module <prelude>
+  --> Immediate expression:2:37
+   |
+ 2 | (defconstraint c () (vanishes! (+ A Z)))
+   |                                     ^
+"
+    );
+
+    let json: serde_json::Value = serde_json::from_str(&d.to_json())?;
+    assert_eq!(json["severity"], "error");
+    assert_eq!(json["message"], "symbol Z not found in module <prelude>");
+    assert_eq!(json["span"]["column"], 37);
+    // the source line is only used for rendering
+    assert!(json["span"].get("text").is_none());
+
+    let warning = Diagnostic {
+        severity: Severity::Warning,
+        message: "shadowed".to_string(),
+        span: Some(Span::new("f.lisp", content, (2, 4), "foo")),
+        labels: vec![Label {
+            span: Span::new("f.lisp", content, (1, 13), "A"),
+            message: "first defined here".to_string(),
+        }],
+        notes: vec!["in module m".to_string()],
+    };
+    assert_eq!(
+        strip_ansi(&warning.to_string()),
+        "warning: shadowed
+  --> f.lisp:2:4
+   |
+ 2 |   (foo bar)
+   |    ^^^
+  ::: f.lisp:1:13
+   |
+ 1 | (defcolumns A)
+   |             - first defined here
+   = note: in module m
+"
+    );
+    Ok(())
+}
@@ -0,0 +1,274 @@
+//! Column-by-column comparison of two traces computed against the same
+//! constraint set.
+use anyhow::*;
+use itertools::Itertools;
+use owo_colors::OwoColorize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    column::{ColumnID, Value},
+    compiler::{ColumnRef, ConstraintSet},
+    compute,
+    pretty::{Base, Pretty},
+};
+
+pub struct TraceDiffSettings {
+    /// whether to skip the rows of padding prepended to the columns
+    pub ignore_padding: bool,
+    /// how many differing rows to list & dump
+    pub rows: usize,
+}
+
+/// The values of a column, from its first padding row on
+struct Values {
+    /// the number of padding rows, i.e. of rows at a negative index
+    spilling: isize,
+    values: Vec<Value>,
+}
+impl Values {
+    fn of(cs: &ConstraintSet, h: &ColumnRef) -> Option<Values> {
+        let len = cs.columns.len(h)? as isize;
+        let spilling = cs.columns.padded_len(h)? as isize - len;
+        Some(Values {
+            spilling,
+            values: (-spilling..len)
+                .map(|i| cs.columns.get(h, i, false).unwrap_or_else(Value::zero))
+                .collect(),
+        })
+    }
+
+    fn len(&self) -> isize {
+        self.values.len() as isize - self.spilling
+    }
+
+    fn get(&self, i: isize) -> Option<&Value> {
+        if i < -self.spilling {
+            None
+        } else {
+            self.values.get((i + self.spilling) as usize)
+        }
+    }
+}
+
+/// How the two versions of a column differ
+pub(crate) struct ColumnDiff {
+    pub(crate) name: String,
+    base: Base,
+    /// the lengths in the old and new traces, if the column was filled
+    pub(crate) lens: (Option<isize>, Option<isize>),
+    /// added to a row of the new trace to get the matching one in the old one
+    offset: isize,
+    /// the differing rows, as indices in the new trace
+    rows: Vec<isize>,
+    /// the total number of differing rows
+    pub(crate) count: usize,
+    old: Option<Values>,
+    new: Option<Values>,
+}
+impl ColumnDiff {
+    fn value(&self, old: bool, i: isize) -> String {
+        let (values, i) = if old {
+            (self.old.as_ref(), i + self.offset)
+        } else {
+            (self.new.as_ref(), i)
+        };
+        values
+            .and_then(|v| v.get(i))
+            .map(|x| x.pretty_with_base(self.base))
+            .unwrap_or_else(|| "nil".into())
+    }
+}
+
+fn compare(
+    name: String,
+    base: Base,
+    old: Option<Values>,
+    new: Option<Values>,
+    settings: &TraceDiffSettings,
+) -> Option<ColumnDiff> {
+    let lens = (old.as_ref().map(Values::len), new.as_ref().map(Values::len));
+    let (offset, rows, count) = match (old.as_ref(), new.as_ref()) {
+        (Some(old), Some(new)) => {
+            // Padding is prepended; so once it is ignored, the columns are
+            // aligned on their last row
+            let (offset, start, end) = if settings.ignore_padding {
+                let offset = old.len() - new.len();
+                (offset, (-offset).max(0), new.len())
+            } else {
+                (0, -old.spilling.max(new.spilling), old.len().max(new.len()))
+            };
+            let mut rows = Vec::new();
+            let mut count = 0;
+            for i in start..end {
+                if old.get(i + offset) != new.get(i) {
+                    count += 1;
+                    if rows.len() < settings.rows {
+                        rows.push(i);
+                    }
+                }
+            }
+            (offset, rows, count)
+        }
+        (None, None) => return None,
+        _ => (0, Vec::new(), 0),
+    };
+
+    (count > 0 || lens.0 != lens.1).then_some(ColumnDiff {
+        name,
+        base,
+        lens,
+        offset,
+        rows,
+        count,
+        old,
+        new,
+    })
+}
+
+/// Fill `cs` with `old`, then with `new`, and return per module the columns
+/// differing between both
+pub(crate) fn diff_columns(
+    cs: &mut ConstraintSet,
+    old: impl FnOnce(&mut ConstraintSet) -> Result<()>,
+    new: impl FnOnce(&mut ConstraintSet) -> Result<()>,
+    settings: &TraceDiffSettings,
+) -> Result<BTreeMap<String, Vec<ColumnDiff>>> {
+    cs.columns.reset();
+    old(cs)?;
+    let mut old_values = cs
+        .columns
+        .all()
+        .into_iter()
+        .filter_map(|h| Values::of(cs, &h).map(|v| (h.as_id(), v)))
+        .collect::<HashMap<ColumnID, _>>();
+
+    cs.columns.reset();
+    new(cs)?;
+
+    let mut modules = BTreeMap::<String, Vec<ColumnDiff>>::new();
+    for (h, column) in cs
+        .columns
+        .iter()
+        .sorted_by_cached_key(|(_, c)| c.handle.to_string())
+    {
+        if let Some(d) = compare(
+            column.handle.name.clone(),
+            column.base,
+            old_values.remove(&h.as_id()),
+            Values::of(cs, &h),
+            settings,
+        ) {
+            modules
+                .entry(column.handle.module.clone())
+                .or_default()
+                .push(d);
+        }
+    }
+    Ok(modules)
+}
+
+/// Compute `old_trace` and `new_trace` against `cs`, then report per module
+/// their differing columns. Return whether any difference has been found.
+pub fn trace_diff(
+    cs: &mut ConstraintSet,
+    old_trace: &str,
+    new_trace: &str,
+    settings: &TraceDiffSettings,
+) -> Result<bool> {
+    let modules = diff_columns(
+        cs,
+        |cs| {
+            compute::compute_trace(old_trace, cs, false)
+                .with_context(|| format!("while computing from `{}`", old_trace))
+        },
+        |cs| {
+            compute::compute_trace(new_trace, cs, false)
+                .with_context(|| format!("while computing from `{}`", new_trace))
+        },
+        settings,
+    )?;
+
+    let describe = |len: Option<isize>| {
+        len.map(|l| l.to_string())
+            .unwrap_or_else(|| "missing".to_string())
+    };
+    for (module, diffs) in modules.iter() {
+        println!(
+            "{} {} differing columns",
+            module.bold().bright_white(),
+            diffs.len().to_string().red().bold()
+        );
+        for d in diffs.iter() {
+            print!("  {}", d.name.bold());
+            if d.lens.0 != d.lens.1 {
+                print!(
+                    ": length {} → {}",
+                    describe(d.lens.0).red(),
+                    describe(d.lens.1).green()
+                );
+            }
+            if d.count > 0 {
+                print!(
+                    "{} {} differing rows ({}{})",
+                    if d.lens.0 != d.lens.1 { ";" } else { ":" },
+                    d.count,
+                    d.rows.iter().join(", "),
+                    if d.count > d.rows.len() { ", ..." } else { "" }
+                );
+            }
+            println!();
+        }
+
+        // Dump side-by-side the first differing rows of the module, numbered
+        // as in the new trace
+        let compared = diffs
+            .iter()
+            .filter(|d| !d.rows.is_empty())
+            .collect::<Vec<_>>();
+        let rows = compared
+            .iter()
+            .flat_map(|d| d.rows.iter().cloned())
+            .sorted()
+            .dedup()
+            .take(settings.rows)
+            .collect::<Vec<_>>();
+        if rows.is_empty() {
+            continue;
+        }
+        let mut table = vec![std::iter::once("row".to_string())
+            .chain(
+                compared
+                    .iter()
+                    .flat_map(|d| [format!("{} (old)", d.name), format!("{} (new)", d.name)]),
+            )
+            .collect::<Vec<_>>()];
+        for i in rows.iter() {
+            let mut line = vec![i.to_string()];
+            for d in compared.iter() {
+                line.push(d.value(true, *i));
+                line.push(d.value(false, *i));
+            }
+            table.push(line);
+        }
+        let widths = (0..table[0].len())
+            .map(|j| table.iter().map(|l| l[j].len()).max().unwrap() + 2)
+            .collect::<Vec<_>>();
+        println!();
+        for (k, line) in table.iter().enumerate() {
+            for (j, cell) in line.iter().enumerate() {
+                let cell = format!("{:width$}", cell, width = widths[j]);
+                if k == 0 || j == 0 {
+                    print!("{}", cell.bright_white().bold());
+                } else if j % 2 == 0 && line[j - 1] != line[j] {
+                    print!("{}", cell.red().bold());
+                } else {
+                    print!("{}", cell);
+                }
+            }
+            println!();
+        }
+        println!();
+    }
+
+    Ok(!modules.is_empty())
+}
@@ -1,8 +1,13 @@
 mod concretize;
+mod cse;
+mod degree;
 mod ifs;
 mod inverses;
+mod limbs;
 mod nhood;
+mod prune;
 mod selectors;
+mod simplify;
 mod sort;
 mod splatter;
 mod statics;
@@ -11,16 +16,26 @@ use anyhow::*;
 use log::*;
 
 pub use concretize::concretize;
+use cse::cse;
+use degree::reduce_degree;
 use ifs::expand_ifs;
 use inverses::expand_invs;
+use limbs::limbs;
 use nhood::validate_nhood;
+use prune::{prune, PruneReport};
 use selectors::expand_constraints;
+use simplify::simplify_constraints;
 use sort::sorts;
 use splatter::splatter;
 pub use statics::precompute;
 
+use num_bigint::BigInt;
+use num_traits::Signed;
+
 use crate::{
+    column::Value,
     compiler::{ConstraintSet, Expression, Intrinsic, Kind, Magma, Node},
+    field::Field,
     structs::Handle,
 };
 
@@ -70,6 +85,7 @@ pub(crate) enum ExpansionLevel {
     Splatter = 2,
     ColumnizeExpressions = 4,
     ExpandInvs = 8,
+    Limbs = 16,
 }
 impl From<u8> for ExpansionLevel {
     fn from(x: u8) -> Self {
@@ -79,13 +95,14 @@ impl From<u8> for ExpansionLevel {
             2 => ExpansionLevel::Splatter,
             3 => ExpansionLevel::ColumnizeExpressions,
             4 => ExpansionLevel::ExpandInvs,
-            _ => ExpansionLevel::ExpandInvs,
+            5 => ExpansionLevel::Limbs,
+            _ => ExpansionLevel::Limbs,
         }
     }
 }
 impl ExpansionLevel {
     pub fn all() -> u8 {
-        5
+        6
     }
 
     pub fn top() -> ExpansionLevel {
@@ -101,6 +118,7 @@ impl ExpansionLevel {
                 ExpansionLevel::Splatter => splatter(cs),
                 ExpansionLevel::ColumnizeExpressions => expand_constraints(cs)?,
                 ExpansionLevel::ExpandInvs => expand_invs(cs)?,
+                ExpansionLevel::Limbs => limbs(cs)?,
             }
             cs.transformations |= *self as u32;
         }
@@ -113,13 +131,35 @@ pub(crate) fn expand_to(
     cs: &mut ConstraintSet,
     level: ExpansionLevel,
     auto_constraints: &[AutoConstraint],
-) -> Result<()> {
+    max_degree: Option<usize>,
+    eliminate_common_subexpressions: bool,
+    simplify: bool,
+    prune_unused: bool,
+) -> Result<Option<PruneReport>> {
     for c in auto_constraints.iter() {
         c.apply(cs)?;
     }
 
+    // Limbs decomposition only handles linear constraints, and must thus
+    // happen before any further expansion introduces non-linear terms
+    for transformation in [ExpansionLevel::ExpandsIfs, ExpansionLevel::Limbs] {
+        if level >= transformation {
+            transformation.apply(cs)?;
+        }
+    }
+    if simplify {
+        simplify_constraints(cs);
+    }
+    // Both work on the actual polynomials, so conditionals must be expanded
+    if eliminate_common_subexpressions {
+        ExpansionLevel::ExpandsIfs.apply(cs)?;
+        cse(cs)?;
+    }
+    if let Some(max_degree) = max_degree {
+        ExpansionLevel::ExpandsIfs.apply(cs)?;
+        reduce_degree(cs, max_degree)?;
+    }
     for transformation in [
-        ExpansionLevel::ExpandsIfs,
         ExpansionLevel::Splatter,
         ExpansionLevel::ColumnizeExpressions,
         ExpansionLevel::ExpandInvs,
@@ -128,9 +168,13 @@ pub(crate) fn expand_to(
             transformation.apply(cs)?;
         }
     }
+    // Pruning must come last, for the previous steps may create new columns
+    // or make some obsolete
+    let report = if prune_unused { Some(prune(cs)?) } else { None };
 
     cs.convert_refs_to_ids()?;
-    cs.validate()
+    cs.validate()?;
+    Ok(report)
 }
 
 fn validate_computation(cs: &mut Vec<Node>, x_expr: &Node, x_col: &Handle) {
@@ -148,6 +192,27 @@ fn validate_computation(cs: &mut Vec<Node>, x_expr: &Node, x_col: &Handle) {
     )
 }
 
+/// Interpret a constant as a signed integer, mapping the upper half of
+/// `field` to negative numbers when computing natively
+fn signed(x: &Value, field: Field) -> BigInt {
+    let x = x.to_bi();
+    let modulus = field.modulus();
+    if (*crate::IS_NATIVE.read().unwrap() || !field.is_ark()) && x > modulus >> 1 {
+        x - modulus
+    } else {
+        x
+    }
+}
+
+/// Build a constant from a signed integer
+fn from_signed(x: &BigInt) -> Node {
+    if x.is_negative() {
+        Intrinsic::Neg.call(&[Node::from_bigint(-x)]).unwrap()
+    } else {
+        Node::from_bigint(x.to_owned())
+    }
+}
+
 fn expression_to_name(e: &Node, prefix: &str) -> String {
     format!("C/{}[{}]", prefix, e)
 }
@@ -65,8 +65,10 @@ impl ConstraintSet {
     }
 }
 
+/// Values are only stored as field elements in the fields backed by arkworks;
+/// the others compute over reduced integers
 pub fn concretize(cs: &mut ConstraintSet) {
-    if *crate::IS_NATIVE.read().unwrap() {
+    if *crate::IS_NATIVE.read().unwrap() && cs.field.is_ark() {
         cs.make_registers_native();
         cs.make_constraints_native();
         cs.make_computations_native();
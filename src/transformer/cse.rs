@@ -0,0 +1,157 @@
+use anyhow::*;
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    compiler::{Constraint, ConstraintSet, Expression, Intrinsic, Node},
+    structs::Handle,
+};
+
+use super::selectors::{columnized, do_expand_expr};
+
+/// Whether `e` is worth sharing in a column of its own
+fn is_candidate(e: &Node) -> bool {
+    match e.e() {
+        Expression::Funcall { func, args } => {
+            // Negated columns are not worth a column of their own
+            let is_negated_leaf = matches!(func, Intrinsic::Neg) && args[0].leaves().len() == 1;
+            let is_wide = e.leaves().iter().any(|l| l.is_exocolumn());
+            !is_negated_leaf && !is_wide && e.pure_eval().is_err()
+        }
+        _ => false,
+    }
+}
+
+/// Apply `f` to all the strict sub-expressions of `e` that could be shared
+fn for_each_candidate(e: &Node, f: &mut dyn FnMut(&Node)) {
+    let children = match e.e() {
+        Expression::Funcall { args, .. } => args,
+        Expression::List(xs) => xs,
+        _ => return,
+    };
+    for c in children.iter() {
+        if is_candidate(c) {
+            f(c);
+        }
+        for_each_candidate(c, f);
+    }
+}
+
+/// The expressions of `c` that may contain common sub-expressions
+fn roots(c: &mut Constraint) -> Vec<(&str, &mut Node)> {
+    match c {
+        Constraint::Vanishes { handle, expr, .. } => vec![(&handle.module, expr.as_mut())],
+        Constraint::Lookup {
+            handle,
+            including,
+            included,
+        } => including
+            .iter_mut()
+            .chain(included.iter_mut())
+            .map(|e| (handle.module.as_str(), e))
+            .collect(),
+        Constraint::InRange { handle, exp, .. } => vec![(&handle.module, exp)],
+        Constraint::Permutation { .. } | Constraint::Normalization { .. } => vec![],
+    }
+}
+
+/// Eliminate the common sub-expressions of all the constraints, by computing
+/// each of the expressions occuring more than once in a single column.
+pub fn cse(cs: &mut ConstraintSet) -> Result<()> {
+    let mut counts = HashMap::<Node, usize>::new();
+    for c in cs.constraints.iter_mut() {
+        for (_, e) in roots(c) {
+            if is_candidate(e) {
+                *counts.entry(e.clone()).or_default() += 1;
+            }
+            for_each_candidate(e, &mut |x| *counts.entry(x.clone()).or_default() += 1);
+        }
+    }
+
+    // Share the largest expressions first: the occurrences of their own
+    // sub-expressions then collapse into the one in the shared definition
+    let mut candidates = counts
+        .iter()
+        .filter(|(_, count)| **count > 1)
+        .map(|(e, _)| e.clone())
+        .collect::<Vec<_>>();
+    candidates.sort_by_cached_key(|e| (std::cmp::Reverse(e.size()), e.to_string()));
+    let mut shared = HashSet::new();
+    for e in candidates.into_iter() {
+        let count = counts[&e];
+        if count > 1 {
+            for_each_candidate(&e, &mut |x| {
+                let c = counts.get_mut(x).unwrap();
+                *c = c.saturating_sub(count - 1);
+            });
+            shared.insert(e);
+        }
+    }
+    if shared.is_empty() {
+        return Ok(());
+    }
+
+    let mut columnized = columnized(&cs.computations);
+    let mut new_cs_exps = vec![];
+    let mut constraints = std::mem::take(&mut cs.constraints);
+    for c in constraints.iter_mut() {
+        for (module, e) in roots(c) {
+            *e = share(e, &shared, &mut |x| {
+                do_expand_expr(
+                    x,
+                    module,
+                    &mut cs.columns,
+                    &mut cs.computations,
+                    &mut columnized,
+                    &mut new_cs_exps,
+                    cs.field,
+                )
+            })?;
+        }
+    }
+    cs.constraints = constraints;
+    cs.insert_constraint(Constraint::Vanishes {
+        handle: Handle::new("RESERVED", "CSE_CONSTRAINTS"),
+        domain: None,
+        expr: Box::new(Expression::List(new_cs_exps).into()),
+    });
+
+    Ok(())
+}
+
+/// Replace the `shared` sub-expressions of `e` by their column
+fn share(
+    e: &Node,
+    shared: &HashSet<Node>,
+    columnize: &mut dyn FnMut(&Node) -> Result<Node>,
+) -> Result<Node> {
+    let r = match e.e() {
+        Expression::Funcall { func, args } => {
+            let new_args = args
+                .iter()
+                .map(|a| share(a, shared, columnize))
+                .collect::<Result<Vec<_>>>()?;
+            if new_args == *args {
+                e.clone()
+            } else {
+                func.call(&new_args)?
+            }
+        }
+        Expression::List(xs) => {
+            let new_xs = xs
+                .iter()
+                .map(|x| share(x, shared, columnize))
+                .collect::<Result<Vec<_>>>()?;
+            if new_xs == *xs {
+                e.clone()
+            } else {
+                Node::from_expr(Expression::List(new_xs))
+            }
+        }
+        _ => return Ok(e.clone()),
+    };
+    if shared.contains(e) {
+        columnize(&r)
+    } else {
+        Ok(r)
+    }
+}
@@ -0,0 +1,158 @@
+use anyhow::*;
+use num_traits::ToPrimitive;
+use std::collections::HashMap;
+
+use crate::{
+    column::ColumnSet,
+    compiler::{
+        ColumnRef, ComputationTable, Constraint, ConstraintSet, Expression, Intrinsic, Node,
+    },
+    field::Field,
+    pretty::Pretty,
+    structs::Handle,
+};
+
+use super::selectors::{columnized, do_expand_expr};
+
+struct Reducer<'a> {
+    max_degree: usize,
+    module: &'a str,
+    cols: &'a mut ColumnSet,
+    comps: &'a mut ComputationTable,
+    columnized: &'a mut HashMap<Node, ColumnRef>,
+    new_cs: &'a mut Vec<Node>,
+    field: Field,
+}
+impl Reducer<'_> {
+    /// Replace `e` by a computed column, of degree 1
+    fn columnize(&mut self, e: &Node) -> Result<Node> {
+        do_expand_expr(
+            e,
+            self.module,
+            self.cols,
+            self.comps,
+            self.columnized,
+            self.new_cs,
+            self.field,
+        )
+    }
+
+    /// Rewrite `e` into an expression of degree at most `max_degree`, moving
+    /// sub-expressions into computed columns as required
+    fn reduce(&mut self, e: &Node) -> Result<Node> {
+        if e.degree() <= self.max_degree {
+            return Ok(e.clone());
+        }
+
+        match e.e() {
+            Expression::List(xs) => Ok(Node::from_expr(Expression::List(
+                xs.iter()
+                    .map(|x| self.reduce(x))
+                    .collect::<Result<Vec<_>>>()?,
+            ))),
+            Expression::Funcall { func, args } => match func {
+                Intrinsic::Add
+                | Intrinsic::Sub
+                | Intrinsic::VectorAdd
+                | Intrinsic::VectorSub
+                | Intrinsic::Neg
+                | Intrinsic::Begin => func.call(
+                    &args
+                        .iter()
+                        .map(|x| self.reduce(x))
+                        .collect::<Result<Vec<_>>>()?,
+                ),
+                Intrinsic::Mul | Intrinsic::VectorMul => {
+                    let factors = args
+                        .iter()
+                        .map(|x| self.reduce(x))
+                        .collect::<Result<Vec<_>>>()?;
+                    self.reduce_product(*func, factors)
+                }
+                Intrinsic::Exp => {
+                    let exponent = args[1]
+                        .pure_eval()?
+                        .to_usize()
+                        .ok_or_else(|| anyhow!("invalid exponent {}", args[1].pretty()))?;
+                    let base = self.reduce(&args[0])?;
+                    self.reduce_product(Intrinsic::Mul, vec![base; exponent])
+                }
+                Intrinsic::Normalize => {
+                    let x = self.reduce(&args[0])?;
+                    Intrinsic::Normalize.call(&[self.columnize(&x)?])
+                }
+                Intrinsic::Inv | Intrinsic::IfZero | Intrinsic::IfNotZero => {
+                    unreachable!("{:?} should have been expanded", func)
+                }
+            },
+            _ => Ok(e.clone()),
+        }
+    }
+
+    /// Columnize the factors of a product, each of them of degree at most
+    /// `max_degree`, until the product itself fits in `max_degree`
+    fn reduce_product(&mut self, func: Intrinsic, mut factors: Vec<Node>) -> Result<Node> {
+        while factors.iter().map(Node::degree).sum::<usize>() > self.max_degree {
+            let (heaviest, degree) = factors
+                .iter()
+                .map(Node::degree)
+                .enumerate()
+                .max_by_key(|(_, d)| *d)
+                .unwrap();
+            if degree > 1 {
+                factors[heaviest] = self.columnize(&factors[heaviest])?;
+            } else {
+                // Only columns are left; merge as many as possible in a
+                // single new one
+                let mut merged = Vec::new();
+                let mut rest = Vec::new();
+                for f in factors.into_iter() {
+                    if merged.len() < self.max_degree && f.degree() > 0 {
+                        merged.push(f);
+                    } else {
+                        rest.push(f);
+                    }
+                }
+                factors = vec![self.columnize(&func.call(&merged)?)?];
+                factors.extend(rest);
+            }
+        }
+        func.call(&factors)
+    }
+}
+
+/// Introduce intermediate computed columns until all the vanishing
+/// constraints are of degree at most `max_degree`.
+pub fn reduce_degree(cs: &mut ConstraintSet, max_degree: usize) -> Result<()> {
+    if max_degree < 2 {
+        bail!("the maximal degree must be at least 2")
+    }
+
+    let mut new_cs_exps = vec![];
+    let mut columnized = columnized(&cs.computations);
+    for c in cs.constraints.iter_mut() {
+        if let Constraint::Vanishes { handle, expr, .. } = c {
+            if expr.degree() > max_degree {
+                let mut reducer = Reducer {
+                    max_degree,
+                    module: &handle.module,
+                    cols: &mut cs.columns,
+                    comps: &mut cs.computations,
+                    columnized: &mut columnized,
+                    new_cs: &mut new_cs_exps,
+                    field: cs.field,
+                };
+                **expr = reducer.reduce(expr)?;
+            }
+        }
+    }
+    if !new_cs_exps.is_empty() {
+        cs.insert_constraint(Constraint::Vanishes {
+            handle: Handle::new("RESERVED", "DEGREE_REDUCTION_CONSTRAINTS"),
+            domain: None,
+            expr: Box::new(Expression::List(new_cs_exps).into()),
+        });
+    }
+
+    Ok(())
+}
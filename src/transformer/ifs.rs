@@ -10,13 +10,13 @@ use super::{flatten_list, wrap};
 /// `IfZero` / `IfNotZero` but nothing else.  The simplest example is
 /// something like this:
 ///
-/// ```
+/// ```lisp
 /// (if (vanishes! A) B C)
 /// ```
 ///
 /// Which is translated into a list of two constraints:
 ///
-/// ```
+/// ```text
 /// {
 ///  (1 - NORM(A)) * B
 ///  A * C
@@ -201,7 +201,7 @@ fn raise_ifs(mut e: Node) -> Node {
 ///
 /// Would be compiled as follows:
 ///
-/// ```
+/// ```text
 /// (1 - NORM(A)) * B
 /// ```
 ///
@@ -3,6 +3,7 @@ use std::collections::HashSet;
 use crate::{
     column::{Column, Computation},
     compiler::{ColumnRef, Constraint, ConstraintSet, Expression, Intrinsic, Kind, Node},
+    field::Field,
     structs::Handle,
 };
 use anyhow::*;
@@ -21,16 +22,17 @@ impl Node {
         &mut self,
         get_module: &dyn Fn(&HashSet<ColumnRef>) -> String,
         new_cols: &mut Vec<(Handle, Node)>,
+        field: Field,
     ) {
         match self.e_mut() {
             Expression::List(es) => {
                 for e in es.iter_mut() {
-                    e.do_normalize(get_module, new_cols);
+                    e.do_normalize(get_module, new_cols, field);
                 }
             }
             Expression::Funcall { func, args, .. } => {
                 for e in args.iter_mut() {
-                    e.do_normalize(get_module, new_cols);
+                    e.do_normalize(get_module, new_cols, field);
                 }
                 if matches!(func, Intrinsic::Normalize) {
                     // Intrinsic::Inv should never have more than one argument
@@ -39,7 +41,9 @@ impl Node {
                     if let Result::Ok(inverted) = arg.pure_eval() {
                         // Replace by the numeric inverse if the value is known at compile time
                         *self = Node::from_value(
-                            crate::column::Value::try_from(inverted).unwrap().inverse(),
+                            crate::column::Value::try_from(inverted)
+                                .unwrap()
+                                .inverse(field),
                         );
                     } else if arg.t().is_binary() {
                         // No need for a normalised column if its already binary.
@@ -91,7 +95,7 @@ impl ConstraintSet {
         let get_module = |rs: &HashSet<ColumnRef>| self.columns.module_for(rs.iter()).unwrap();
         for i in 0..self.constraints.len() {
             if let Constraint::Vanishes { expr: e, .. } = self.constraints.get_mut(i).unwrap() {
-                e.do_normalize(&get_module, &mut new_cols);
+                e.do_normalize(&get_module, &mut new_cols, self.field);
             }
         }
 
@@ -138,6 +142,7 @@ impl ConstraintSet {
                         .handle(inverted_handle.clone())
                         .kind(Kind::Computed)
                         .build(),
+                    self.field,
                 )?;
 
                 self.computations.insert(
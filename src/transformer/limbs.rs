@@ -0,0 +1,499 @@
+use anyhow::{bail, Result};
+use num_bigint::BigInt;
+use num_traits::{Euclid, One, Signed, ToPrimitive, Zero};
+use owo_colors::OwoColorize;
+use std::collections::HashMap;
+
+use crate::{
+    column::{Column, Computation, Value},
+    compiler::{ColumnRef, Constraint, ConstraintSet, Expression, Intrinsic, Kind, Magma, Node},
+    field::Field,
+    pretty::{Base, Pretty},
+    structs::Handle,
+};
+
+use super::{from_signed, signed};
+
+/// Carries larger than that would require unreasonably large range checks
+const MAX_CARRY: usize = 1 << 16;
+
+/// The width of the limbs; half of the field, so that limb-wise sums of a
+/// few limbs can not wrap around
+fn limb_bits(field: Field) -> usize {
+    field.bit_size() / 2
+}
+
+/// Whether `e` refers to a column too wide to fit in the field
+fn is_wide(e: &Node) -> bool {
+    e.leaves().iter().any(|l| l.is_exocolumn())
+}
+
+enum Term {
+    /// a column, possibly shifted, wider than a limb
+    Limbed {
+        column: ColumnRef,
+        shift: i16,
+    },
+    /// an expression fitting in a single limb
+    Small(Node),
+    One,
+}
+
+/// Write `e` as a linear combination of terms with constant coefficients, if
+/// possible
+fn linearize(e: &Node, bits: usize, field: Field) -> Option<Vec<(BigInt, Term)>> {
+    if !is_wide(e) && e.t().m().bit_size_in(field) <= bits {
+        if let Ok(x) = e.pure_eval() {
+            return Some(vec![(x, Term::One)]);
+        }
+        return Some(vec![(BigInt::one(), Term::Small(e.clone()))]);
+    }
+
+    match e.e() {
+        Expression::Const(x) => Some(vec![(signed(x, field), Term::One)]),
+        Expression::Column { handle, shift, .. } | Expression::ExoColumn { handle, shift, .. } => {
+            Some(vec![(
+                BigInt::one(),
+                Term::Limbed {
+                    column: handle.clone(),
+                    shift: *shift,
+                },
+            )])
+        }
+        Expression::Funcall { func, args } => match func {
+            Intrinsic::Add | Intrinsic::VectorAdd => {
+                let mut r = Vec::new();
+                for a in args.iter() {
+                    r.extend(linearize(a, bits, field)?);
+                }
+                Some(r)
+            }
+            Intrinsic::Sub | Intrinsic::VectorSub => {
+                let mut r = linearize(&args[0], bits, field)?;
+                for a in args.iter().skip(1) {
+                    r.extend(linearize(a, bits, field)?.into_iter().map(|(c, t)| (-c, t)));
+                }
+                Some(r)
+            }
+            Intrinsic::Neg => Some(
+                linearize(&args[0], bits, field)?
+                    .into_iter()
+                    .map(|(c, t)| (-c, t))
+                    .collect(),
+            ),
+            Intrinsic::Mul | Intrinsic::VectorMul => {
+                // Only products by constants are linear
+                let mut coeff = BigInt::one();
+                let mut variable = None;
+                for a in args.iter() {
+                    match a.e() {
+                        Expression::Const(x) => coeff *= signed(x, field),
+                        _ if variable.is_none() => variable = Some(a),
+                        _ => return None,
+                    }
+                }
+                match variable {
+                    Some(v) => Some(
+                        linearize(v, bits, field)?
+                            .into_iter()
+                            .map(|(c, t)| (c * &coeff, t))
+                            .collect(),
+                    ),
+                    None => Some(vec![(coeff, Term::One)]),
+                }
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Split `e` into the factors not referring to wide columns, and the one
+/// that does
+fn split_guards(e: &Node) -> (Vec<Node>, Node) {
+    if let Expression::Funcall {
+        func: Intrinsic::Mul,
+        args,
+    } = e.e()
+    {
+        let (wide, guards): (Vec<_>, Vec<_>) = args.iter().cloned().partition(is_wide);
+        if wide.len() == 1 {
+            let (mut inner_guards, body) = split_guards(&wide[0]);
+            inner_guards.extend(guards);
+            return (inner_guards, body);
+        }
+    }
+    (vec![], e.clone())
+}
+
+struct Decomposer {
+    bits: usize,
+    limbs: HashMap<ColumnRef, Vec<Node>>,
+}
+impl Decomposer {
+    /// The limbs of `column`, created if they do not exist yet
+    fn limbs_of(&mut self, cs: &mut ConstraintSet, column: &ColumnRef) -> Result<Vec<Node>> {
+        if let Some(limbs) = self.limbs.get(column) {
+            return Ok(limbs.clone());
+        }
+
+        let handle = cs.handle(column).to_owned();
+        let magma = cs.columns.column(column)?.t;
+        let count = (magma.bit_size_in(cs.field) + self.bits - 1) / self.bits;
+        let limb_magma = Magma::integer(self.bits);
+        let mut limbs = Vec::with_capacity(count);
+        for i in 0..count {
+            limbs.push(
+                cs.columns.insert_column_and_register(
+                    Column::builder()
+                        .handle(Handle::new(
+                            &handle.module,
+                            format!("__LIMB__{}_{}", i, handle.name),
+                        ))
+                        .kind(Kind::Computed)
+                        .t(limb_magma)
+                        .base(Base::Hex)
+                        .build(),
+                    cs.field,
+                )?,
+            );
+        }
+        cs.computations.insert_many(
+            &limbs,
+            Computation::Limbs {
+                source: column.clone(),
+                limbs: limbs.clone(),
+                bits: self.bits,
+            },
+        )?;
+
+        let nodes = limbs
+            .iter()
+            .map(|l| {
+                Node::column()
+                    .handle(l.clone())
+                    .kind(Kind::Computed)
+                    .t(limb_magma)
+                    .base(Base::Hex)
+                    .build()
+            })
+            .collect::<Vec<_>>();
+        for (i, limb) in nodes.iter().enumerate() {
+            cs.insert_constraint(Constraint::InRange {
+                handle: Handle::new(&handle.module, format!("{}-limb-{}", handle.name, i)),
+                exp: limb.clone(),
+                max: Value::try_from(BigInt::one() << self.bits).unwrap(),
+            });
+        }
+        // Columns fitting in the field are still used as is elsewhere, and
+        // must match their limbs
+        if magma.bit_size_in(cs.field) <= cs.field.bit_size() {
+            let mut recomposed = vec![];
+            for (i, limb) in nodes.iter().enumerate() {
+                recomposed.push(Intrinsic::Mul.call(&[
+                    Node::from_bigint(BigInt::one() << (i * self.bits)),
+                    limb.clone(),
+                ])?);
+            }
+            cs.insert_constraint(Constraint::Vanishes {
+                handle: Handle::new(&handle.module, format!("{}-limbs", handle.name)),
+                domain: None,
+                expr: Box::new(
+                    Intrinsic::Sub.call(&[
+                        Node::column()
+                            .handle(column.clone())
+                            .kind(Kind::Computed)
+                            .t(magma)
+                            .build(),
+                        Intrinsic::Add.call(&recomposed)?,
+                    ])?,
+                ),
+            });
+        }
+
+        self.limbs.insert(column.clone(), nodes.clone());
+        Ok(nodes)
+    }
+
+    /// Rewrite the vanishing expression `e` of the constraint `handle` into
+    /// limb-wise expressions linked by carries
+    fn decompose(
+        &mut self,
+        cs: &mut ConstraintSet,
+        handle: &Handle,
+        e: &Node,
+    ) -> Result<Vec<Node>> {
+        let (guards, body) = split_guards(e);
+        let terms = linearize(&body, self.bits, cs.field).ok_or_else(|| {
+            anyhow::anyhow!(
+                "{}: only linear combinations of columns wider than the field can be split in limbs",
+                handle.pretty()
+            )
+        })?;
+
+        let base = BigInt::one() << self.bits;
+        let mut sums: Vec<Vec<Node>> = Vec::new();
+        let mut push = |i: usize, x: Node| {
+            if sums.len() <= i {
+                sums.resize_with(i + 1, Vec::new);
+            }
+            sums[i].push(x);
+        };
+        let mut bound = BigInt::zero();
+        let mut constant = BigInt::zero();
+        for (coeff, term) in terms.into_iter() {
+            if coeff.is_zero() {
+                continue;
+            }
+            match term {
+                Term::Limbed { column, shift } => {
+                    // Multiplying by a power of the limb base shifts the limbs
+                    let mut coeff = coeff;
+                    let mut offset = 0;
+                    while coeff.rem_euclid(&base).is_zero() {
+                        coeff /= &base;
+                        offset += 1;
+                    }
+                    bound += coeff.abs();
+                    for (i, limb) in self.limbs_of(cs, &column)?.into_iter().enumerate() {
+                        push(
+                            i + offset,
+                            Intrinsic::Mul.call(&[from_signed(&coeff), limb.shift(shift)])?,
+                        );
+                    }
+                }
+                Term::Small(x) => {
+                    bound += coeff.abs();
+                    push(0, Intrinsic::Mul.call(&[from_signed(&coeff), x])?);
+                }
+                Term::One => constant += coeff,
+            }
+        }
+        if !constant.is_zero() {
+            bound += 1;
+            let sign = if constant.is_negative() { -1 } else { 1 };
+            let mut x = constant.abs();
+            let mut i = 0;
+            while !x.is_zero() {
+                let digit = x.rem_euclid(&base);
+                if !digit.is_zero() {
+                    push(i, from_signed(&(digit * sign)));
+                }
+                x /= &base;
+                i += 1;
+            }
+        }
+        let sums = sums
+            .into_iter()
+            .map(|xs| {
+                if xs.is_empty() {
+                    Ok(Node::zero())
+                } else {
+                    Intrinsic::Add.call(&xs)
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let bound = match bound.to_usize() {
+            Some(b) if b <= MAX_CARRY => b,
+            _ => bail!(
+                "{}: coefficients are too large to be split in limbs",
+                handle.pretty()
+            ),
+        };
+
+        // Carries all live in the module of the constraint
+        let module = cs
+            .columns
+            .module_for(body.dependencies())
+            .unwrap_or_else(|| handle.module.clone());
+        let mut carries = Vec::new();
+        for i in 0..sums.len().saturating_sub(1) {
+            carries.push(
+                cs.columns.insert_column_and_register(
+                    Column::builder()
+                        .handle(Handle::new(
+                            &module,
+                            format!("__CARRY__{}_{}", i, handle.name),
+                        ))
+                        .kind(Kind::Computed)
+                        .t(Magma::native())
+                        .build(),
+                    cs.field,
+                )?,
+            );
+        }
+        let carry_nodes = carries
+            .iter()
+            .map(|c| {
+                Node::column()
+                    .handle(c.clone())
+                    .kind(Kind::Computed)
+                    .build()
+            })
+            .collect::<Vec<_>>();
+        if !carries.is_empty() {
+            cs.computations.insert_many(
+                &carries,
+                Computation::Carries {
+                    sums: sums.clone(),
+                    carries: carries.clone(),
+                    bits: self.bits,
+                    bound,
+                },
+            )?;
+            for (i, carry) in carry_nodes.iter().enumerate() {
+                cs.insert_constraint(Constraint::InRange {
+                    handle: Handle::new(&module, format!("{}-carry-{}", handle.name, i)),
+                    exp: Intrinsic::Add.call(&[carry.clone(), Node::from_isize(bound as isize)])?,
+                    max: Value::from(2 * bound + 1),
+                });
+            }
+        }
+
+        // S_i + C_{i-1} - 2^bits × C_i = 0
+        let mut r = Vec::with_capacity(sums.len());
+        for (i, sum) in sums.into_iter().enumerate() {
+            let mut args = vec![sum];
+            if i > 0 {
+                args.push(carry_nodes[i - 1].clone());
+            }
+            let mut limb = Intrinsic::Add.call(&args)?;
+            if let Some(carry) = carry_nodes.get(i) {
+                limb = Intrinsic::Sub.call(&[
+                    limb,
+                    Intrinsic::Mul.call(&[Node::from_bigint(base.clone()), carry.clone()])?,
+                ])?;
+            }
+            r.push(if guards.is_empty() {
+                limb
+            } else {
+                Intrinsic::Mul.call(&guards.iter().cloned().chain([limb]).collect::<Vec<_>>())?
+            });
+        }
+        Ok(r)
+    }
+
+    /// Pair the limbs of the lookup arguments `a` and `b`
+    fn pair(
+        &mut self,
+        cs: &mut ConstraintSet,
+        a: &Node,
+        b: &Node,
+    ) -> Result<(Vec<Node>, Vec<Node>)> {
+        let field = cs.field;
+        let mut limbs = |x: &Node| -> Result<Vec<Node>> {
+            match x.e() {
+                Expression::Column { handle, shift, .. }
+                | Expression::ExoColumn { handle, shift, .. }
+                    if x.t().m().bit_size_in(field) > self.bits =>
+                {
+                    Ok(self
+                        .limbs_of(cs, handle)?
+                        .into_iter()
+                        .map(|l| l.shift(*shift))
+                        .collect())
+                }
+                _ if !is_wide(x) && x.t().m().bit_size_in(field) <= self.bits => {
+                    Ok(vec![x.clone()])
+                }
+                _ => bail!(
+                    "only columns wider than the field can be split in limbs, found {}",
+                    x.pretty().red()
+                ),
+            }
+        };
+        let mut xs = limbs(a)?;
+        let mut ys = limbs(b)?;
+        let len = xs.len().max(ys.len());
+        xs.resize_with(len, Node::zero);
+        ys.resize_with(len, Node::zero);
+        Ok((xs, ys))
+    }
+}
+
+/// Split the columns wider than the field into limbs small enough for
+/// limb-wise sums to fit in the field, and rewrite the constraints using them
+/// into limb-wise constraints linked by carry columns.
+pub fn limbs(cs: &mut ConstraintSet) -> Result<()> {
+    let mut decomposer = Decomposer {
+        bits: limb_bits(cs.field),
+        limbs: HashMap::new(),
+    };
+
+    for i in 0..cs.constraints.len() {
+        match cs.constraints[i].clone() {
+            Constraint::Vanishes {
+                handle,
+                domain,
+                expr,
+            } => {
+                if !is_wide(&expr) {
+                    continue;
+                }
+                let exprs = match expr.e() {
+                    Expression::List(xs) => xs.clone(),
+                    _ => vec![*expr.clone()],
+                };
+                let mut new_exprs = Vec::new();
+                for e in exprs.iter() {
+                    if is_wide(e) {
+                        new_exprs.extend(decomposer.decompose(cs, &handle, e)?);
+                    } else {
+                        new_exprs.push(e.clone());
+                    }
+                }
+                cs.constraints[i] = Constraint::Vanishes {
+                    handle,
+                    domain,
+                    expr: Box::new(Node::from_expr(Expression::List(new_exprs))),
+                };
+            }
+            Constraint::Lookup {
+                handle,
+                including,
+                included,
+            } => {
+                if !including.iter().chain(included.iter()).any(is_wide) {
+                    continue;
+                }
+                let mut new_including = Vec::new();
+                let mut new_included = Vec::new();
+                for (a, b) in including.iter().zip(included.iter()) {
+                    let (xs, ys) = decomposer.pair(cs, a, b)?;
+                    new_including.extend(xs);
+                    new_included.extend(ys);
+                }
+                cs.constraints[i] = Constraint::Lookup {
+                    handle,
+                    including: new_including,
+                    included: new_included,
+                };
+            }
+            Constraint::Permutation { handle, from, to } => {
+                for c in from.iter().chain(to.iter()) {
+                    if cs.columns.column(c)?.t.bit_size_in(cs.field) > cs.field.bit_size() {
+                        bail!(
+                            "{}: columns wider than the field can not be permuted",
+                            handle.pretty()
+                        )
+                    }
+                }
+            }
+            Constraint::InRange { handle, exp: e, .. }
+            | Constraint::Normalization {
+                handle,
+                reference: e,
+                ..
+            } => {
+                if is_wide(&e) {
+                    bail!(
+                        "{}: columns wider than the field can only be used in vanishing and lookup constraints",
+                        handle.pretty()
+                    )
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -44,6 +44,7 @@ fn process_nhood(
             .kind(Kind::Computed)
             .t(Magma::native())
             .build(),
+        cs.field,
     )?;
     cs.computations.insert(
         &_aux_id,
@@ -63,6 +64,7 @@ fn process_nhood(
             .handle(Handle::new(module, format!("INTRLD_AUX_{modulo}_HOOD")))
             .kind(Kind::Computed)
             .build(),
+        cs.field,
     )?;
     cs.computations.insert(
         &_intrld_aux_xs_id,
@@ -77,6 +79,7 @@ fn process_nhood(
             .handle(Handle::new(module, format!("SRT_INTRLD_AUX_{modulo}_HOOD")))
             .kind(Kind::Computed)
             .build(),
+        cs.field,
     )?;
     cs.computations.insert(
         &srt_intrld_aux_xs_id,
@@ -155,7 +158,7 @@ pub fn validate_nhood(cs: &mut ConstraintSet) -> Result<()> {
                 _ => constrained_columns
                     .entry(c.handle.module.to_owned())
                     .or_default()
-                    .entry(c.t.bit_size() as u32)
+                    .entry(c.t.bit_size_in(cs.field) as u32)
                     .or_default()
                     .push(h.clone()),
             }
@@ -0,0 +1,204 @@
+use anyhow::*;
+use num_traits::Zero;
+use owo_colors::OwoColorize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::{
+    column::RegisterID,
+    compiler::{ColumnRef, Constraint, ConstraintSet, Expression, Node},
+    pretty::Pretty,
+    structs::Handle,
+};
+
+/// What has been removed from a constraint set by [`prune`]
+#[derive(Default)]
+pub(crate) struct PruneReport {
+    /// the removed columns, per module
+    columns: BTreeMap<String, Vec<String>>,
+    registers: usize,
+    computations: usize,
+    constraints: Vec<Handle>,
+}
+impl PruneReport {
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+            && self.registers == 0
+            && self.computations == 0
+            && self.constraints.is_empty()
+    }
+}
+impl std::fmt::Display for PruneReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "pruned {} columns, {} registers, {} computations and {} constraints",
+            self.columns.values().map(Vec::len).sum::<usize>(),
+            self.registers,
+            self.computations,
+            self.constraints.len()
+        )?;
+        for (module, columns) in self.columns.iter() {
+            writeln!(
+                f,
+                "  {} ({}): {}",
+                module.bold(),
+                columns.len(),
+                columns.join(" ")
+            )?;
+        }
+        for c in self.constraints.iter() {
+            writeln!(f, "  {} trivially holds", c.pretty())?;
+        }
+        std::fmt::Result::Ok(())
+    }
+}
+
+/// Whether `e` vanishes whatever the trace
+fn is_trivial(e: &Node) -> bool {
+    match e.e() {
+        Expression::List(xs) => xs.iter().all(is_trivial),
+        _ => e.pure_eval().is_ok_and(|x| x.is_zero()),
+    }
+}
+
+/// Remove the trivially satisfied constraints, then all the columns, registers
+/// and computations that are not required, directly or transitively, by any
+/// constraint or perspective.
+pub(crate) fn prune(cs: &mut ConstraintSet) -> Result<PruneReport> {
+    let mut report = PruneReport::default();
+
+    cs.constraints.retain(|c| match c {
+        Constraint::Vanishes { handle, expr, .. } if is_trivial(expr) => {
+            report.constraints.push(handle.clone());
+            false
+        }
+        _ => true,
+    });
+
+    // Column ID -> ID of the computation filling it
+    let computed_by = cs
+        .computations
+        .dependencies
+        .iter()
+        .map(|(target, i)| (cs.columns.id_of(target), *i))
+        .collect::<HashMap<_, _>>();
+
+    let mut todo = cs
+        .constraints
+        .iter()
+        .flat_map(Constraint::dependencies)
+        .chain(
+            cs.perspectives
+                .values()
+                .flat_map(|ps| ps.values())
+                .flat_map(|p| p.dependencies()),
+        )
+        .map(|c| cs.columns.id_of(&c))
+        .collect::<Vec<_>>();
+    let mut live_columns = HashSet::new();
+    let mut live_computations = HashSet::new();
+    while let Some(c) = todo.pop() {
+        if !live_columns.insert(c) {
+            continue;
+        }
+        if let Some(&i) = computed_by.get(&c) {
+            if live_computations.insert(i) {
+                todo.extend(
+                    cs.computations.computations[i]
+                        .dependencies()
+                        .iter()
+                        .map(|c| cs.columns.id_of(c)),
+                );
+            }
+        }
+    }
+
+    if live_columns.len() == cs.columns._cols.len()
+        && live_computations.len() == cs.computations.computations.len()
+    {
+        return Ok(report);
+    }
+
+    // Old column ID -> new column ID
+    let mut new_ids = HashMap::new();
+    // Old register ID -> new register ID
+    let mut new_registers = HashMap::<RegisterID, RegisterID>::new();
+    let old_ids = std::mem::take(&mut cs.columns.cols);
+    let mut old_registers = std::mem::take(&mut cs.columns.registers)
+        .into_iter()
+        .map(Some)
+        .collect::<Vec<_>>();
+    for (i, mut column) in std::mem::take(&mut cs.columns._cols)
+        .into_iter()
+        .enumerate()
+    {
+        if live_columns.contains(&i) {
+            if let Some(r) = column.register {
+                column.register = Some(*new_registers.entry(r).or_insert_with(|| {
+                    cs.columns.registers.push(old_registers[r].take().unwrap());
+                    cs.columns.registers.len() - 1
+                }));
+            }
+            new_ids.insert(i, cs.columns._cols.len());
+            cs.columns
+                .cols
+                .insert(column.handle.clone(), cs.columns._cols.len());
+            cs.columns._cols.push(column);
+        } else {
+            report
+                .columns
+                .entry(column.handle.module.clone())
+                .or_default()
+                .push(column.handle.name.clone());
+        }
+    }
+    report.registers = old_registers.len() - cs.columns.registers.len();
+    report.columns.values_mut().for_each(|cols| cols.sort());
+
+    // Old computation ID -> new computation ID
+    let mut new_computations = HashMap::new();
+    for (i, computation) in std::mem::take(&mut cs.computations.computations)
+        .into_iter()
+        .enumerate()
+    {
+        if live_computations.contains(&i) {
+            new_computations.insert(i, cs.computations.computations.len());
+            cs.computations.computations.push(computation);
+        } else {
+            report.computations += 1;
+        }
+    }
+
+    let renumber = |r: &mut ColumnRef| {
+        let old = if r.is_id() {
+            r.as_id()
+        } else {
+            old_ids[r.as_handle()]
+        };
+        let new = ColumnRef::from_id(new_ids[&old]);
+        *r = if r.is_handle() {
+            new.handle(r.as_handle().clone())
+        } else {
+            new
+        };
+    };
+    cs.computations.dependencies = std::mem::take(&mut cs.computations.dependencies)
+        .into_iter()
+        .filter_map(|(mut target, i)| {
+            let i = *new_computations.get(&i)?;
+            renumber(&mut target);
+            Some((target, i))
+        })
+        .collect();
+    for c in cs.constraints.iter_mut() {
+        c.add_id_to_handles(&renumber);
+    }
+    for c in cs.computations.iter_mut() {
+        c.add_id_to_handles(&renumber);
+    }
+    for p in cs.perspectives.values_mut().flat_map(|ps| ps.values_mut()) {
+        p.add_id_to_handles(&renumber);
+    }
+
+    Ok(report)
+}
@@ -1,6 +1,11 @@
+use std::collections::HashMap;
+
 use crate::{
     column::{Column, ColumnSet, Computation},
-    compiler::{ComputationTable, Constraint, ConstraintSet, Expression, Kind, Magma, Node},
+    compiler::{
+        ColumnRef, ComputationTable, Constraint, ConstraintSet, Expression, Kind, Magma, Node,
+    },
+    field::Field,
     pretty::Base,
     structs::Handle,
 };
@@ -8,43 +13,67 @@ use anyhow::*;
 
 use super::{expression_to_name, validate_computation};
 
-fn do_expand_expr(
+/// Map the expressions already computed into columns to these columns
+pub(super) fn columnized(comps: &ComputationTable) -> HashMap<Node, ColumnRef> {
+    comps
+        .iter()
+        .filter_map(|c| match c {
+            Computation::Composite { target, exp } => Some((exp.clone(), target.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+pub(super) fn do_expand_expr(
     e: &Node,
     module: &str,
     cols: &mut ColumnSet,
     comps: &mut ComputationTable,
+    columnized: &mut HashMap<Node, ColumnRef>,
     new_cs: &mut Vec<Node>,
+    field: Field,
 ) -> Result<Node> {
     match e.e() {
         Expression::Column { .. } | Expression::ExoColumn { .. } => Ok(e.clone()),
         _ => {
-            let module = cols
-                .module_for(e.dependencies())
-                .unwrap_or(module.to_owned());
-            let new_handle = Handle::new(module, expression_to_name(e, "#EXPAND"));
-            // TODO: replace name with exprs hash to 100% ensure bijectivity handle/expression
-            // Only insert the computation if a column matching the expression has not already been created
-            if cols
-                .insert_column_and_register(
+            // Structurally identical expressions share the same column
+            let target = if let Some(target) = columnized.get(e) {
+                target.clone()
+            } else {
+                let module = cols
+                    .module_for(e.dependencies())
+                    .unwrap_or(module.to_owned());
+                // Distinct expressions may be rendered identically, e.g. when
+                // referencing homonymous columns from different modules
+                let name = expression_to_name(e, "#EXPAND");
+                let mut new_handle = Handle::new(&module, &name);
+                let mut i = 1;
+                while cols.by_handle(&new_handle).is_ok() {
+                    new_handle = Handle::new(&module, format!("{}#{}", name, i));
+                    i += 1;
+                }
+                cols.insert_column_and_register(
                     Column::builder()
                         .handle(new_handle.clone())
                         .kind(Kind::Computed)
                         .build(),
-                )
-                .is_ok()
-            {
+                    field,
+                )?;
+                let target: ColumnRef = new_handle.clone().into();
                 validate_computation(new_cs, e, &new_handle);
-                let _ = comps.insert(
-                    &new_handle.clone().into(),
+                comps.insert(
+                    &target,
                     Computation::Composite {
-                        target: new_handle.clone().into(),
+                        target: target.clone(),
                         exp: e.clone(),
                     },
-                );
-            }
+                )?;
+                columnized.insert(e.clone(), target.clone());
+                target
+            };
 
             Ok(Node::column()
-                .handle(new_handle)
+                .handle(target)
                 .kind(Kind::Computed)
                 .base(Base::Dec)
                 .t(Magma::native())
@@ -55,6 +84,7 @@ fn do_expand_expr(
 
 pub fn expand_constraints(cs: &mut ConstraintSet) -> Result<()> {
     let mut new_cs_exps = vec![];
+    let mut columnized = columnized(&cs.computations);
     for c in cs.constraints.iter_mut() {
         match c {
             Constraint::Lookup {
@@ -68,7 +98,9 @@ pub fn expand_constraints(cs: &mut ConstraintSet) -> Result<()> {
                         &handle.module,
                         &mut cs.columns,
                         &mut cs.computations,
+                        &mut columnized,
                         &mut new_cs_exps,
+                        cs.field,
                     )?;
                 }
             }
@@ -82,7 +114,9 @@ pub fn expand_constraints(cs: &mut ConstraintSet) -> Result<()> {
                     &handle.module,
                     &mut cs.columns,
                     &mut cs.computations,
+                    &mut columnized,
                     &mut new_cs_exps,
+                    cs.field,
                 )?;
             }
             _ => (),
@@ -0,0 +1,329 @@
+use num_bigint::BigInt;
+use num_traits::{Euclid, One, Signed, ToPrimitive, Zero};
+use std::collections::HashMap;
+
+use crate::{
+    compiler::{Constraint, ConstraintSet, Expression, Intrinsic, Node},
+    constants,
+    field::Field,
+};
+
+use super::{flatten_list, from_signed, signed};
+
+/// Coefficients are kept as small as possible in the field when computing
+/// natively, but must be kept exact otherwise
+fn normalize(x: BigInt, field: Field) -> BigInt {
+    if *crate::IS_NATIVE.read().unwrap() || !field.is_ark() {
+        let modulus = field.modulus();
+        let x = x.rem_euclid(modulus);
+        if x > modulus >> 1 {
+            x - modulus
+        } else {
+            x
+        }
+    } else {
+        x
+    }
+}
+
+/// A linear combination of monomials, plus a constant, over `field`
+struct Sum {
+    terms: Vec<(Node, BigInt)>,
+    index: HashMap<Node, usize>,
+    constant: BigInt,
+    field: Field,
+}
+impl Sum {
+    fn new(field: Field) -> Sum {
+        Sum::constant(BigInt::zero(), field)
+    }
+
+    fn constant(x: BigInt, field: Field) -> Sum {
+        Sum {
+            terms: Vec::new(),
+            index: HashMap::new(),
+            constant: x,
+            field,
+        }
+    }
+
+    fn monomial(x: Node, field: Field) -> Sum {
+        let mut r = Sum::new(field);
+        r.add_term(x, BigInt::one());
+        r
+    }
+
+    fn add_term(&mut self, x: Node, coeff: BigInt) {
+        if let Some(&i) = self.index.get(&x) {
+            self.terms[i].1 = normalize(&self.terms[i].1 + coeff, self.field);
+        } else {
+            self.index.insert(x.clone(), self.terms.len());
+            self.terms.push((x, coeff));
+        }
+    }
+
+    /// self += factor × other
+    fn add(&mut self, other: Sum, factor: &BigInt) {
+        for (x, coeff) in other.terms.into_iter() {
+            self.add_term(x, normalize(coeff * factor, self.field));
+        }
+        self.constant = normalize(&self.constant + other.constant * factor, self.field);
+    }
+
+    fn scaled(self, factor: &BigInt) -> Sum {
+        let mut r = Sum::new(self.field);
+        r.add(self, factor);
+        r
+    }
+
+    fn as_constant(&self) -> Option<&BigInt> {
+        if self.terms.iter().all(|(_, coeff)| coeff.is_zero()) {
+            Some(&self.constant)
+        } else {
+            None
+        }
+    }
+
+    /// If this sum is c × x, return (x, c)
+    fn as_monomial(&self) -> Option<(&Node, &BigInt)> {
+        let mut terms = self.terms.iter().filter(|(_, coeff)| !coeff.is_zero());
+        match (terms.next(), terms.next()) {
+            (Some((x, coeff)), None) if self.constant.is_zero() => Some((x, coeff)),
+            _ => None,
+        }
+    }
+
+    /// Convert this sum back to an expression, or `None` if its
+    /// coefficients are too large to be represented
+    fn into_node(self) -> Option<Node> {
+        let too_large = |x: &BigInt| x.bits() as usize > constants::MAX_BIT_SIZE;
+        if too_large(&self.constant) || self.terms.iter().any(|(_, c)| too_large(c)) {
+            return None;
+        }
+
+        let scaled = |x: Node, coeff: BigInt| -> Node {
+            if coeff.is_one() {
+                x
+            } else {
+                let mut factors = vec![Node::from_bigint(coeff)];
+                match x.e() {
+                    Expression::Funcall {
+                        func: Intrinsic::Mul,
+                        args,
+                    } => factors.extend(args.iter().cloned()),
+                    _ => factors.push(x),
+                }
+                Intrinsic::Mul.call(&factors).unwrap()
+            }
+        };
+        let mut positives = Vec::new();
+        let mut negatives = Vec::new();
+        for (x, coeff) in self.terms.into_iter() {
+            if coeff.is_positive() {
+                positives.push(scaled(x, coeff));
+            } else if coeff.is_negative() {
+                negatives.push(scaled(x, -coeff));
+            }
+        }
+        if self.constant.is_positive() {
+            positives.push(from_signed(&self.constant));
+        } else if self.constant.is_negative() {
+            negatives.push(from_signed(&-self.constant));
+        }
+
+        let sum = |mut xs: Vec<Node>| {
+            if xs.len() == 1 {
+                xs.pop().unwrap()
+            } else {
+                Intrinsic::Add.call(&xs).unwrap()
+            }
+        };
+        Some(match (positives.is_empty(), negatives.is_empty()) {
+            (true, true) => Node::zero(),
+            (false, true) => sum(positives),
+            (true, false) => Intrinsic::Neg.call(&[sum(negatives)]).unwrap(),
+            (false, false) => {
+                let mut args = vec![sum(positives)];
+                args.extend(negatives);
+                Intrinsic::Sub.call(&args).unwrap()
+            }
+        })
+    }
+}
+
+/// Write the arithmetic expression `e` as a sum of monomials
+fn linearize(e: &Node, field: Field) -> Sum {
+    match e.e() {
+        Expression::Const(x) => Sum::constant(normalize(signed(x, field), field), field),
+        Expression::Funcall { func, args } => match func {
+            Intrinsic::Add => {
+                let mut r = Sum::new(field);
+                for a in args.iter() {
+                    r.add(linearize(a, field), &BigInt::one());
+                }
+                r
+            }
+            Intrinsic::Sub => {
+                let mut r = linearize(&args[0], field);
+                for a in args.iter().skip(1) {
+                    r.add(linearize(a, field), &-BigInt::one());
+                }
+                r
+            }
+            Intrinsic::Neg => linearize(&args[0], field).scaled(&-BigInt::one()),
+            Intrinsic::Mul => {
+                let mut coeff = BigInt::one();
+                let mut factors = Vec::new();
+                for a in args.iter() {
+                    let a = linearize(a, field);
+                    if let Some(c) = a.as_constant() {
+                        coeff = normalize(coeff * c, field);
+                    } else if let Some((x, c)) = a.as_monomial() {
+                        coeff = normalize(coeff * c, field);
+                        match x.e() {
+                            Expression::Funcall {
+                                func: Intrinsic::Mul,
+                                args,
+                            } => {
+                                factors.extend(args.iter().map(|x| Sum::monomial(x.clone(), field)))
+                            }
+                            _ => factors.push(Sum::monomial(x.clone(), field)),
+                        }
+                    } else {
+                        factors.push(a);
+                    }
+                }
+                if coeff.is_zero() || factors.is_empty() {
+                    Sum::constant(coeff, field)
+                } else if factors.len() == 1 {
+                    factors.pop().unwrap().scaled(&coeff)
+                } else {
+                    match factors
+                        .into_iter()
+                        .map(Sum::into_node)
+                        .collect::<Option<Vec<_>>>()
+                    {
+                        Some(factors) => {
+                            Sum::monomial(Intrinsic::Mul.call(&factors).unwrap(), field)
+                                .scaled(&coeff)
+                        }
+                        None => Sum::monomial(e.clone(), field),
+                    }
+                }
+            }
+            Intrinsic::Exp => match args[1].pure_eval().ok().and_then(|x| x.to_usize()) {
+                Some(0) => Sum::constant(BigInt::one(), field),
+                Some(1) => linearize(&args[0], field),
+                _ => Sum::monomial(simplify(e, field), field),
+            },
+            Intrinsic::Normalize | Intrinsic::Inv => {
+                let x = simplify(&args[0], field);
+                match x.pure_eval() {
+                    Ok(c) if normalize(c.clone(), field).is_zero() => {
+                        Sum::constant(BigInt::zero(), field)
+                    }
+                    Ok(_) if matches!(func, Intrinsic::Normalize) => {
+                        Sum::constant(BigInt::one(), field)
+                    }
+                    _ => Sum::monomial(func.call(&[x]).unwrap(), field),
+                }
+            }
+            _ => Sum::monomial(simplify(e, field), field),
+        },
+        _ => Sum::monomial(e.clone(), field),
+    }
+}
+
+/// Simplify `e`, by folding constants, flattening sums and products,
+/// cancelling opposite terms, distributing constants, and collapsing
+/// conditionals on constant conditions.
+pub(crate) fn simplify(e: &Node, field: Field) -> Node {
+    let r = match e.e() {
+        Expression::Funcall { func, args } => match func {
+            Intrinsic::Add | Intrinsic::Sub | Intrinsic::Neg | Intrinsic::Mul | Intrinsic::Exp
+                if !e.leaves().iter().any(|l| l.is_exocolumn()) =>
+            {
+                let simplified = match func {
+                    Intrinsic::Exp => match args[1].pure_eval().ok().and_then(|x| x.to_usize()) {
+                        Some(0) | Some(1) => linearize(e, field).into_node(),
+                        _ => Some(
+                            Intrinsic::Exp
+                                .call(&[simplify(&args[0], field), args[1].clone()])
+                                .unwrap(),
+                        ),
+                    },
+                    _ => linearize(e, field).into_node(),
+                };
+                simplified.unwrap_or_else(|| e.clone())
+            }
+            Intrinsic::IfZero | Intrinsic::IfNotZero => {
+                let cond = simplify(&args[0], field);
+                match cond.pure_eval() {
+                    Ok(x) => {
+                        let x = normalize(x, field);
+                        if x.is_zero() == matches!(func, Intrinsic::IfZero) {
+                            simplify(&args[1], field)
+                        } else {
+                            flatten_list(
+                                args.get(2)
+                                    .map(|x| simplify(x, field))
+                                    .unwrap_or_else(Node::zero),
+                            )
+                        }
+                    }
+                    Err(_) => {
+                        let mut new_args = vec![cond];
+                        new_args.extend(args.iter().skip(1).map(|x| simplify(x, field)));
+                        func.call(&new_args).unwrap_or_else(|_| e.clone())
+                    }
+                }
+            }
+            _ => func
+                .call(&args.iter().map(|x| simplify(x, field)).collect::<Vec<_>>())
+                .unwrap_or_else(|_| e.clone()),
+        },
+        Expression::List(xs) => {
+            // Constraints trivially vanishing can be dropped
+            let mut xs = xs
+                .iter()
+                .map(|x| simplify(x, field))
+                .filter(|x| !x.pure_eval().is_ok_and(|x| normalize(x, field).is_zero()))
+                .collect::<Vec<_>>();
+            if xs.is_empty() {
+                xs.push(Node::zero());
+            }
+            Node::from_expr(Expression::List(xs))
+        }
+        _ => e.clone(),
+    };
+
+    // Keep the original node, and its type and debug information, whenever
+    // possible
+    if r == *e {
+        e.clone()
+    } else {
+        r.with_debug(e.dbg().cloned())
+    }
+}
+
+/// Simplify the expressions of all the constraints in `cs`.
+pub fn simplify_constraints(cs: &mut ConstraintSet) {
+    let field = cs.field;
+    for c in cs.constraints.iter_mut() {
+        match c {
+            Constraint::Vanishes { expr, .. } => **expr = simplify(expr, field),
+            Constraint::Lookup {
+                including,
+                included,
+                ..
+            } => {
+                for e in including.iter_mut().chain(included.iter_mut()) {
+                    *e = simplify(e, field);
+                }
+            }
+            Constraint::InRange { exp, .. } => *exp = simplify(exp, field),
+            Constraint::Normalization { reference, .. } => *reference = simplify(reference, field),
+            Constraint::Permutation { .. } => {}
+        }
+    }
+}
@@ -55,6 +55,7 @@ fn create_sort_constraint(
                     .t(Magma::binary())
                     .intrinsic_size_factor(size)
                     .build(),
+                cs.field,
             )
         })
         .collect::<Result<Vec<_>>>()?;
@@ -67,6 +68,7 @@ fn create_sort_constraint(
             .kind(Kind::Computed)
             .padding_value(1)
             .build(),
+        cs.field,
     )?;
     let delta = cs.columns.insert_column_and_register(
         Column::builder()
@@ -75,6 +77,7 @@ fn create_sort_constraint(
             .intrinsic_size_factor(cs.length_multiplier(&froms[0]))
             .base(Base::Hex)
             .build(),
+        cs.field,
     )?;
     let delta_bytes = (0..16)
         .map(|i| {
@@ -86,6 +89,7 @@ fn create_sort_constraint(
                     .base(Base::Hex)
                     .intrinsic_size_factor(cs.length_multiplier(&froms[0]))
                     .build(),
+                cs.field,
             )
         })
         .collect::<Result<Vec<_>>>()?;
@@ -191,10 +195,15 @@ fn create_sort_constraint(
                     ])?,
                     // => sorted_i = sorted_i[-1]
                     Intrinsic::Sub.call(&[
-                        Node::column().handle(sorted[i].clone()).t(sorted_t).build(),
                         Node::column()
                             .handle(sorted[i].clone())
                             .t(sorted_t)
+                            .field(cs.field)
+                            .build(),
+                        Node::column()
+                            .handle(sorted[i].clone())
+                            .t(sorted_t)
+                            .field(cs.field)
                             .shift(-1)
                             .build(),
                     ])?,
@@ -212,10 +221,15 @@ fn create_sort_constraint(
                 // => sorted_i ≠ sorted_i[-1]
                 {
                     let diff = Intrinsic::Sub.call(&[
-                        Node::column().handle(sorted[i].clone()).t(sorted_t).build(),
                         Node::column()
                             .handle(sorted[i].clone())
                             .t(sorted_t)
+                            .field(cs.field)
+                            .build(),
+                        Node::column()
+                            .handle(sorted[i].clone())
+                            .t(sorted_t)
+                            .field(cs.field)
                             .shift(-1)
                             .build(),
                     ])?;
@@ -271,10 +285,12 @@ fn create_sort_constraint(
                                     Node::column()
                                         .handle(sorted[l].clone())
                                         .t(sorted_l_t)
+                                        .field(cs.field)
                                         .build(),
                                     Node::column()
                                         .handle(sorted[l].clone())
                                         .t(sorted_l_t)
+                                        .field(cs.field)
                                         .shift(-1)
                                         .build(),
                                 ])?;
@@ -4,6 +4,7 @@ use crate::{
     column::{Column, Computation, ExoOperation},
     compiler::generator::{ADDER_MODULE, MULER_MODULE},
     compiler::{Constraint, Expression, Intrinsic, Kind, Magma, Node},
+    field::Field,
     pretty::Base,
     structs::Handle,
     ConstraintSet,
@@ -91,11 +92,12 @@ impl Node {
         ancillaries: &mut ProtoAncillaries,
         exo_op_columns: &mut Vec<(ExoOperation, (Handle, Magma), (Node, Node))>,
         new_constants: &mut Vec<(Column, Computation)>,
+        field: Field,
     ) {
         match self.e_mut() {
             Expression::Funcall { func, args } => {
                 for arg in args.iter_mut() {
-                    arg.do_splatter(module, ancillaries, exo_op_columns, new_constants);
+                    arg.do_splatter(module, ancillaries, exo_op_columns, new_constants, field);
                 }
 
                 if args.iter().any(|a| a.is_exocolumn()) {
@@ -113,7 +115,7 @@ impl Node {
 
                             let op = (*func).into();
                             let new_magma = args.iter().map(|a| a.t().m()).max().unwrap();
-                            ancillaries.update_width(op, new_magma.bit_size());
+                            ancillaries.update_width(op, new_magma.bit_size_in(field));
                             let new_handle =
                                 Handle::new(module, format!("{}{}{}", args[0], op, args[1]));
 
@@ -125,6 +127,7 @@ impl Node {
                             *self = Node::column()
                                 .handle(new_handle)
                                 .t(new_magma)
+                                .field(field)
                                 .base(Base::Hex)
                                 .kind(Kind::Computed)
                                 .build();
@@ -143,7 +146,7 @@ impl Node {
             Expression::ArrayColumn { .. } => todo!(),
             Expression::List(ls) => {
                 for l in ls.iter_mut() {
-                    l.do_splatter(module, ancillaries, exo_op_columns, new_constants);
+                    l.do_splatter(module, ancillaries, exo_op_columns, new_constants, field);
                 }
             }
 
@@ -162,6 +165,7 @@ impl ConstraintSet {
                     .handle(Handle::new(ADDER_MODULE, "op"))
                     .base(Base::Hex)
                     .build(),
+                self.field,
             )
             .unwrap();
         self.columns
@@ -171,6 +175,7 @@ impl ConstraintSet {
                     .handle(Handle::new(ADDER_MODULE, "arg-1"))
                     .base(Base::Hex)
                     .build(),
+                self.field,
             )
             .unwrap();
         self.columns
@@ -180,6 +185,7 @@ impl ConstraintSet {
                     .handle(Handle::new(ADDER_MODULE, "arg-2"))
                     .base(Base::Hex)
                     .build(),
+                self.field,
             )
             .unwrap();
         self.columns
@@ -189,6 +195,7 @@ impl ConstraintSet {
                     .handle(Handle::new(ADDER_MODULE, "result"))
                     .base(Base::Hex)
                     .build(),
+                self.field,
             )
             .unwrap();
         self.columns
@@ -198,6 +205,7 @@ impl ConstraintSet {
                     .handle(Handle::new(ADDER_MODULE, "done"))
                     .kind(Kind::Computed)
                     .build(),
+                self.field,
             )
             .unwrap();
 
@@ -208,6 +216,7 @@ impl ConstraintSet {
                     .handle(Handle::new(MULER_MODULE, "arg-1"))
                     .base(Base::Hex)
                     .build(),
+                self.field,
             )
             .unwrap();
         self.columns
@@ -217,6 +226,7 @@ impl ConstraintSet {
                     .handle(Handle::new(MULER_MODULE, "arg-2"))
                     .base(Base::Hex)
                     .build(),
+                self.field,
             )
             .unwrap();
         self.columns
@@ -226,6 +236,7 @@ impl ConstraintSet {
                     .handle(Handle::new(MULER_MODULE, "result"))
                     .base(Base::Hex)
                     .build(),
+                self.field,
             )
             .unwrap();
         self.columns
@@ -235,6 +246,7 @@ impl ConstraintSet {
                     .handle(Handle::new(MULER_MODULE, "done"))
                     .kind(Kind::Computed)
                     .build(),
+                self.field,
             )
             .unwrap();
         self.compute_spilling(ADDER_MODULE);
@@ -255,6 +267,7 @@ impl ConstraintSet {
                         &mut ancillaries,
                         &mut new_exo_columns,
                         &mut new_constants,
+                        self.field,
                     );
                 }
             }
@@ -263,7 +276,10 @@ impl ConstraintSet {
         // self.make_ancillaries(ancillaries);
 
         for (new_column, new_computation) in new_constants {
-            let id = self.columns.insert_column_and_register(new_column).unwrap();
+            let id = self
+                .columns
+                .insert_column_and_register(new_column, self.field)
+                .unwrap();
             self.computations.insert(&id, new_computation).unwrap();
         }
 
@@ -279,6 +295,7 @@ impl ConstraintSet {
                             .base(Base::Hex)
                             .kind(Kind::Computed)
                             .build(),
+                        self.field,
                     )
                     .unwrap();
                 self.computations
@@ -96,3 +96,11 @@ pub fn hash_strings<S: ToString, I: Iterator<Item = S>>(xs: I) -> String {
     s.truncate(6);
     s
 }
+
+/// Remove the ANSI escape sequences, e.g. colors, from a string
+pub fn strip_ansi(s: &str) -> String {
+    lazy_static::lazy_static! {
+        static ref ANSI: regex_lite::Regex = regex_lite::Regex::new("\x1b\\[[0-9;]*m").unwrap();
+    }
+    ANSI.replace_all(s, "").to_string()
+}
@@ -0,0 +1,19 @@
+use corset::{Corset, TraceSource};
+
+#[test]
+fn compute_and_check() {
+    let mut corset = Corset::compile(&[("iszero.lisp", include_str!("iszero.lisp"))]).unwrap();
+
+    let accepted = br#"{ "<prelude>": {"A": [1, 0], "B": [2, 0]} }"#;
+    let report = corset.check(TraceSource::Json(accepted)).unwrap();
+    assert!(report.is_success());
+
+    let trace = corset.compute(TraceSource::Json(accepted)).unwrap();
+    let a = trace.column(&trace.column_names()[0]).unwrap();
+    assert!(!a.is_empty());
+
+    let rejected = br#"{ "<prelude>": {"A": [1, 0], "B": [0, 2]} }"#;
+    let report = corset.check(TraceSource::Json(rejected)).unwrap();
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(report.failures[0].handle.name, "test1");
+}
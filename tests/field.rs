@@ -0,0 +1,22 @@
+use corset::{Corset, Field, TraceSource};
+
+#[test]
+fn goldilocks_wraps_around() {
+    let sources = [(
+        "square.lisp",
+        "(defcolumns A B) (defconstraint square () (vanishes! (- B (* A A))))",
+    )];
+    // (2^32)^2 = 2^32 - 1 in the Goldilocks field
+    let trace = br#"{ "<prelude>": {"A": ["4294967296"], "B": ["4294967295"]} }"#;
+
+    let mut bls = Corset::compile(&sources).unwrap();
+    assert!(!bls.check(TraceSource::Json(trace)).unwrap().is_success());
+
+    let goldilocks = Corset::compile_for(&sources, Field::Goldilocks).unwrap();
+    let mut goldilocks = Corset::from_bin(&goldilocks.to_bin().unwrap()).unwrap();
+    assert_eq!(goldilocks.field(), Field::Goldilocks);
+    assert!(goldilocks
+        .check(TraceSource::Json(trace))
+        .unwrap()
+        .is_success());
+}
@@ -0,0 +1,26 @@
+use corset::{Corset, Field, TraceSource};
+
+#[test]
+fn wide_addition_over_goldilocks() {
+    let sources = [(
+        "add.lisp",
+        "(defcolumns (A :i256) (B :i256) (C :i256)) (defconstraint add () (vanishes! (- C (+ A B))))",
+    )];
+    let mut corset = Corset::compile_for(&sources, Field::Goldilocks).unwrap();
+
+    // (2^255 + 2^64 - 1) + (2^64 + 1) = 2^255 + 2^65
+    let good = br#"{ "<prelude>": {
+        "A": ["57896044618658097711785492504343953926634992332820282019747238748030274371583"],
+        "B": ["18446744073709551617"],
+        "C": ["57896044618658097711785492504343953926634992332820282019765685492103983923200"]
+    } }"#;
+    assert!(corset.check(TraceSource::Json(good)).unwrap().is_success());
+
+    // Off by 2^128, which can not be caught modulo the field
+    let bad = br#"{ "<prelude>": {
+        "A": ["57896044618658097711785492504343953926634992332820282019747238748030274371583"],
+        "B": ["18446744073709551617"],
+        "C": ["57896044618658097711785492504343953926975274699741220483229060099535752134656"]
+    } }"#;
+    assert!(!corset.check(TraceSource::Json(bad)).unwrap().is_success());
+}